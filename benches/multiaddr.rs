@@ -0,0 +1,54 @@
+#[macro_use]
+extern crate criterion;
+extern crate rust_multiaddr;
+
+use criterion::{black_box, Criterion};
+use rust_multiaddr::Multiaddr;
+use std::str::FromStr;
+
+fn bench_from_str(c: &mut Criterion) {
+    c.bench_function("from_str ip4/tcp", |b| {
+        b.iter(|| Multiaddr::from_str(black_box("/ip4/127.0.0.1/tcp/4001")).unwrap())
+    });
+    c.bench_function("from_str ip6/tcp", |b| {
+        b.iter(|| Multiaddr::from_str(black_box("/ip6/2601:9:4f81:9700:803e:ca65:66e8:c21/tcp/4001")).unwrap())
+    });
+}
+
+fn bench_peer_list(c: &mut Criterion) {
+    let addrs: Vec<String> = (0..1000u32)
+        .map(|i| match i % 3 {
+            0 => format!("/ip4/10.0.{}.{}/tcp/{}", i / 256, i % 256, 4000 + i),
+            1 => format!("/ip6/2601:9:4f81:9700:803e:ca65:66e8:{:x}/udp/{}", i, 4000 + i),
+            _ => format!("/ip4/10.0.{}.{}/tcp/4001/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC",
+                         i / 256, i % 256),
+        })
+        .collect();
+    c.bench_function("from_str 1000 peers", |b| {
+        b.iter(|| {
+            for addr in black_box(&addrs) {
+                Multiaddr::from_str(addr).unwrap();
+            }
+        })
+    });
+}
+
+fn bench_from_bytes(c: &mut Criterion) {
+    let bytes = Multiaddr::from_str("/ip4/127.0.0.1/tcp/4001").unwrap().as_bytes().to_vec();
+    c.bench_function("from_bytes ip4/tcp", |b| {
+        b.iter(|| Multiaddr::from_bytes(black_box(bytes.clone())).unwrap())
+    });
+}
+
+fn bench_clone(c: &mut Criterion) {
+    let ma = Multiaddr::from_str("/ip4/127.0.0.1/tcp/4001").unwrap();
+    c.bench_function("clone ip4/tcp", |b| b.iter(|| black_box(&ma).clone()));
+
+    let addrs: Vec<_> = (0..1000u32)
+        .map(|i| Multiaddr::from_str(&format!("/ip4/10.0.{}.{}/tcp/{}", i / 256, i % 256, i)).unwrap())
+        .collect();
+    c.bench_function("clone 1000 ip4/tcp", |b| b.iter(|| black_box(&addrs).clone()));
+}
+
+criterion_group!(benches, bench_from_str, bench_peer_list, bench_from_bytes, bench_clone);
+criterion_main!(benches);
@@ -0,0 +1,73 @@
+// Generates the protocol table in src/protocol.rs from protocols.csv, a
+// snapshot of the table in the multiaddr spec. Adding a protocol only
+// requires adding a line to the csv.
+//
+// Some groups of protocols can be compiled out by disabling a feature; see
+// feature_for below.
+
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let csv_path = Path::new(&manifest_dir).join("protocols.csv");
+    println!("cargo:rerun-if-changed={}", csv_path.display());
+
+    let mut csv = String::new();
+    File::open(&csv_path)
+        .and_then(|mut f| f.read_to_string(&mut csv))
+        .unwrap_or_else(|e| panic!("Error reading {}: {}", csv_path.display(), e));
+
+    let mut out = String::from("protocols! {\n");
+    // The first line is the header
+    for (i, line) in csv.lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<_> = line.split(',').map(|f| f.trim()).collect();
+        if fields.len() < 3 {
+            panic!("protocols.csv:{}: expected code, size, name", i + 1);
+        }
+
+        let code: u32 = fields[0].parse().unwrap_or_else(|e| {
+            panic!("protocols.csv:{}: invalid code {}: {}", i + 1, fields[0], e)
+        });
+        let size = match fields[1] {
+            "V" => "Size::Variable".to_string(),
+            bits => {
+                let bits: u32 = bits.parse().unwrap_or_else(|e| {
+                    panic!("protocols.csv:{}: invalid size {}: {}", i + 1, bits, e)
+                });
+                format!("Size::Fixed({})", bits)
+            }
+        };
+        let name = fields[2];
+        if let Some(feature) = feature_for(name) {
+            let var = format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"));
+            if env::var_os(var).is_none() {
+                continue;
+            }
+        }
+        let variant = name.to_uppercase().replace('-', "_");
+
+        out.push_str(&format!("    {} = {}, \"{}\", {};\n", variant, code, name, size));
+    }
+    out.push_str("}\n");
+
+    let out_path = Path::new(&env::var("OUT_DIR").unwrap()).join("protocols.rs");
+    File::create(&out_path)
+        .and_then(|mut f| f.write_all(out.as_bytes()))
+        .unwrap_or_else(|e| panic!("Error writing {}: {}", out_path.display(), e));
+}
+
+// The feature a protocol is gated behind, if any
+fn feature_for(name: &str) -> Option<&'static str> {
+    match name {
+        "dns" | "dns4" | "dns6" | "dnsaddr" => Some("proto-dns"),
+        "onion" | "onion3" => Some("proto-onion"),
+        _ => None,
+    }
+}
@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_multiaddr::Multiaddr;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(ma) = Multiaddr::try_from(data) {
+        assert_eq!(ma.as_bytes(), data);
+        // Not every valid binary address has a textual form (e.g. onion),
+        // but those that do must parse back to the same bytes.
+        if let Ok(parsed) = Multiaddr::from_str(&ma.to_string()) {
+            assert_eq!(parsed, ma);
+        }
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_multiaddr::Multiaddr;
+use std::str::FromStr;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Ok(ma) = Multiaddr::from_str(s) {
+            // anything we produce must pass validation
+            assert_eq!(Multiaddr::from_bytes(ma.as_bytes().to_vec()).unwrap(), ma);
+            assert_eq!(Multiaddr::from_str(&ma.to_string()).unwrap(), ma);
+        }
+    }
+});
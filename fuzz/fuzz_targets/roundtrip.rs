@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_multiaddr::Multiaddr;
+use std::str::FromStr;
+
+fuzz_target!(|ma: Multiaddr| {
+    assert_eq!(Multiaddr::from_bytes(ma.as_bytes().to_vec()).unwrap(), ma);
+    if let Ok(parsed) = Multiaddr::from_str(&ma.to_string()) {
+        assert_eq!(parsed, ma);
+    }
+});
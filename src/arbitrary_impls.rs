@@ -0,0 +1,63 @@
+//! `Arbitrary` implementations for fuzzing, enabled by the `arbitrary`
+//! feature.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use std::str::FromStr;
+use varint::VarintWrite;
+
+use protocol::PROTOCOLS;
+use {Multiaddr, Protocol, Size};
+
+impl<'a> Arbitrary<'a> for Protocol {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let &(name, _) = try!(u.choose(PROTOCOLS));
+        Ok(Protocol::from_str(name).unwrap())
+    }
+}
+
+/// Generates valid multiaddrs with up to 8 components.
+impl<'a> Arbitrary<'a> for Multiaddr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut bytes = Vec::new();
+        let len = try!(u.int_in_range(0..=8));
+        for _ in 0..len {
+            let p: Protocol = try!(u.arbitrary());
+            bytes.write_unsigned_varint_32(u32::from(p)).unwrap();
+            match p.size() {
+                Size::Fixed(_) => {
+                    let mut addr = try!(u.bytes(p.size_bytes().unwrap() as usize)).to_vec();
+                    if let Some(last) = addr.last_mut() {
+                        *last &= !p.size().padding_mask();
+                    }
+                    // onion ports can't be 0
+                    if p.name().starts_with("onion") && addr.ends_with(&[0, 0]) {
+                        *addr.last_mut().unwrap() = 1;
+                    }
+                    bytes.extend(addr);
+                }
+                Size::Variable if p == Protocol::IPFS => {
+                    // Give it a well-formed sha2-256 multihash
+                    let digest = try!(u.bytes(32));
+                    bytes.extend(&[34, 0x12, 32]);
+                    bytes.extend(digest);
+                }
+                Size::Variable => {
+                    // The dns protocols, which take a domain name of up
+                    // to four labels
+                    let mut name = Vec::new();
+                    for i in 0..try!(u.int_in_range(1..=4)) {
+                        if i > 0 {
+                            name.push(b'.');
+                        }
+                        for _ in 0..try!(u.int_in_range(1..=8)) {
+                            name.push(*try!(u.choose(b"abcdefghijklmnopqrstuvwxyz0123456789")));
+                        }
+                    }
+                    bytes.write_unsigned_varint_32(name.len() as u32).unwrap();
+                    bytes.extend(name);
+                }
+            }
+        }
+        Ok(Multiaddr::from_bytes(bytes).expect("generated an invalid multiaddr"))
+    }
+}
@@ -0,0 +1,233 @@
+//! Command line tool for inspecting and converting multiaddrs.
+
+extern crate rust_multiaddr;
+
+use rust_multiaddr::{Multiaddr, Protocol, Size};
+use std::convert::TryFrom;
+use std::env;
+use std::io::{self, BufRead, Read, Write};
+use std::process;
+use std::str::FromStr;
+
+const USAGE: &'static str = "\
+Usage: multiaddr <command> [args]
+
+Commands:
+    validate [addr...]      check that each address is valid
+    to-hex [addr...]        print the binary form of each address in hex
+    from-hex [hex...]       print the textual form of hex-encoded addresses
+    to-bin <addr>           write the binary form of an address to stdout
+    from-bin                read a binary address from stdin and print it
+    show [addr...]          print the components of each address
+    filter [proto...]       print the valid addresses that contain all of
+                            the given protocols
+
+Addresses (or hex strings) are read one per line from stdin when none are
+given on the command line. Blank lines and lines starting with '#' are
+skipped.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args[0] == "-h" || args[0] == "--help" {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let rest = &args[1..];
+    let ok = match &args[0][..] {
+        "validate" => for_each_input(rest, validate),
+        "to-hex" => for_each_input(rest, to_hex),
+        "from-hex" => for_each_input(rest, from_hex),
+        "show" => for_each_input(rest, show),
+        "to-bin" if rest.len() == 1 => to_bin(&rest[0]),
+        "from-bin" if rest.is_empty() => from_bin(),
+        "filter" => filter(rest),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if !ok {
+        process::exit(1);
+    }
+}
+
+// Runs `f` on each argument, or on each line of stdin if there are none.
+// Returns false if `f` failed for any input.
+fn for_each_input<F: Fn(&str) -> Result<(), String>>(args: &[String], f: F) -> bool {
+    let mut ok = true;
+    let mut run = |input: &str| {
+        if let Err(e) = f(input) {
+            eprintln!("{}: {}", input, e);
+            ok = false;
+        }
+    };
+
+    if args.is_empty() {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = line.unwrap_or_else(|e| fail(&format!("Error reading stdin: {}", e)));
+            if let Some(input) = input_line(&line) {
+                run(input);
+            }
+        }
+    } else {
+        for arg in args {
+            run(arg);
+        }
+    }
+    ok
+}
+
+fn input_line(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        None
+    } else {
+        Some(line)
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    process::exit(1)
+}
+
+fn parse(s: &str) -> Result<Multiaddr, String> {
+    Multiaddr::from_str(s).map_err(|e| format!("{:?}", e))
+}
+
+fn validate(s: &str) -> Result<(), String> {
+    try!(parse(s));
+    println!("{}: ok", s);
+    Ok(())
+}
+
+fn to_hex(s: &str) -> Result<(), String> {
+    let ma = try!(parse(s));
+    println!("{}", hex_encode(ma.as_bytes()));
+    Ok(())
+}
+
+fn from_hex(s: &str) -> Result<(), String> {
+    let bytes = try!(hex_decode(s));
+    let ma = try!(Multiaddr::from_bytes(bytes).map_err(|e| format!("{:?}", e)));
+    println!("{}", ma);
+    Ok(())
+}
+
+fn to_bin(s: &str) -> bool {
+    match parse(s) {
+        Ok(ma) => {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            out.write_all(ma.as_bytes())
+               .and_then(|_| out.flush())
+               .unwrap_or_else(|e| fail(&format!("Error writing stdout: {}", e)));
+            true
+        }
+        Err(e) => {
+            eprintln!("{}: {}", s, e);
+            false
+        }
+    }
+}
+
+fn from_bin() -> bool {
+    let mut bytes = Vec::new();
+    io::stdin()
+        .read_to_end(&mut bytes)
+        .unwrap_or_else(|e| fail(&format!("Error reading stdin: {}", e)));
+    match Multiaddr::try_from(&bytes[..]) {
+        Ok(ma) => {
+            println!("{}", ma);
+            true
+        }
+        Err(e) => {
+            eprintln!("{:?}", e);
+            false
+        }
+    }
+}
+
+fn show(s: &str) -> Result<(), String> {
+    let ma = try!(parse(s));
+    println!("{}", ma);
+    println!("  bytes: {}", hex_encode(ma.as_bytes()));
+
+    for (p, addr) in ma.iter() {
+        let code = u32::from(p);
+        match p.size() {
+            Size::Fixed(0) => println!("  {:<8} {:>7}", p.to_str(), code),
+            _ => {
+                let value = Multiaddr::from_bytes(component_bytes(p, addr)).unwrap().to_string();
+                let value = value.splitn(3, '/').nth(2).unwrap_or("");
+                println!("  {:<8} {:>7}  {:<24} {}", p.to_str(), code, value, hex_encode(addr));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Rebuilds the bytes of a single-component multiaddr
+fn component_bytes(p: Protocol, addr: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_varint(u32::from(p), &mut bytes);
+    if let Size::Variable = p.size() {
+        write_varint(addr.len() as u32, &mut bytes);
+    }
+    bytes.extend(addr);
+    bytes
+}
+
+fn write_varint(mut n: u32, bytes: &mut Vec<u8>) {
+    while n >= 0x80 {
+        bytes.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+fn filter(protos: &[String]) -> bool {
+    let mut wanted = Vec::new();
+    for name in protos {
+        match Protocol::from_str(name) {
+            Ok(p) => wanted.push(p),
+            Err(_) => fail(&format!("Unknown protocol: {}", name)),
+        }
+    }
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line.unwrap_or_else(|e| fail(&format!("Error reading stdin: {}", e)));
+        let input = match input_line(&line) {
+            Some(input) => input,
+            None => continue,
+        };
+        if let Ok(ma) = Multiaddr::from_str(input) {
+            if wanted.iter().all(|w| ma.iter().any(|(p, _)| p == *w)) {
+                println!("{}", input);
+            }
+        }
+    }
+    true
+}
+
+fn hex_encode(b: &[u8]) -> String {
+    b.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(s: &str) -> Result<Vec<u8>, String> {
+    let s = if s.starts_with("0x") { &s[2..] } else { s };
+    if s.len() % 2 != 0 {
+        return Err(format!("Odd number of hex digits"));
+    }
+    (0..s.len() / 2)
+        .map(|i| {
+            s.get(2 * i..2 * i + 2)
+             .and_then(|d| u8::from_str_radix(d, 16).ok())
+             .ok_or(format!("Invalid hex: {}", s))
+        })
+        .collect()
+}
@@ -0,0 +1,41 @@
+//! Borsh serialization, enabled by the `borsh` feature.
+//!
+//! A multiaddr is encoded like a `Vec<u8>` holding its binary form, and is
+//! validated when deserialized.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::io::{self, Read, Write};
+
+use Multiaddr;
+
+impl BorshSerialize for Multiaddr {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        BorshSerialize::serialize(self.as_bytes(), writer)
+    }
+}
+
+impl BorshDeserialize for Multiaddr {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let bytes: Vec<u8> = try!(BorshDeserialize::deserialize_reader(reader));
+        Multiaddr::from_bytes(bytes).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use borsh::{self, BorshDeserialize};
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_roundtrip() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        let encoded = borsh::to_vec(&ma).unwrap();
+        assert_eq!(&encoded[..4], &[8, 0, 0, 0]);
+        assert_eq!(Multiaddr::try_from_slice(&encoded).unwrap(), ma);
+
+        assert!(Multiaddr::try_from_slice(&[3, 0, 0, 0, 4, 1, 2]).is_err());
+    }
+}
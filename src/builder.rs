@@ -0,0 +1,193 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use protocol::Protocol::*;
+use {storage_from_slice, write_component, AddrComponent, Multiaddr, ParseError, ParseResult,
+     Protocol, Registry};
+
+/// Builds a multiaddr component by component from typed values, without
+/// going through its textual form:
+///
+/// ```
+/// # use std::net::Ipv4Addr;
+/// # use rust_multiaddr::Multiaddr;
+/// let ma = Multiaddr::builder().ip4(Ipv4Addr::new(1, 2, 3, 4)).tcp(4001).build().unwrap();
+/// assert_eq!(ma.to_string(), "/ip4/1.2.3.4/tcp/4001");
+/// ```
+///
+/// Values that can be invalid, such as domain names and peer ids, are
+/// checked as they are added. The first error is returned by `build`.
+#[derive(Debug)]
+pub struct Builder {
+    bytes: Vec<u8>,
+    error: Option<ParseError>,
+}
+
+impl Multiaddr {
+    /// Starts building a multiaddr, see `Builder`.
+    pub fn builder() -> Builder {
+        Builder { bytes: Vec::new(), error: None }
+    }
+}
+
+impl Builder {
+    pub fn ip4(self, ip: Ipv4Addr) -> Builder {
+        self.push(IP4, &ip.octets())
+    }
+
+    pub fn ip6(self, ip: Ipv6Addr) -> Builder {
+        self.push(IP6, &ip.octets())
+    }
+
+    /// Adds an ip4 or ip6 component, depending on the address.
+    pub fn ip(self, ip: IpAddr) -> Builder {
+        match ip {
+            IpAddr::V4(ip) => self.ip4(ip),
+            IpAddr::V6(ip) => self.ip6(ip),
+        }
+    }
+
+    pub fn tcp(self, port: u16) -> Builder {
+        self.port(TCP, port)
+    }
+
+    pub fn udp(self, port: u16) -> Builder {
+        self.port(UDP, port)
+    }
+
+    pub fn dccp(self, port: u16) -> Builder {
+        self.port(DCCP, port)
+    }
+
+    pub fn sctp(self, port: u16) -> Builder {
+        self.port(SCTP, port)
+    }
+
+    #[cfg(feature = "proto-dns")]
+    pub fn dns(self, name: &str) -> Builder {
+        self.checked(DNS, name.as_bytes())
+    }
+
+    #[cfg(feature = "proto-dns")]
+    pub fn dns4(self, name: &str) -> Builder {
+        self.checked(DNS4, name.as_bytes())
+    }
+
+    #[cfg(feature = "proto-dns")]
+    pub fn dns6(self, name: &str) -> Builder {
+        self.checked(DNS6, name.as_bytes())
+    }
+
+    #[cfg(feature = "proto-dns")]
+    pub fn dnsaddr(self, name: &str) -> Builder {
+        self.checked(DNSADDR, name.as_bytes())
+    }
+
+    /// Adds an ipfs component holding the binary multihash of a peer id.
+    pub fn p2p(self, peer_id: &[u8]) -> Builder {
+        self.checked(IPFS, peer_id)
+    }
+
+    pub fn p2p_circuit(self) -> Builder {
+        self.push(P2P_CIRCUIT, &[])
+    }
+
+    pub fn quic(self) -> Builder {
+        self.push(QUIC, &[])
+    }
+
+    pub fn ws(self) -> Builder {
+        self.push(WS, &[])
+    }
+
+    pub fn wss(self) -> Builder {
+        self.push(WSS, &[])
+    }
+
+    pub fn http(self) -> Builder {
+        self.push(HTTP, &[])
+    }
+
+    pub fn https(self) -> Builder {
+        self.push(HTTPS, &[])
+    }
+
+    /// Adds any component, checking it like the typed methods do.
+    pub fn component(mut self, c: AddrComponent) -> Builder {
+        let mut bytes = Vec::new();
+        c.write_bytes(&mut bytes);
+        if self.error.is_none() {
+            if let Err(e) = Multiaddr::validate(&bytes) {
+                self.error = Some(e);
+            }
+        }
+        self.bytes.extend(bytes);
+        self
+    }
+
+    /// Returns the multiaddr, or the first error met while building it.
+    pub fn build(self) -> ParseResult<Multiaddr> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(Multiaddr { bytes: storage_from_slice(&self.bytes) }),
+        }
+    }
+
+    fn port(self, proto: Protocol, port: u16) -> Builder {
+        self.push(proto, &[(port >> 8) as u8, port as u8])
+    }
+
+    fn checked(mut self, proto: Protocol, addr: &[u8]) -> Builder {
+        if self.error.is_none() {
+            if let Err(e) = Registry::new().check_address(proto, addr) {
+                self.error = Some(ParseError::invalid_address(e));
+            }
+        }
+        self.push(proto, addr)
+    }
+
+    fn push(mut self, proto: Protocol, addr: &[u8]) -> Builder {
+        write_component(proto, addr, &mut self.bytes);
+        self
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::net::{IpAddr, Ipv4Addr};
+    use Multiaddr;
+
+    #[test]
+    fn test_builder() {
+        let peer = [&[0x12, 32][..], &[7; 32][..]].concat();
+        let ma = Multiaddr::builder()
+            .ip4(Ipv4Addr::new(1, 2, 3, 4))
+            .tcp(4001)
+            .ws()
+            .p2p(&peer)
+            .build()
+            .unwrap();
+        assert_eq!(ma.protocol_stack(), ["ip4", "tcp", "ws", "ipfs"]);
+        assert_eq!(ma.value_for(::Protocol::TCP).unwrap(), "4001");
+        assert_eq!(ma.value_bytes_for(::Protocol::IPFS).unwrap(), &peer[..]);
+
+        let ip: IpAddr = "::1".parse().unwrap();
+        let ma = Multiaddr::builder().ip(ip).udp(53).quic().build().unwrap();
+        assert_eq!(ma.to_string(), "/ip6/::1/udp/53/quic");
+
+        assert!(Multiaddr::builder().p2p(&[0x12, 2, 1, 2]).build().is_err());
+    }
+
+    #[cfg(feature = "proto-dns")]
+    #[test]
+    fn test_builder_dns() {
+        use {AddrComponent, ErrorKind};
+
+        let ma = Multiaddr::builder().dns4("example.com").component(AddrComponent::TCP(80)).build();
+        assert_eq!(ma.unwrap().to_string(), "/dns4/example.com/tcp/80");
+
+        let e = Multiaddr::builder().dns("a/b").tcp(1).build().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidAddress);
+        assert!(Multiaddr::builder().dns("").build().is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use std::net::Ipv4Addr;
+
+use protocol::Protocol::{self, *};
+use {read_ip4, read_ip6, write_component, write_protocol, Maddr, Multiaddr};
+
+impl Maddr {
+    /// Returns the normal form of the multiaddr, so that two multiaddrs
+    /// denoting the same endpoint are equal after canonicalization.
+    ///
+    /// The binary form already removes most differences of spelling, such
+    /// as "/p2p" for "/ipfs", uncompressed or uppercase ip6 addresses and
+    /// leading zeros in ports. On top of that, domain names are lowercased
+    /// and lose any trailing dot, and the host bits of an ip address
+    /// followed by ipcidr are cleared.
+    pub fn canonicalize(&self) -> Multiaddr {
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.as_bytes().len());
+        let mut components = self.iter().peekable();
+        while let Some((p, addr)) = components.next() {
+            match p {
+                #[cfg(feature = "proto-dns")]
+                DNS | DNS4 | DNS6 | DNSADDR => {
+                    let name = String::from_utf8_lossy(addr).to_lowercase();
+                    let trimmed = name.trim_end_matches('.');
+                    // A name of only dots keeps them, since it can't be empty
+                    let name = if trimmed.is_empty() { &name[..] } else { trimmed };
+                    write_component(p, name.as_bytes(), &mut bytes);
+                }
+                IP4 | IP6 => {
+                    let mut addr = addr.to_vec();
+                    if let Some(&(IPCIDR, bits)) = components.peek() {
+                        mask(&mut addr, bits[0]);
+                    }
+                    write_component(p, &addr, &mut bytes);
+                }
+                _ => write_component(p, addr, &mut bytes),
+            }
+        }
+        Multiaddr { bytes: bytes.into() }
+    }
+
+    /// Checks whether two multiaddrs denote the same endpoint, e.g.
+    /// "/dns4/Example.com/tcp/80" and "/dns4/example.com./tcp/80", which
+    /// `==` tells apart. This compares the canonical forms.
+    pub fn equivalent(&self, other: &Maddr) -> bool {
+        self.canonicalize() == other.canonicalize()
+    }
+
+    /// Rewrites the deprecated composed protocols into the forms the spec
+    /// now uses: /https into /tls/http and /wss into /tls/ws.
+    ///
+    /// /ipfs and /p2p are the same protocol code, so they need no
+    /// conversion. Both forms parse to the same multiaddr.
+    pub fn upgrade(&self) -> Multiaddr {
+        let mut bytes = Vec::with_capacity(self.as_bytes().len() + 4);
+        for (p, addr) in self.iter() {
+            match p {
+                HTTPS => {
+                    write_protocol(TLS, &mut bytes);
+                    write_protocol(HTTP, &mut bytes);
+                }
+                WSS => {
+                    write_protocol(TLS, &mut bytes);
+                    write_protocol(WS, &mut bytes);
+                }
+                _ => write_component(p, addr, &mut bytes),
+            }
+        }
+        Multiaddr { bytes: bytes.into() }
+    }
+
+    /// Rewrites /tls/http into /https and /tls/ws into /wss, for peers that
+    /// only understand the older forms. This reverses `upgrade`. A /tls
+    /// that is followed by anything else is kept.
+    pub fn downgrade(&self) -> Multiaddr {
+        let mut bytes = Vec::with_capacity(self.as_bytes().len());
+        let mut components = self.iter().peekable();
+        while let Some((p, addr)) = components.next() {
+            let combined = match (p, components.peek()) {
+                (TLS, Some(&(HTTP, _))) => Some(HTTPS),
+                (TLS, Some(&(WS, _))) => Some(WSS),
+                _ => None,
+            };
+            match combined {
+                Some(p) => {
+                    components.next();
+                    write_protocol(p, &mut bytes);
+                }
+                None => write_component(p, addr, &mut bytes),
+            }
+        }
+        Multiaddr { bytes: bytes.into() }
+    }
+
+    /// Checks whether the multiaddr has an ip4-mapped ip6 address,
+    /// ::ffff:a.b.c.d, that `unmap` would replace.
+    pub fn is_ip4_mapped(&self) -> bool {
+        let components: Vec<_> = self.iter().collect();
+        (0..components.len()).any(|i| unmapped(&components, i).is_some())
+    }
+
+    /// Replaces ip4-mapped ip6 addresses, which dual-stack sockets report
+    /// for ip4 peers, with the ip4 addresses they map:
+    /// "/ip6/::ffff:1.2.3.4/tcp/80" becomes "/ip4/1.2.3.4/tcp/80". An
+    /// ip6zone before the address is dropped, and an ipcidr after it loses
+    /// the 96 bits of the mapping prefix. Addresses followed by an ipcidr
+    /// shorter than that cover more than ip4 addresses, and are kept.
+    pub fn unmap(&self) -> Multiaddr {
+        let components: Vec<_> = self.iter().collect();
+        let mut bytes = Vec::with_capacity(self.as_bytes().len());
+        for (i, &(p, addr)) in components.iter().enumerate() {
+            match p {
+                IP6ZONE if unmapped(&components, i + 1).is_some() => (),
+                IP6 => {
+                    match unmapped(&components, i) {
+                        Some(ip) => write_component(IP4, &ip.octets(), &mut bytes),
+                        None => write_component(p, addr, &mut bytes),
+                    }
+                }
+                IPCIDR if i > 0 && unmapped(&components, i - 1).is_some() => {
+                    write_component(p, &[addr[0] - 96], &mut bytes)
+                }
+                _ => write_component(p, addr, &mut bytes),
+            }
+        }
+        Multiaddr { bytes: bytes.into() }
+    }
+
+    /// Replaces ip4 addresses with their ip4-mapped ip6 form, for comparing
+    /// with the addresses of dual-stack sockets: "/ip4/1.2.3.4/tcp/80"
+    /// becomes "/ip6/::ffff:1.2.3.4/tcp/80". An ipcidr after the address
+    /// gains 96 bits. This reverses `unmap`. Addresses followed by an ipcidr
+    /// longer than 32 bits aren't valid ip4 ranges, and are kept.
+    pub fn to_ip4_mapped(&self) -> Multiaddr {
+        let components: Vec<_> = self.iter().collect();
+        let mut bytes = Vec::with_capacity(self.as_bytes().len() + 12);
+        for (i, &(p, addr)) in components.iter().enumerate() {
+            match p {
+                IP4 if mappable(&components, i) => {
+                    let ip = read_ip4(addr).to_ipv6_mapped();
+                    write_component(IP6, &ip.octets(), &mut bytes)
+                }
+                IPCIDR if i > 0 && mappable(&components, i - 1) => {
+                    write_component(p, &[addr[0] + 96], &mut bytes)
+                }
+                _ => write_component(p, addr, &mut bytes),
+            }
+        }
+        Multiaddr { bytes: bytes.into() }
+    }
+}
+
+// The ip4 address the component at `i` maps, if it is an ip4-mapped ip6
+// address that `unmap` replaces
+fn unmapped(components: &[(Protocol, &[u8])], i: usize) -> Option<Ipv4Addr> {
+    match components.get(i) {
+        Some(&(IP6, addr)) => {
+            match components.get(i + 1) {
+                Some(&(IPCIDR, bits)) if bits[0] < 96 => None,
+                _ => read_ip6(addr).to_ipv4_mapped(),
+            }
+        }
+        _ => None,
+    }
+}
+
+// Whether the component at `i` is an ip4 address that `to_ip4_mapped`
+// replaces
+fn mappable(components: &[(Protocol, &[u8])], i: usize) -> bool {
+    match components.get(i) {
+        Some(&(IP4, _)) => {
+            match components.get(i + 1) {
+                Some(&(IPCIDR, bits)) => bits[0] <= 32,
+                _ => true,
+            }
+        }
+        _ => false,
+    }
+}
+
+// Clears all but the first `bits` bits of an address
+fn mask(addr: &mut [u8], bits: u8) {
+    for (i, b) in addr.iter_mut().enumerate() {
+        let keep = (bits as usize).saturating_sub(8 * i).min(8);
+        *b &= !(0xffu16 >> keep) as u8;
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use Multiaddr;
+
+    fn canonical(s: &str) -> String {
+        Multiaddr::from_str(s).unwrap().canonicalize().to_string()
+    }
+
+    #[cfg(feature = "proto-dns")]
+    #[test]
+    fn test_equivalent() {
+        let pairs = [("/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC",
+                      "/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC"),
+                     ("/dns4/EXAMPLE.com/tcp/80", "/dns4/example.com./tcp/80"),
+                     ("/ip4/10.9.9.9/ipcidr/8", "/ip4/10.0.0.0/ipcidr/8")];
+        for &(a, b) in &pairs {
+            let (a, b) = (Multiaddr::from_str(a).unwrap(), Multiaddr::from_str(b).unwrap());
+            assert!(a.equivalent(&b));
+        }
+
+        let a = Multiaddr::from_str("/dns4/example.com/tcp/80").unwrap();
+        assert!(a != Multiaddr::from_str("/dns4/Example.com/tcp/80").unwrap());
+        assert!(!a.equivalent(&Multiaddr::from_str("/dns6/example.com/tcp/80").unwrap()));
+        assert!(!a.equivalent(&Multiaddr::from_str("/dns4/example.org/tcp/80").unwrap()));
+    }
+
+    #[test]
+    fn test_upgrade_downgrade() {
+        let old = Multiaddr::from_str("/ip4/1.2.3.4/tcp/443/wss/p2p-circuit/tcp/443/https").unwrap();
+        let new = Multiaddr::from_str("/ip4/1.2.3.4/tcp/443/tls/ws/p2p-circuit/tcp/443/tls/http")
+            .unwrap();
+        assert_eq!(old.upgrade(), new);
+        assert_eq!(new.downgrade(), old);
+        assert_eq!(new.upgrade(), new);
+        assert_eq!(old.downgrade(), old);
+
+        let tls = Multiaddr::from_str("/ip4/1.2.3.4/tcp/443/tls/tls/ws/utp/tls").unwrap();
+        assert_eq!(tls.downgrade().to_string(), "/ip4/1.2.3.4/tcp/443/tls/wss/utp/tls");
+    }
+
+    #[test]
+    fn test_unmap() {
+        let unmap = |s: &str| Multiaddr::from_str(s).unwrap().unmap().to_string();
+        assert_eq!(unmap("/ip6/::ffff:1.2.3.4/tcp/80"), "/ip4/1.2.3.4/tcp/80");
+        assert_eq!(unmap("/ip6zone/eth0/ip6/::ffff:1.2.3.4/udp/53"), "/ip4/1.2.3.4/udp/53");
+        assert_eq!(unmap("/ip6/::ffff:10.0.0.0/ipcidr/104"), "/ip4/10.0.0.0/ipcidr/8");
+        assert_eq!(unmap("/ip6/::ffff:0.0.0.0/ipcidr/64"), "/ip6/::ffff:0.0.0.0/ipcidr/64");
+        assert_eq!(unmap("/ip6/::1/tcp/80"), "/ip6/::1/tcp/80");
+        assert_eq!(unmap("/ip6zone/eth0/ip6/fe80::1"), "/ip6zone/eth0/ip6/fe80::1");
+        // The deprecated ip4-compatible form isn't a mapping
+        assert_eq!(unmap("/ip6/::1.2.3.4"), "/ip6/::102:304");
+
+        let mapped = Multiaddr::from_str("/ip6/::ffff:1.2.3.4/tcp/80").unwrap();
+        assert!(mapped.is_ip4_mapped());
+        assert!(!mapped.unmap().is_ip4_mapped());
+        assert!(!Multiaddr::from_str("/ip6/::ffff:0.0.0.0/ipcidr/64").unwrap().is_ip4_mapped());
+    }
+
+    #[test]
+    fn test_to_ip4_mapped() {
+        let map = |s: &str| Multiaddr::from_str(s).unwrap().to_ip4_mapped().to_string();
+        assert_eq!(map("/ip4/1.2.3.4/tcp/80"), "/ip6/::ffff:1.2.3.4/tcp/80");
+        assert_eq!(map("/ip4/10.0.0.0/ipcidr/8"), "/ip6/::ffff:10.0.0.0/ipcidr/104");
+        assert_eq!(map("/ip6/::1/ipcidr/128"), "/ip6/::1/ipcidr/128");
+        // not an ip4 range, so left alone rather than made an ip6 one
+        assert_eq!(map("/ip4/1.2.3.4/ipcidr/33"), "/ip4/1.2.3.4/ipcidr/33");
+        assert_eq!(map("/ip4/1.2.3.4/ipcidr/200/tcp/80"), "/ip4/1.2.3.4/ipcidr/200/tcp/80");
+        assert_eq!(map("/ip4/1.2.3.4/ipcidr/32"), "/ip6/::ffff:1.2.3.4/ipcidr/128");
+        for s in &["/ip4/1.2.3.4/tcp/80/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC",
+                   "/ip4/192.168.0.0/ipcidr/16"] {
+            let ma = Multiaddr::from_str(s).unwrap();
+            assert_eq!(ma.to_ip4_mapped().unmap(), ma);
+        }
+    }
+
+    #[cfg(feature = "proto-dns")]
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(canonical("/ip6/2001:DB8:0:0::0001/tcp/0080"), "/ip6/2001:db8::1/tcp/80");
+        assert_eq!(canonical("/dns4/Example.COM./tcp/443/wss"), "/dns4/example.com/tcp/443/wss");
+        assert_eq!(canonical("/ip4/10.1.2.3/ipcidr/8"), "/ip4/10.0.0.0/ipcidr/8");
+        assert_eq!(canonical("/ip6/fe80::1:2/ipcidr/112"), "/ip6/fe80::1:0/ipcidr/112");
+        assert_eq!(canonical("/ip4/10.1.2.3/ipcidr/40"), "/ip4/10.1.2.3/ipcidr/40");
+        assert_eq!(canonical("/ip4/10.1.2.3/tcp/1"), "/ip4/10.1.2.3/tcp/1");
+        assert_eq!(canonical("/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC"),
+                   "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC");
+    }
+}
@@ -0,0 +1,81 @@
+//! Peer ids written as CIDv1, e.g. "bafzaa...", as an alternative to the
+//! base58 multihash. Only the multihash is stored, so an ipfs component
+//! parsed from either form is the same.
+
+use varint::VarintWrite;
+
+use protocol::Protocol::*;
+use {base32_encode, multibase, read_varint, Maddr};
+
+// The multicodec of public keys, which peer id CIDs must use
+const LIBP2P_KEY: u32 = 0x72;
+
+impl Maddr {
+    /// Returns the textual form of the multiaddr with peer ids written as
+    /// base32 CIDv1 rather than base58, as in "/ipfs/bafz...". Both forms
+    /// parse to the same multiaddr.
+    pub fn to_cid_string(&self) -> String {
+        ::to_string_with(self, |proto, addr| {
+            match proto {
+                IPFS => Some(peer_id_to_cid(addr)),
+                _ => None,
+            }
+        })
+    }
+}
+
+// Appends the multihash in a base32 CIDv1 with the libp2p-key codec to `v`,
+// or returns false if `s` isn't one.
+pub(crate) fn write_peer_id_from_cid(s: &str, v: &mut Vec<u8>) -> bool {
+    if !s.starts_with('b') {
+        return false;
+    }
+    let start = v.len();
+    if !multibase::base32_decode_into(&s[1..], v) {
+        return false;
+    }
+    let header_len = {
+        let mut rest = &v[start..];
+        match (read_varint(&mut rest), read_varint(&mut rest)) {
+            (Ok(1), Ok(LIBP2P_KEY)) => v.len() - start - rest.len(),
+            _ => 0,
+        }
+    };
+    if header_len == 0 {
+        v.truncate(start);
+        return false;
+    }
+    v.drain(start..start + header_len);
+    true
+}
+
+fn peer_id_to_cid(multihash: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(multihash.len() + 2);
+    bytes.write_unsigned_varint_32(1).unwrap();
+    bytes.write_unsigned_varint_32(LIBP2P_KEY).unwrap();
+    bytes.extend(multihash);
+    format!("b{}", base32_encode(&bytes))
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_cid_peer_id() {
+        let base58 = "/ip4/1.2.3.4/tcp/4001/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+        let cid = "/ip4/1.2.3.4/tcp/4001/ipfs/\
+                   bafzbeigvf25ytwc3akrijfecaotc74udrhcxzh2cx3we5qqnw5vgrei4bm";
+        let ma = Multiaddr::from_str(base58).unwrap();
+        assert_eq!(ma.to_cid_string(), cid);
+        assert_eq!(Multiaddr::from_str(cid).unwrap(), ma);
+        let p2p = cid.replace("/ipfs/", "/p2p/");
+        assert_eq!(Multiaddr::from_str(&p2p).unwrap().to_string(), base58);
+
+        // a CIDv1 with the dag-pb codec isn't a peer id
+        let dag_pb = "/ipfs/bafybeigvf25ytwc3akrijfecaotc74udrhcxzh2cx3we5qqnw5vgrei4bm";
+        assert!(Multiaddr::from_str(dag_pb).is_err());
+    }
+}
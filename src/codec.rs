@@ -0,0 +1,152 @@
+use bytes::BytesMut;
+use std::error::Error;
+use std::io;
+use tokio_util::codec::{Decoder, Encoder};
+use varint::VarintWrite;
+
+use {read_varint, Limits, Maddr, Multiaddr};
+
+/// Frames multiaddrs for `tokio_util::codec::Framed`, in the same
+/// varint length-prefixed form as `write_delimited`.
+///
+/// Frames longer than `max_len` are rejected with an `InvalidData` error
+/// as soon as their length prefix is read, and so are frames that aren't a
+/// valid multiaddr.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MultiaddrCodec {
+    max_len: usize,
+}
+
+impl MultiaddrCodec {
+    /// A codec accepting frames up to the default `Limits`.
+    pub fn new() -> MultiaddrCodec {
+        MultiaddrCodec::with_max_len(Limits::default().max_len)
+    }
+
+    pub fn with_max_len(max_len: usize) -> MultiaddrCodec {
+        MultiaddrCodec { max_len: max_len }
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+}
+
+impl Default for MultiaddrCodec {
+    fn default() -> MultiaddrCodec {
+        MultiaddrCodec::new()
+    }
+}
+
+impl Decoder for MultiaddrCodec {
+    type Item = Multiaddr;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Multiaddr>> {
+        // The length is a varint of at most 5 bytes
+        let prefix_len = match src.iter().take(5).position(|&b| b & 0x80 == 0) {
+            Some(i) => i + 1,
+            None if src.len() < 5 => return Ok(None),
+            None => 5,
+        };
+        let len = try!(read_varint(&mut &src[..prefix_len]).map_err(invalid_data)) as usize;
+        if len > self.max_len {
+            return Err(invalid_data(format!("Multiaddr is {} bytes, more than the limit of {}",
+                                            len, self.max_len)));
+        }
+        if src.len() < prefix_len + len {
+            src.reserve(prefix_len + len - src.len());
+            return Ok(None);
+        }
+
+        let frame = src.split_to(prefix_len + len);
+        Multiaddr::from_bytes(frame[prefix_len..].to_vec()).map(Some).map_err(invalid_data)
+    }
+}
+
+impl<'a> Encoder<&'a Maddr> for MultiaddrCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, ma: &'a Maddr, dst: &mut BytesMut) -> io::Result<()> {
+        let bytes = ma.as_bytes();
+        if bytes.len() > self.max_len {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("Multiaddr is {} bytes, more than the limit of {}",
+                                              bytes.len(), self.max_len)));
+        }
+        let mut prefix = Vec::with_capacity(5);
+        prefix.write_unsigned_varint_32(bytes.len() as u32).unwrap();
+        dst.reserve(prefix.len() + bytes.len());
+        dst.extend_from_slice(&prefix);
+        dst.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl Encoder<Multiaddr> for MultiaddrCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, ma: Multiaddr, dst: &mut BytesMut) -> io::Result<()> {
+        self.encode(&*ma, dst)
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::MultiaddrCodec;
+    use bytes::BytesMut;
+    use std::io::ErrorKind;
+    use std::str::FromStr;
+    use tokio_util::codec::{Decoder, Encoder};
+    use Multiaddr;
+
+    #[test]
+    fn test_codec() {
+        let addrs: Vec<_> = ["/ip4/1.2.3.4/tcp/80", "/", "/ip6/::1/udp/53/quic"]
+            .iter()
+            .map(|s| Multiaddr::from_str(s).unwrap())
+            .collect();
+        let mut codec = MultiaddrCodec::new();
+        let mut buf = BytesMut::new();
+        for ma in &addrs {
+            codec.encode(&**ma, &mut buf).unwrap();
+        }
+        let mut delimited = Vec::new();
+        for ma in &addrs {
+            ma.write_delimited(&mut delimited).unwrap();
+        }
+        assert_eq!(&buf[..], &delimited[..]);
+
+        // A frame that hasn't fully arrived is left in the buffer
+        let mut partial = BytesMut::new();
+        partial.extend_from_slice(&buf[..5]);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+        assert_eq!(partial.len(), 5);
+
+        for ma in &addrs {
+            assert_eq!(&codec.decode(&mut buf).unwrap().unwrap(), ma);
+        }
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_codec_errors() {
+        let decode = |max_len: usize, b: &[u8]| {
+            let mut buf = BytesMut::new();
+            buf.extend_from_slice(b);
+            MultiaddrCodec::with_max_len(max_len).decode(&mut buf).unwrap_err().kind()
+        };
+        assert_eq!(decode(1024, &[2, 4, 1]), ErrorKind::InvalidData);
+        assert_eq!(decode(4, &[8, 4, 1, 2, 3, 4, 6, 0, 80]), ErrorKind::InvalidData);
+        assert_eq!(decode(1024, &[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]), ErrorKind::InvalidData);
+
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        let err = MultiaddrCodec::with_max_len(4).encode(ma, &mut BytesMut::new()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,679 @@
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use std::fmt;
+use std::iter::FromIterator;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ops::{Index, Range};
+use std::str::FromStr;
+use varint::VarintWrite;
+
+use protocol::Protocol;
+use {read_ip4, read_ip6, storage_append, verify_bytes, write_component, write_ip4_to_vec,
+     write_ip6_to_vec, write_protocol};
+use {Maddr, Multiaddr, ParseError, ParseResult, Registry};
+
+/// A single component of a multiaddr: a protocol and its decoded address.
+/// As with `Protocol`, features add variants, so matches on it need a
+/// wildcard arm.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AddrComponent {
+    IP4(Ipv4Addr),
+    TCP(u16),
+    UDP(u16),
+    DCCP(u16),
+    IP6(Ipv6Addr),
+    /// The zone of the ip6 address that follows, e.g. an interface name
+    IP6ZONE(String),
+    IPCIDR(u8),
+    #[cfg(feature = "proto-dns")]
+    DNS(String),
+    #[cfg(feature = "proto-dns")]
+    DNS4(String),
+    #[cfg(feature = "proto-dns")]
+    DNS6(String),
+    #[cfg(feature = "proto-dns")]
+    DNSADDR(String),
+    SCTP(u16),
+    P2P_WEBRTC_STAR,
+    WEBRTC,
+    P2P_CIRCUIT,
+    UDT,
+    UTP,
+    /// The bytes of a multihash
+    IPFS(Vec<u8>),
+    QUIC,
+    WEBTRANSPORT,
+    WS,
+    WSS,
+    P2P_WEBSOCKET_STAR,
+    HTTP,
+    HTTPS,
+    #[cfg(feature = "proto-onion")]
+    ONION([u8; 10], u16),
+    #[cfg(feature = "proto-onion")]
+    ONION3([u8; 35], u16),
+    TLS,
+    /// The id of an in-process memory transport listener
+    MEMORY(u64),
+    PLAINTEXTV2,
+    /// A protocol this crate doesn't know, as decoded by a `Registry` that
+    /// allows unknown codes. It is written with a length prefix.
+    Unknown { code: u32, bytes: Vec<u8> },
+}
+
+impl AddrComponent {
+    pub fn protocol_id(&self) -> Option<Protocol> {
+        match *self {
+            AddrComponent::IP4(_) => Some(Protocol::IP4),
+            AddrComponent::TCP(_) => Some(Protocol::TCP),
+            AddrComponent::UDP(_) => Some(Protocol::UDP),
+            AddrComponent::DCCP(_) => Some(Protocol::DCCP),
+            AddrComponent::IP6(_) => Some(Protocol::IP6),
+            AddrComponent::IP6ZONE(_) => Some(Protocol::IP6ZONE),
+            AddrComponent::IPCIDR(_) => Some(Protocol::IPCIDR),
+            #[cfg(feature = "proto-dns")]
+            AddrComponent::DNS(_) => Some(Protocol::DNS),
+            #[cfg(feature = "proto-dns")]
+            AddrComponent::DNS4(_) => Some(Protocol::DNS4),
+            #[cfg(feature = "proto-dns")]
+            AddrComponent::DNS6(_) => Some(Protocol::DNS6),
+            #[cfg(feature = "proto-dns")]
+            AddrComponent::DNSADDR(_) => Some(Protocol::DNSADDR),
+            AddrComponent::SCTP(_) => Some(Protocol::SCTP),
+            AddrComponent::P2P_WEBRTC_STAR => Some(Protocol::P2P_WEBRTC_STAR),
+            AddrComponent::WEBRTC => Some(Protocol::WEBRTC),
+            AddrComponent::P2P_CIRCUIT => Some(Protocol::P2P_CIRCUIT),
+            AddrComponent::UDT => Some(Protocol::UDT),
+            AddrComponent::UTP => Some(Protocol::UTP),
+            AddrComponent::IPFS(_) => Some(Protocol::IPFS),
+            AddrComponent::QUIC => Some(Protocol::QUIC),
+            AddrComponent::WEBTRANSPORT => Some(Protocol::WEBTRANSPORT),
+            AddrComponent::WS => Some(Protocol::WS),
+            AddrComponent::WSS => Some(Protocol::WSS),
+            AddrComponent::P2P_WEBSOCKET_STAR => Some(Protocol::P2P_WEBSOCKET_STAR),
+            AddrComponent::HTTP => Some(Protocol::HTTP),
+            AddrComponent::HTTPS => Some(Protocol::HTTPS),
+            #[cfg(feature = "proto-onion")]
+            AddrComponent::ONION(..) => Some(Protocol::ONION),
+            #[cfg(feature = "proto-onion")]
+            AddrComponent::ONION3(..) => Some(Protocol::ONION3),
+            AddrComponent::TLS => Some(Protocol::TLS),
+            AddrComponent::MEMORY(_) => Some(Protocol::MEMORY),
+            AddrComponent::PLAINTEXTV2 => Some(Protocol::PLAINTEXTV2),
+            AddrComponent::Unknown { .. } => None,
+        }
+    }
+
+    /// Returns the protocol code, which unlike `protocol_id` is also known
+    /// for unknown protocols.
+    pub fn code(&self) -> u32 {
+        match *self {
+            AddrComponent::Unknown { code, .. } => code,
+            _ => u32::from(self.protocol_id().unwrap()),
+        }
+    }
+
+    // Decodes a component yielded by Iter, whose address has been validated
+    pub(crate) fn from_parts(proto: Protocol, addr: &[u8]) -> AddrComponent {
+        match proto {
+            Protocol::IP4 => AddrComponent::IP4(read_ip4(addr)),
+            Protocol::TCP => AddrComponent::TCP(BigEndian::read_u16(addr)),
+            Protocol::UDP => AddrComponent::UDP(BigEndian::read_u16(addr)),
+            Protocol::DCCP => AddrComponent::DCCP(BigEndian::read_u16(addr)),
+            Protocol::IP6 => AddrComponent::IP6(read_ip6(addr)),
+            Protocol::IPCIDR => AddrComponent::IPCIDR(addr[0]),
+            // Validated as UTF-8 by verify_multiaddr_bytes
+            Protocol::IP6ZONE => AddrComponent::IP6ZONE(String::from_utf8_lossy(addr).into_owned()),
+            #[cfg(feature = "proto-dns")]
+            Protocol::DNS => AddrComponent::DNS(String::from_utf8_lossy(addr).into_owned()),
+            #[cfg(feature = "proto-dns")]
+            Protocol::DNS4 => AddrComponent::DNS4(String::from_utf8_lossy(addr).into_owned()),
+            #[cfg(feature = "proto-dns")]
+            Protocol::DNS6 => AddrComponent::DNS6(String::from_utf8_lossy(addr).into_owned()),
+            #[cfg(feature = "proto-dns")]
+            Protocol::DNSADDR => AddrComponent::DNSADDR(String::from_utf8_lossy(addr).into_owned()),
+            Protocol::SCTP => AddrComponent::SCTP(BigEndian::read_u16(addr)),
+            Protocol::P2P_WEBRTC_STAR => AddrComponent::P2P_WEBRTC_STAR,
+            Protocol::WEBRTC => AddrComponent::WEBRTC,
+            Protocol::P2P_CIRCUIT => AddrComponent::P2P_CIRCUIT,
+            Protocol::UDT => AddrComponent::UDT,
+            Protocol::UTP => AddrComponent::UTP,
+            Protocol::IPFS => AddrComponent::IPFS(addr.to_vec()),
+            Protocol::QUIC => AddrComponent::QUIC,
+            Protocol::WEBTRANSPORT => AddrComponent::WEBTRANSPORT,
+            Protocol::WS => AddrComponent::WS,
+            Protocol::WSS => AddrComponent::WSS,
+            Protocol::P2P_WEBSOCKET_STAR => AddrComponent::P2P_WEBSOCKET_STAR,
+            Protocol::HTTP => AddrComponent::HTTP,
+            Protocol::HTTPS => AddrComponent::HTTPS,
+            #[cfg(feature = "proto-onion")]
+            Protocol::ONION => {
+                let mut onion = [0; 10];
+                onion.copy_from_slice(&addr[..10]);
+                AddrComponent::ONION(onion, BigEndian::read_u16(&addr[10..]))
+            }
+            #[cfg(feature = "proto-onion")]
+            Protocol::ONION3 => {
+                let mut onion = [0; 35];
+                onion.copy_from_slice(&addr[..35]);
+                AddrComponent::ONION3(onion, BigEndian::read_u16(&addr[35..]))
+            }
+            Protocol::TLS => AddrComponent::TLS,
+            Protocol::MEMORY => {
+                let mut id = [0; 8];
+                id.copy_from_slice(addr);
+                AddrComponent::MEMORY(u64::from_be_bytes(id))
+            }
+            Protocol::PLAINTEXTV2 => AddrComponent::PLAINTEXTV2,
+        }
+    }
+
+    // Appends the binary form of the component if it's valid, checking it
+    // as decoding it would. Components with unknown codes are kept, as
+    // registries that allow them keep them, but only if their code really is
+    // unknown, so that they decode back to the same component.
+    pub(crate) fn write_checked(&self, buf: &mut Vec<u8>) -> ParseResult<()> {
+        if let AddrComponent::Unknown { code, .. } = *self {
+            if Protocol::from_code(code).is_ok() {
+                return Err(ParseError::invalid_code(format!(
+                    "Protocol code {} is known, so it can't be an unknown component",
+                    code
+                )));
+            }
+        }
+        let start = buf.len();
+        self.write_bytes(buf);
+        let mut registry = Registry::new();
+        registry.set_allow_unknown(true);
+        let result = verify_bytes(&buf[start..], &registry).and_then(|()| {
+            let written = Maddr::from_bytes_unchecked(&buf[start..]);
+            let mut decoded = registry.components(written);
+            match (decoded.next(), decoded.next()) {
+                (Some(ref c), None) if c == self => Ok(()),
+                _ => {
+                    let message = format!("{:?} doesn't decode back to itself", self);
+                    Err(ParseError::invalid_address(message))
+                }
+            }
+        });
+        if result.is_err() {
+            buf.truncate(start);
+        }
+        result
+    }
+
+    // Appends the binary form of the component
+    pub(crate) fn write_bytes(&self, buf: &mut Vec<u8>) {
+        let proto = match *self {
+            AddrComponent::Unknown { code, ref bytes } => {
+                buf.write_unsigned_varint_32(code).unwrap();
+                buf.write_unsigned_varint_32(bytes.len() as u32).unwrap();
+                buf.extend(bytes);
+                return;
+            }
+            _ => self.protocol_id().unwrap(),
+        };
+        match *self {
+            AddrComponent::IP4(ref ip) => {
+                write_protocol(Protocol::IP4, buf);
+                write_ip4_to_vec(ip, buf);
+            }
+            AddrComponent::IP6(ref ip) => {
+                write_protocol(Protocol::IP6, buf);
+                write_ip6_to_vec(ip, buf);
+            }
+            AddrComponent::IPCIDR(bits) => write_component(Protocol::IPCIDR, &[bits], buf),
+            AddrComponent::TCP(port) | AddrComponent::UDP(port) |
+            AddrComponent::DCCP(port) | AddrComponent::SCTP(port) => {
+                write_protocol(proto, buf);
+                buf.write_u16::<BigEndian>(port).unwrap();
+            }
+            #[cfg(feature = "proto-dns")]
+            AddrComponent::DNS(ref name) | AddrComponent::DNS4(ref name) |
+            AddrComponent::DNS6(ref name) | AddrComponent::DNSADDR(ref name) => {
+                write_component(proto, name.as_bytes(), buf)
+            }
+            AddrComponent::IP6ZONE(ref zone) => write_component(Protocol::IP6ZONE, zone.as_bytes(), buf),
+            AddrComponent::IPFS(ref mh) => write_component(Protocol::IPFS, mh, buf),
+            #[cfg(feature = "proto-onion")]
+            AddrComponent::ONION(ref onion, port) => {
+                write_protocol(proto, buf);
+                buf.extend(onion);
+                buf.write_u16::<BigEndian>(port).unwrap();
+            }
+            #[cfg(feature = "proto-onion")]
+            AddrComponent::ONION3(ref onion, port) => {
+                write_protocol(proto, buf);
+                buf.extend(&onion[..]);
+                buf.write_u16::<BigEndian>(port).unwrap();
+            }
+            AddrComponent::P2P_CIRCUIT | AddrComponent::UDT | AddrComponent::UTP |
+            AddrComponent::QUIC | AddrComponent::WEBTRANSPORT | AddrComponent::WS |
+            AddrComponent::WSS | AddrComponent::HTTP | AddrComponent::HTTPS |
+            AddrComponent::PLAINTEXTV2 | AddrComponent::P2P_WEBRTC_STAR |
+            AddrComponent::P2P_WEBSOCKET_STAR | AddrComponent::WEBRTC | AddrComponent::TLS => {
+                write_protocol(proto, buf)
+            }
+            AddrComponent::MEMORY(id) => write_component(proto, &id.to_be_bytes(), buf),
+            AddrComponent::Unknown { .. } => unreachable!(),
+        }
+    }
+}
+
+/// Displays the component as it appears in the textual form of a multiaddr,
+/// e.g. "/tcp/8080" or "/ip6/::1". Unknown components are shown as their
+/// code and the hex of their bytes, as `Multiaddr` shows them. Width and
+/// alignment are applied to the whole.
+impl fmt::Display for AddrComponent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if let AddrComponent::Unknown { code, ref bytes } = *self {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            return f.pad(&format!("/{}/{}", code, hex));
+        }
+        let mut bytes = Vec::new();
+        self.write_bytes(&mut bytes);
+        f.pad(&Maddr::from_bytes_unchecked(&bytes).to_string())
+    }
+}
+
+/// Parses the textual form of a single component, such as "/tcp/8080" or
+/// "/p2p/Qm...". Anything that parses to zero or several components, like
+/// the "/ip6zone/eth0/ip6/fe80::1" of "/ip6/fe80::1%eth0", is an error.
+impl FromStr for AddrComponent {
+    type Err = ParseError;
+    fn from_str(s: &str) -> ParseResult<AddrComponent> {
+        let ma = try!(Multiaddr::from_str(s));
+        let mut components = ma.components();
+        match (components.next(), components.next()) {
+            (Some(c), None) => Ok(c),
+            (None, _) => Err(ParseError::other(format!("No component in {:?}", s))),
+            (Some(_), Some(_)) => {
+                Err(ParseError::other(format!("{} has more than one component", s)))
+            }
+        }
+    }
+}
+
+impl Maddr {
+    /// Iterates over the decoded components of the multiaddr.
+    pub fn components<'a>(&'a self) -> Components<'a> {
+        Components { inner: self.iter() }
+    }
+
+    /// Returns the first component, e.g. the ip address of
+    /// "/ip4/1.2.3.4/tcp/80".
+    pub fn first(&self) -> Option<AddrComponent> {
+        self.components().next()
+    }
+
+    /// Returns the last component, e.g. the peer id of
+    /// "/ip4/1.2.3.4/tcp/80/ipfs/Qm...".
+    pub fn last(&self) -> Option<AddrComponent> {
+        self.components().next_back()
+    }
+
+    /// Returns the `n`th component, counting from 0.
+    pub fn get(&self, n: usize) -> Option<AddrComponent> {
+        self.components().nth(n)
+    }
+}
+
+/// Borrows the `n`th component as a multiaddr of its own, e.g. "/tcp/80"
+/// for `ma[1]` if `ma` is "/ip4/1.2.3.4/tcp/80". Panics if there are fewer
+/// than `n + 1` components; see `get` for a decoded, non-panicking version.
+impl Index<usize> for Maddr {
+    type Output = Maddr;
+    fn index(&self, n: usize) -> &Maddr {
+        let mut iter = self.iter();
+        for _ in 0..n {
+            if iter.next().is_none() {
+                break;
+            }
+        }
+        let start = self.as_bytes().len() - iter.remaining().len();
+        if iter.next().is_none() {
+            panic!("component index {} out of range for {}", n, self);
+        }
+        let end = self.as_bytes().len() - iter.remaining().len();
+        Maddr::from_bytes_unchecked(&self.as_bytes()[start..end])
+    }
+}
+
+/// An iterator over the decoded components of a multiaddr.
+pub struct Components<'a> {
+    inner: ::Iter<'a>,
+}
+
+impl<'a> Iterator for Components<'a> {
+    type Item = AddrComponent;
+    fn next(&mut self) -> Option<AddrComponent> {
+        self.inner.next().map(|(p, addr)| AddrComponent::from_parts(p, addr))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Components<'a> {
+    fn next_back(&mut self) -> Option<AddrComponent> {
+        self.inner.next_back().map(|(p, addr)| AddrComponent::from_parts(p, addr))
+    }
+}
+
+impl Multiaddr {
+    /// Appends a component, or returns why it isn't valid, e.g. an `IPFS`
+    /// address that isn't a multihash or a DNS name containing a '/'.
+    pub fn push(&mut self, c: AddrComponent) -> ParseResult<()> {
+        let mut bytes = Vec::new();
+        try!(c.write_checked(&mut bytes));
+        storage_append(&mut self.bytes, &bytes);
+        Ok(())
+    }
+
+    /// Removes the last component and returns it, or None if the multiaddr
+    /// is empty or its last component can't be decoded without the
+    /// `Registry` it was parsed with, in which case it is left unchanged.
+    pub fn pop(&mut self) -> Option<AddrComponent> {
+        let bounds = self.bounds();
+        if bounds.len() < 2 || bounds[bounds.len() - 1] != self.bytes.len() {
+            return None;
+        }
+        let start = bounds[bounds.len() - 2];
+        let c = self.component_at(start);
+        self.splice(start..self.bytes.len(), &[]);
+        Some(c)
+    }
+
+    /// Inserts a component at position `i`, shifting the ones after it, or
+    /// returns why the component isn't valid, as `push` does.
+    ///
+    /// Panics if `i` is greater than the number of components. Only the
+    /// components before the first custom protocol are counted, since the
+    /// rest can't be told apart without its `Registry`; they are kept as they
+    /// are.
+    pub fn insert(&mut self, i: usize, c: AddrComponent) -> ParseResult<()> {
+        let bounds = self.bounds();
+        assert!(i < bounds.len(), "insertion index {} is out of bounds", i);
+        let mut bytes = Vec::new();
+        try!(c.write_checked(&mut bytes));
+        self.splice(bounds[i]..bounds[i], &bytes);
+        Ok(())
+    }
+
+    /// Removes the component at position `i` and returns it.
+    ///
+    /// Panics if `i` is out of bounds. As with `insert`, only the components
+    /// before the first custom protocol are counted.
+    pub fn remove(&mut self, i: usize) -> AddrComponent {
+        let bounds = self.bounds();
+        assert!(i + 1 < bounds.len(), "removal index {} is out of bounds", i);
+        let c = self.component_at(bounds[i]);
+        self.splice(bounds[i]..bounds[i + 1], &[]);
+        c
+    }
+
+    // Offsets at which the components start, followed by where the last
+    // one ends. Components after one that can't be decoded without its
+    // registry aren't included, so the last offset is then short of the end.
+    fn bounds(&self) -> Vec<usize> {
+        let mut bounds = vec![0];
+        let mut iter = self.iter();
+        while iter.next().is_some() {
+            bounds.push(self.bytes.len() - iter.remaining().len());
+        }
+        bounds
+    }
+
+    fn component_at(&self, offset: usize) -> AddrComponent {
+        let (p, addr) = Maddr::from_bytes_unchecked(&self.bytes[offset..]).iter().next().unwrap();
+        AddrComponent::from_parts(p, addr)
+    }
+
+    fn splice(&mut self, range: Range<usize>, with: &[u8]) {
+        let mut bytes = Vec::with_capacity(self.bytes.len() + with.len());
+        bytes.extend(&self.bytes[..range.start]);
+        bytes.extend(with);
+        bytes.extend(&self.bytes[range.end..]);
+        self.bytes = bytes.into();
+    }
+}
+
+/// Collects components into a multiaddr, or the error for the first one
+/// that isn't valid, such as an `IPFS` address that isn't a multihash.
+impl FromIterator<AddrComponent> for ParseResult<Multiaddr> {
+    fn from_iter<I: IntoIterator<Item = AddrComponent>>(iter: I) -> ParseResult<Multiaddr> {
+        let mut bytes = Vec::new();
+        for c in iter {
+            try!(c.write_checked(&mut bytes));
+        }
+        Ok(Multiaddr { bytes: bytes.into() })
+    }
+}
+
+/// Appends the components in order.
+///
+/// Panics if a component isn't valid, such as an `IPFS` address that isn't
+/// a multihash or an empty `IP6ZONE`. Collect into a `ParseResult<Multiaddr>`,
+/// or use `push`, to get an error instead.
+impl Extend<AddrComponent> for Multiaddr {
+    fn extend<I: IntoIterator<Item = AddrComponent>>(&mut self, iter: I) {
+        let mut bytes = Vec::new();
+        for c in iter {
+            bytes.clear();
+            if let Err(e) = c.write_checked(&mut bytes) {
+                panic!("invalid multiaddr component {:?}: {}", c, e);
+            }
+            storage_append(&mut self.bytes, &bytes);
+        }
+    }
+}
+
+impl IntoIterator for Multiaddr {
+    type Item = AddrComponent;
+    type IntoIter = IntoIter;
+    fn into_iter(self) -> IntoIter {
+        IntoIter { ma: self, pos: 0 }
+    }
+}
+
+/// An iterator that moves the decoded components out of a multiaddr.
+pub struct IntoIter {
+    ma: Multiaddr,
+    pos: usize,
+}
+
+impl Iterator for IntoIter {
+    type Item = AddrComponent;
+    fn next(&mut self) -> Option<AddrComponent> {
+        let rest = &self.ma.as_bytes()[self.pos..];
+        let mut iter = Maddr::from_bytes_unchecked(rest).iter();
+        let next = iter.next();
+        self.pos = self.ma.as_bytes().len() - iter.remaining().len();
+        next.map(|(p, addr)| AddrComponent::from_parts(p, addr))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::AddrComponent;
+    use std::net::Ipv4Addr;
+    use std::str::FromStr;
+    use {ErrorKind, Multiaddr, ParseResult, Registry, Size};
+
+    #[test]
+    #[should_panic]
+    fn test_index_out_of_range() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        let _ = &ma[2];
+    }
+
+    #[test]
+    fn test_iterator_traits() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/1234/http/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").unwrap();
+        let components: Vec<_> = ma.components().collect();
+        assert_eq!(components[0], AddrComponent::IP4(Ipv4Addr::new(1, 2, 3, 4)));
+        assert_eq!(components[1], AddrComponent::TCP(1234));
+        assert_eq!(components[2], AddrComponent::HTTP);
+
+        assert_eq!(ma.clone().into_iter().collect::<Vec<_>>(), components);
+        assert_eq!(components.iter().cloned().collect::<ParseResult<Multiaddr>>().unwrap(), ma);
+
+        // drop the http component and bump the port
+        let rewritten: ParseResult<Multiaddr> = ma.clone()
+            .into_iter()
+            .filter(|c| *c != AddrComponent::HTTP)
+            .map(|c| match c {
+                AddrComponent::TCP(port) => AddrComponent::TCP(port + 1),
+                c => c,
+            })
+            .collect();
+        assert_eq!(rewritten.unwrap().to_string(),
+                   "/ip4/1.2.3.4/tcp/1235/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC");
+
+        assert_eq!(ma.first(), Some(AddrComponent::IP4(Ipv4Addr::new(1, 2, 3, 4))));
+        assert_eq!(ma.last(), ma.components().last());
+        assert!(match ma.last() {
+            Some(AddrComponent::IPFS(_)) => true,
+            _ => false,
+        });
+        assert_eq!(ma.components().count(), 4);
+        assert_eq!(Multiaddr::empty().last(), None);
+
+        assert_eq!(ma.get(1), Some(AddrComponent::TCP(1234)));
+        assert_eq!(ma.get(4), None);
+        assert_eq!(ma[1].to_string(), "/tcp/1234");
+        assert_eq!(ma[3].as_bytes().len(), 37);
+
+        let mut ma = Multiaddr::from_str("/ip4/1.2.3.4").unwrap();
+        ma.extend(vec![AddrComponent::UDP(53), AddrComponent::UTP]);
+        assert_eq!(ma.to_string(), "/ip4/1.2.3.4/udp/53/utp");
+    }
+
+    #[test]
+    fn test_collect_invalid() {
+        let invalid = [vec![AddrComponent::IPFS(vec![1, 2, 3])],
+                       vec![AddrComponent::IP6ZONE(String::new())],
+                       vec![AddrComponent::TCP(80),
+                            AddrComponent::Unknown { code: 4, bytes: vec![] }]];
+        for components in &invalid {
+            let ma: ParseResult<Multiaddr> = components.iter().cloned().collect();
+            assert!(ma.is_err(), "{:?}", components);
+        }
+    }
+
+    #[test]
+    fn test_unknown_with_known_code() {
+        let mut ma = Multiaddr::empty();
+        ma.push(AddrComponent::Unknown { code: 4242, bytes: vec![10, 11] }).unwrap();
+        // Written as [6, 1, 0], which would decode as /tcp/256
+        let tcp = AddrComponent::Unknown { code: 6, bytes: vec![0] };
+        assert_eq!(ma.push(tcp).unwrap_err().kind(), ErrorKind::InvalidCode);
+        assert_eq!(ma.as_bytes(), &[0x92, 0x21, 2, 10, 11]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid multiaddr component")]
+    fn test_extend_invalid() {
+        let mut ma = Multiaddr::from_str("/ip4/1.2.3.4").unwrap();
+        ma.extend(vec![AddrComponent::IP6ZONE(String::new())]);
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut ma = Multiaddr::empty();
+        ma.push(AddrComponent::IP4(Ipv4Addr::new(1, 2, 3, 4))).unwrap();
+        ma.push(AddrComponent::HTTP).unwrap();
+        ma.insert(1, AddrComponent::TCP(80)).unwrap();
+        assert_eq!(ma.to_string(), "/ip4/1.2.3.4/tcp/80/http");
+
+        ma.insert(3, AddrComponent::WS).unwrap();
+        assert_eq!(ma.remove(2), AddrComponent::HTTP);
+        assert_eq!(ma.to_string(), "/ip4/1.2.3.4/tcp/80/ws");
+
+        assert_eq!(ma.pop(), Some(AddrComponent::WS));
+        assert_eq!(ma.remove(0), AddrComponent::IP4(Ipv4Addr::new(1, 2, 3, 4)));
+        assert_eq!(ma.pop(), Some(AddrComponent::TCP(80)));
+        assert_eq!(ma.pop(), None);
+        assert!(ma.is_empty());
+    }
+
+    #[test]
+    fn test_editing_custom_protocol() {
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", Size::Fixed(16)).unwrap();
+        let mut ma = registry.parse("/tcp/80/mytag/0102/udp/53").unwrap();
+
+        // the last component can't be found without the registry
+        assert_eq!(ma.pop(), None);
+        assert_eq!(registry.display(&ma).to_string(), "/tcp/80/mytag/0102/udp/53");
+
+        ma.insert(1, AddrComponent::WS).unwrap();
+        assert_eq!(registry.display(&ma).to_string(), "/tcp/80/ws/mytag/0102/udp/53");
+        assert_eq!(ma.remove(0), AddrComponent::TCP(80));
+        assert_eq!(ma.remove(0), AddrComponent::WS);
+        assert_eq!(registry.display(&ma).to_string(), "/mytag/0102/udp/53");
+        assert_eq!(ma.pop(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_remove_custom_protocol() {
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", Size::Fixed(16)).unwrap();
+        let mut ma = registry.parse("/tcp/80/mytag/0102/udp/53").unwrap();
+        ma.remove(1);
+    }
+
+    #[test]
+    fn test_editing_invalid() {
+        let mut ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        assert!(ma.push(AddrComponent::IPFS(vec![1, 2, 3])).is_err());
+        assert!(ma.push(AddrComponent::IP6ZONE(String::new())).is_err());
+        assert!(ma.insert(0, AddrComponent::IP6ZONE("eth/0".to_string())).is_err());
+        #[cfg(feature = "proto-dns")]
+        {
+            assert!(ma.push(AddrComponent::DNS(String::new())).is_err());
+            assert!(ma.insert(1, AddrComponent::DNS4("example.com/x".to_string())).is_err());
+        }
+        assert_eq!(ma.to_string(), "/ip4/1.2.3.4/tcp/80");
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(AddrComponent::from_str("/tcp/8080").unwrap(), AddrComponent::TCP(8080));
+        assert_eq!(AddrComponent::from_str("/ws").unwrap(), AddrComponent::WS);
+        let peer_id = "/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+        assert!(match AddrComponent::from_str(peer_id).unwrap() {
+            AddrComponent::IPFS(ref mh) => mh.len() == 34,
+            _ => false,
+        });
+
+        assert_eq!(AddrComponent::from_str("/tcp/65536").unwrap_err().kind(),
+                   ErrorKind::InvalidAddress);
+        assert_eq!(AddrComponent::from_str("/").unwrap_err().kind(), ErrorKind::Other);
+        assert!(AddrComponent::from_str("/ip4/1.2.3.4/tcp/80").is_err());
+        assert!(AddrComponent::from_str("/ip6/fe80::1%eth0").is_err());
+        assert!(AddrComponent::from_str("tcp/80").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        for s in &["/tcp/8080", "/ip6/::1", "/ws", "/ip6zone/eth0", "/memory/7",
+                   "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC"] {
+            assert_eq!(AddrComponent::from_str(s).unwrap().to_string(), *s);
+        }
+        assert_eq!(AddrComponent::IP4(Ipv4Addr::new(1, 2, 3, 4)).to_string(), "/ip4/1.2.3.4");
+        assert_eq!(format!("[{:<10}]", AddrComponent::UDP(53)), "[/udp/53   ]");
+        let unknown = AddrComponent::Unknown { code: 999, bytes: vec![0xab, 0x01] };
+        assert_eq!(unknown.to_string(), "/999/ab01");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_remove_out_of_bounds() {
+        Multiaddr::from_str("/ip4/1.2.3.4").unwrap().remove(1);
+    }
+}
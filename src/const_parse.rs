@@ -0,0 +1,487 @@
+//! Validation of the textual form of a multiaddr in const fns, which lets the
+//! `multiaddr!` macro reject invalid literals at compile time. Every string it
+//! accepts is accepted by `Multiaddr::from_str`, but it rejects some that
+//! `from_str` accepts: onion, onion3, memory and ip6zone addresses, ip6
+//! addresses with a zone like "fe80::1%eth0", and internationalized domain
+//! names.
+
+use protocol::{Size, PROTOCOLS};
+
+// Returns the index of the next '/' at or after `i`, or `end`.
+const fn next_slash(b: &[u8], mut i: usize, end: usize) -> usize {
+    while i < end && b[i] != b'/' {
+        i += 1;
+    }
+    i
+}
+
+// Compares ignoring ASCII case, as protocol names are parsed
+const fn eq(b: &[u8], start: usize, end: usize, s: &str) -> bool {
+    let s = s.as_bytes();
+    if end - start != s.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < s.len() {
+        if b[start + i].to_ascii_lowercase() != s[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn lookup(b: &[u8], start: usize, end: usize) -> Option<(&'static str, Size)> {
+    if eq(b, start, end, "p2p") {
+        let ipfs = b"ipfs";
+        return lookup(ipfs, 0, ipfs.len());
+    }
+    let mut i = 0;
+    while i < PROTOCOLS.len() {
+        if eq(b, start, end, PROTOCOLS[i].0) {
+            return Some(PROTOCOLS[i]);
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Checks a multiaddr string, returning a description of the first problem.
+pub const fn check(s: &str) -> Result<(), &'static str> {
+    let b = s.as_bytes();
+    let mut end = b.len();
+    // trailing slashes are ignored
+    while end > 0 && b[end - 1] == b'/' {
+        end -= 1;
+    }
+    if end == 0 {
+        return Ok(());
+    }
+    if b[0] != b'/' {
+        return Err("multiaddr must begin with '/'");
+    }
+
+    let mut pos = 1;
+    while pos <= end {
+        let seg_end = next_slash(b, pos, end);
+        let (name, size) = match lookup(b, pos, seg_end) {
+            Some(p) => p,
+            None => return Err("invalid protocol"),
+        };
+        pos = seg_end + 1;
+
+        if let Size::Fixed(0) = size {
+            continue;
+        }
+        if pos > end {
+            return Err("address not found for protocol");
+        }
+
+        let val_end = next_slash(b, pos, end);
+        if let Err(e) = check_address(name, b, pos, val_end) {
+            return Err(e);
+        }
+        pos = val_end + 1;
+    }
+    Ok(())
+}
+
+const fn check_address(name: &str, b: &[u8], start: usize, end: usize)
+                       -> Result<(), &'static str> {
+    let n = name.as_bytes();
+    if eq(n, 0, n.len(), "ip4") {
+        check_ip4(b, start, end)
+    } else if eq(n, 0, n.len(), "ip6") {
+        check_ip6(b, start, end)
+    } else if eq(n, 0, n.len(), "tcp") || eq(n, 0, n.len(), "udp") ||
+              eq(n, 0, n.len(), "sctp") || eq(n, 0, n.len(), "dccp") {
+        check_port(b, start, end)
+    } else if eq(n, 0, n.len(), "ipcidr") {
+        check_u8(b, start, end)
+    } else if eq(n, 0, n.len(), "ipfs") {
+        if start < end && b[start] == b'b' {
+            check_cid(b, start + 1, end)
+        } else {
+            check_multihash(b, start, end)
+        }
+    } else if eq(n, 0, n.len(), "dns") || eq(n, 0, n.len(), "dns4") ||
+              eq(n, 0, n.len(), "dns6") || eq(n, 0, n.len(), "dnsaddr") {
+        check_hostname(b, start, end)
+    } else {
+        Err("addresses of this protocol can't be checked at compile time")
+    }
+}
+
+// The same rules as idn::check_hostname. Non-ASCII names would need to be
+// punycode-encoded first.
+const fn check_hostname(b: &[u8], start: usize, mut end: usize) -> Result<(), &'static str> {
+    if end > start && b[end - 1] == b'.' {
+        end -= 1;
+    }
+    if start == end {
+        return Err("empty domain name");
+    }
+    if end - start > 253 {
+        return Err("domain name longer than 253 characters");
+    }
+    let mut i = start;
+    let mut label_start = start;
+    while i <= end {
+        if i == end || b[i] == b'.' {
+            if i == label_start {
+                return Err("empty label in domain name");
+            }
+            if i - label_start > 63 {
+                return Err("label in domain name longer than 63 characters");
+            }
+            if b[label_start] == b'-' || b[i - 1] == b'-' {
+                return Err("label in domain name begins or ends with '-'");
+            }
+            label_start = i + 1;
+        } else if b[i] >= 0x80 {
+            return Err("internationalized domain names can't be checked at compile time");
+        } else if !(b[i].is_ascii_alphanumeric() || b[i] == b'-' || b[i] == b'_') {
+            return Err("invalid character in domain name");
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+const fn check_port(b: &[u8], i: usize, end: usize) -> Result<(), &'static str> {
+    check_number(b, i, end, 65535, "invalid port number", "port number out of range")
+}
+
+const fn check_u8(b: &[u8], i: usize, end: usize) -> Result<(), &'static str> {
+    check_number(b, i, end, 255, "invalid number", "number out of range")
+}
+
+// Mirrors the FromStr impls of the unsigned integer types
+const fn check_number(b: &[u8], mut i: usize, end: usize, max: u32,
+                      invalid: &'static str, out_of_range: &'static str)
+                      -> Result<(), &'static str> {
+    // from_str accepts a leading '+'
+    if i < end && b[i] == b'+' {
+        i += 1;
+    }
+    if i == end {
+        return Err(invalid);
+    }
+    let mut n: u32 = 0;
+    while i < end {
+        if !b[i].is_ascii_digit() {
+            return Err(invalid);
+        }
+        n = n * 10 + (b[i] - b'0') as u32;
+        if n > max {
+            return Err(out_of_range);
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+const fn check_ip4(b: &[u8], mut i: usize, end: usize) -> Result<(), &'static str> {
+    let mut octets = 0;
+    loop {
+        let start = i;
+        let mut n: u32 = 0;
+        while i < end && i - start < 3 && b[i].is_ascii_digit() {
+            n = n * 10 + (b[i] - b'0') as u32;
+            i += 1;
+        }
+        if i == start || n > 255 || (b[start] == b'0' && i - start > 1) {
+            return Err("invalid ip4 address");
+        }
+        octets += 1;
+
+        if octets == 4 || i == end {
+            break;
+        }
+        if b[i] != b'.' {
+            return Err("invalid ip4 address");
+        }
+        i += 1;
+    }
+    if octets != 4 || i != end {
+        return Err("invalid ip4 address");
+    }
+    Ok(())
+}
+
+const fn check_ip6(b: &[u8], mut i: usize, end: usize) -> Result<(), &'static str> {
+    let mut groups = 0;
+    let mut compressed = false;
+
+    if end - i >= 2 && b[i] == b':' && b[i + 1] == b':' {
+        compressed = true;
+        i += 2;
+    }
+
+    while i < end {
+        // an embedded ip4 address ends the address
+        let mut j = i;
+        while j < end && b[j] != b':' && b[j] != b'.' {
+            j += 1;
+        }
+        if j < end && b[j] == b'.' {
+            if let Err(_) = check_ip4(b, i, end) {
+                return Err("invalid ip6 address");
+            }
+            groups += 2;
+            break;
+        }
+
+        let start = i;
+        while i < end && i - start < 4 && b[i].is_ascii_hexdigit() {
+            i += 1;
+        }
+        if i == start {
+            return Err("invalid ip6 address");
+        }
+        groups += 1;
+
+        if i == end {
+            break;
+        }
+        if b[i] != b':' {
+            return Err("invalid ip6 address");
+        }
+        i += 1;
+        if i < end && b[i] == b':' {
+            if compressed {
+                return Err("invalid ip6 address");
+            }
+            compressed = true;
+            i += 1;
+        } else if i == end {
+            return Err("invalid ip6 address");
+        }
+    }
+
+    // "::" stands for at least one group of zeros
+    if (compressed && groups > 7) || (!compressed && groups != 8) {
+        return Err("invalid ip6 address");
+    }
+    Ok(())
+}
+
+const BASE58_ALPHABET: &'static [u8] =
+    b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+// Checks that the base58 string decodes to a multihash whose length byte
+// matches its digest.
+const fn check_multihash(b: &[u8], start: usize, end: usize) -> Result<(), &'static str> {
+    // decoded bytes, least significant first, not counting leading zeros
+    let mut out = [0u8; 128];
+    let mut len = 0;
+    let mut zeros = 0;
+    let mut leading = true;
+
+    let mut i = start;
+    while i < end {
+        let mut digit = 0;
+        while digit < BASE58_ALPHABET.len() && BASE58_ALPHABET[digit] != b[i] {
+            digit += 1;
+        }
+        if digit == BASE58_ALPHABET.len() {
+            return Err("invalid base58 in multihash");
+        }
+        if leading && digit == 0 {
+            zeros += 1;
+        } else {
+            leading = false;
+        }
+
+        let mut carry = digit as u32;
+        let mut j = 0;
+        while j < len {
+            carry += out[j] as u32 * 58;
+            out[j] = carry as u8;
+            carry >>= 8;
+            j += 1;
+        }
+        while carry > 0 {
+            if len == out.len() {
+                return Err("multihash is too long");
+            }
+            out[len] = carry as u8;
+            len += 1;
+            carry >>= 8;
+        }
+        i += 1;
+    }
+
+    let total = zeros + len;
+    if total < 2 {
+        return Err("multihash is too short");
+    }
+    // the second byte of the multihash is the digest length
+    let digest_len = if zeros >= 2 {
+        0
+    } else if zeros == 1 {
+        out[len - 1]
+    } else {
+        out[len - 2]
+    };
+    if digest_len as usize != total - 2 {
+        return Err("multihash digest length doesn't match");
+    }
+    // the first byte is the hash function, as for check_peer_id
+    let code = if zeros >= 1 { 0 } else { out[len - 1] };
+    check_hash_function(code, digest_len)
+}
+
+// Checks that the base32 string is a CIDv1 with the libp2p-key codec whose
+// multihash is valid, as for check_multihash.
+const fn check_cid(b: &[u8], start: usize, end: usize) -> Result<(), &'static str> {
+    let mut out = [0u8; 128];
+    let mut len = 0;
+    let mut buf = 0u32;
+    let mut bits = 0;
+    let mut i = start;
+    while i < end {
+        let digit = match b[i] {
+            b'a'..=b'z' => b[i] - b'a',
+            b'2'..=b'7' => b[i] - b'2' + 26,
+            _ => return Err("invalid base32 in CID"),
+        };
+        buf = (buf << 5) | digit as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            if len == out.len() {
+                return Err("CID is too long");
+            }
+            out[len] = (buf >> bits) as u8;
+            len += 1;
+        }
+        i += 1;
+    }
+    if bits >= 5 || buf & ((1 << bits) - 1) != 0 {
+        return Err("invalid base32 in CID");
+    }
+
+    // version 1 and the libp2p-key codec, 0x72, are both one-byte varints
+    if len < 4 || out[0] != 1 || out[1] != 0x72 {
+        return Err("CID isn't a CIDv1 peer id");
+    }
+    if out[3] as usize != len - 4 {
+        return Err("multihash digest length doesn't match");
+    }
+    check_hash_function(out[2], out[3])
+}
+
+const fn check_hash_function(code: u8, digest_len: u8) -> Result<(), &'static str> {
+    if code == 0x12 && digest_len != 32 {
+        return Err("sha2-256 digest must be 32 bytes");
+    } else if code == 0 && digest_len > 42 {
+        return Err("identity multihash is too long");
+    } else if code != 0x12 && code != 0 {
+        return Err("multihash code isn't allowed in a peer id");
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::check;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_agrees_with_from_str() {
+        let cases = ["",
+                     "/",
+                     "ip4/1.2.3.4",
+                     "/ip4",
+                     "/ip4/1.2.3.4",
+                     "/ip4/1.2.3.4/",
+                     "/ip4/1.2.3",
+                     "/ip4/1.2.3.4.5",
+                     "/ip4/1.2.3.256",
+                     "/ip4/01.2.3.4",
+                     "/ip4/::1",
+                     "/ip6/::1",
+                     "/ip6/::",
+                     "/ip6/1::",
+                     "/ip6/1:",
+                     "/ip6/:1",
+                     "/ip6/1:2:3:4:5:6:7:8",
+                     "/ip6/1:2:3:4:5:6:7::",
+                     "/ip6/1:2:3:4:5:6:7:8::",
+                     "/ip6/1:2:3:4:5:6:7",
+                     "/ip6/1::2::3",
+                     "/ip6/::ffff:1.2.3.4",
+                     "/ip6/::ffff:1.2.3",
+                     "/ip6/2601:9:4f81:9700:803e:ca65:66e8:c21",
+                     "/ip6/12345::1",
+                     "/tcp/0",
+                     "/tcp/65535",
+                     "/tcp/65536",
+                     "/tcp/+80",
+                     "/tcp/-1",
+                     "/tcp/",
+                     "/udp/1234/sctp/1234/dccp/80",
+                     "/udp/1234/udt/1234",
+                     "/tcp/1234/http",
+                     "/tcp/1234//http",
+                     "/ip4/1.2.3.4/plaintextv2",
+                     "/IP4/1.2.3.4/Tcp/80/P2P/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC",
+                     "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC",
+                     "/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC",
+                     "/p2p",
+                     "/ipfs/5dqoE3aJcBjKUmXmAMuKzTFXqFrcU3",
+                     "/ipfs/5uapmdgoHPBsaeGS6sTGgHiMhz8EmZ",
+                     "/ipfs/13hC12xCn",
+                     "/ipfs/1Eyy5ThQpnMdwLZUFGfmqkLbU7gYyZrSy7qf5EPu8bBwwvqnrQzFhxM46SAQS",
+                     "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNK",
+                     "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNK0",
+                     "/ip4/127.0.0.1/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC/tcp/1234",
+                     "/ip4/127.0.0.1/ipfs/tcp",
+                     "/ipfs/bafzbeigvf25ytwc3akrijfecaotc74udrhcxzh2cx3we5qqnw5vgrei4bm",
+                     "/ipfs/bafybeigvf25ytwc3akrijfecaotc74udrhcxzh2cx3we5qqnw5vgrei4bm",
+                     "/ipfs/bafzbeigvf25ytwc3akrijfecaotc74udrhcxzh2cx3we5qqnw5vgrei4b",
+                     "/ipfs/bafzbeigvf25ytwc3akrijfecaotc74udrhcxzh2cx3we5qqnw5vgrei4bm1",
+                     "/ip4/1.2.3.0/ipcidr/24",
+                     "/ip6/2001:db8::/ipcidr/256",
+                     "/ipcidr/x",
+                     "/dns4/example.com/tcp/443",
+                     "/dnsaddr/bootstrap.libp2p.io",
+                     "/dns6//tcp/443",
+                     "/dns4/example.com./tcp/443",
+                     "/dns4/_dnsaddr.a-b.io",
+                     "/dns4/a..b",
+                     "/dns4/-a.b",
+                     "/dns4/a-.b",
+                     "/dns4/a,b",
+                     "/dns"];
+
+        for case in &cases {
+            assert_eq!(check(case).is_ok(), Multiaddr::from_str(case).is_ok(), "{}", case);
+        }
+
+        // Valid, but not checked at compile time
+        let mut unsupported = vec!["/memory/7",
+                                   "/ip6zone/eth0/ip6/fe80::1",
+                                   "/ip6/fe80::1%eth0/tcp/80"];
+        if cfg!(feature = "proto-onion") {
+            unsupported.push("/onion/timaq4ygg2iegci7:1234");
+            unsupported.push("/onion3/vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd:1234");
+        }
+        if cfg!(feature = "proto-dns") {
+            unsupported.push("/dns4/bücher.example");
+        }
+        for case in &unsupported {
+            assert!(check(case).is_err(), "{}", case);
+            assert!(Multiaddr::from_str(case).is_ok(), "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_macro() {
+        let ma = multiaddr!("/ip4/127.0.0.1/tcp/4001");
+        assert_eq!(ma, Multiaddr::from_str("/ip4/127.0.0.1/tcp/4001").unwrap());
+    }
+}
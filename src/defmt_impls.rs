@@ -0,0 +1,91 @@
+//! `defmt::Format` implementations for logging on embedded targets, enabled
+//! by the `defmt` feature. Values are formatted by defmt on the host, so
+//! nothing here goes through `core::fmt`.
+
+use byteorder::{BigEndian, ByteOrder};
+use defmt::{write, Format, Formatter};
+
+use protocol::Protocol::*;
+use {BufferTooSmall, ErrorKind, ListError, Maddr, Multiaddr, ParseError, Protocol};
+
+/// The textual form, except that ip6 addresses aren't compressed and values
+/// without a simple textual form, such as peer ids, are shown in hex.
+impl Format for Maddr {
+    fn format(&self, f: Formatter) {
+        let mut iter = self.iter();
+        while !iter.remaining().is_empty() {
+            let (proto, addr) = match iter.next() {
+                Some(c) => c,
+                // A custom protocol, which can't be decoded without its registry
+                None => {
+                    write!(f, "/…");
+                    return;
+                }
+            };
+            write!(f, "/{=str}", proto.name());
+            if proto.requires_value() {
+                write!(f, "/");
+                format_value(proto, addr, f);
+            }
+        }
+    }
+}
+
+impl Format for Multiaddr {
+    fn format(&self, f: Formatter) {
+        (**self).format(f)
+    }
+}
+
+fn format_value(proto: Protocol, addr: &[u8], f: Formatter) {
+    match proto {
+        IP4 => write!(f, "{=u8}.{=u8}.{=u8}.{=u8}", addr[0], addr[1], addr[2], addr[3]),
+        IP6 => {
+            for i in 0..8 {
+                if i > 0 {
+                    write!(f, ":");
+                }
+                write!(f, "{=u16:x}", BigEndian::read_u16(&addr[2 * i..]));
+            }
+        }
+        TCP | UDP | DCCP | SCTP => write!(f, "{=u16}", BigEndian::read_u16(addr)),
+        IPCIDR => write!(f, "{=u8}", addr[0]),
+        #[cfg(feature = "proto-dns")]
+        DNS | DNS4 | DNS6 | DNSADDR => {
+            write!(f, "{=str}", ::std::str::from_utf8(addr).unwrap_or("?"))
+        }
+        _ => write!(f, "{=[u8]:x}", addr),
+    }
+}
+
+impl Format for Protocol {
+    fn format(&self, f: Formatter) {
+        write!(f, "{=str}", self.name())
+    }
+}
+
+impl Format for ParseError {
+    fn format(&self, f: Formatter) {
+        let kind = match self.kind() {
+            ErrorKind::InvalidCode => "InvalidCode",
+            ErrorKind::InvalidAddress => "InvalidAddress",
+            ErrorKind::Truncated => "Truncated",
+            ErrorKind::TooLong => "TooLong",
+            ErrorKind::Other => "Other",
+        };
+        write!(f, "{=str}({=str})", kind, self.message())
+    }
+}
+
+impl Format for ListError {
+    fn format(&self, f: Formatter) {
+        write!(f, "entry {=usize} ({=str}): {}", self.index, &self.entry[..], self.error)
+    }
+}
+
+impl Format for BufferTooSmall {
+    fn format(&self, f: Formatter) {
+        write!(f, "buffer of {=usize} bytes is too small, {=usize} needed",
+               self.available, self.needed)
+    }
+}
@@ -0,0 +1,224 @@
+use std::io::{self, Read, Write};
+use varint::VarintWrite;
+
+use {read_varint, ErrorKind, Limits, Maddr, Multiaddr, ParseError};
+
+impl Maddr {
+    /// Writes the binary form of the multiaddr prefixed with its length as
+    /// a varint, so that a sequence of multiaddrs can be read back with
+    /// `Multiaddr::read_delimited`.
+    pub fn write_delimited<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(self.as_bytes().len() + 2);
+        buf.write_unsigned_varint_32(self.as_bytes().len() as u32).unwrap();
+        buf.extend(self.as_bytes());
+        w.write_all(&buf)
+    }
+}
+
+impl Multiaddr {
+    /// Reads a multiaddr written by `write_delimited`. Returns None if the
+    /// reader is at its end, and an `InvalidData` error if the bytes aren't
+    /// a valid multiaddr or are longer than the default `Limits` allow.
+    pub fn read_delimited<R: Read>(r: &mut R) -> io::Result<Option<Multiaddr>> {
+        // The length is a varint of at most 5 bytes
+        let mut prefix = Vec::with_capacity(5);
+        loop {
+            let mut b = [0];
+            if try!(r.read(&mut b)) == 0 {
+                if prefix.is_empty() {
+                    return Ok(None);
+                }
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                          "end of stream inside a length prefix"));
+            }
+            prefix.push(b[0]);
+            if b[0] & 0x80 == 0 || prefix.len() == 5 {
+                break;
+            }
+        }
+        let len = try!(read_varint(&mut &prefix[..]).map_err(|e| invalid_data(e.to_string())));
+        let len = len as usize;
+
+        let max_len = Limits::default().max_len;
+        if len > max_len {
+            return Err(invalid_data(format!("Multiaddr is {} bytes, more than the limit of {}",
+                                            len, max_len)));
+        }
+
+        let mut bytes = vec![0; len];
+        try!(r.read_exact(&mut bytes));
+        // Keep the ParseError as the source, rather than only its message
+        Multiaddr::from_bytes(bytes)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// What `Decoder::decode` found in the bytes fed so far.
+#[derive(Debug)]
+pub enum Decoded {
+    /// The next multiaddr hasn't fully arrived yet.
+    NeedMoreData,
+    Complete(Multiaddr),
+    /// The next frame isn't a valid multiaddr. If its length prefix was
+    /// valid the frame has been skipped, and decoding can go on with the
+    /// next one; otherwise the buffered bytes have been discarded, since the
+    /// stream can't be resynchronized.
+    Error(ParseError),
+}
+
+/// Decodes multiaddrs written by `write_delimited` from chunks of bytes as
+/// they arrive, without blocking on a reader.
+///
+/// Feed each chunk with `feed`, then call `decode` until it returns
+/// `NeedMoreData`. A length prefix above the default `Limits` is rejected
+/// as soon as it is read.
+#[derive(Clone, Debug, Default)]
+pub struct Decoder {
+    buf: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder::default()
+    }
+
+    /// Appends a chunk of bytes from the stream.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Returns the number of bytes fed but not decoded yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Decodes the next multiaddr from the bytes fed so far.
+    pub fn decode(&mut self) -> Decoded {
+        // The length is a varint of at most 5 bytes
+        let prefix_len = match self.buf.iter().take(5).position(|&b| b & 0x80 == 0) {
+            Some(i) => i + 1,
+            None if self.buf.len() < 5 => return Decoded::NeedMoreData,
+            None => 5,
+        };
+        let len = match read_varint(&mut &self.buf[..prefix_len]) {
+            Ok(len) => len as usize,
+            Err(e) => return self.fail(ParseError::varint(ErrorKind::Other, e.to_string(), e)),
+        };
+        let max_len = Limits::default().max_len;
+        if len > max_len {
+            return self.fail(ParseError::too_long(format!("Multiaddr is {} bytes, more than the limit of {}",
+                                                          len, max_len)));
+        }
+        if self.buf.len() < prefix_len + len {
+            return Decoded::NeedMoreData;
+        }
+
+        let frame: Vec<u8> = self.buf.drain(..prefix_len + len).skip(prefix_len).collect();
+        match Multiaddr::from_bytes(frame) {
+            Ok(ma) => Decoded::Complete(ma),
+            Err(e) => Decoded::Error(e),
+        }
+    }
+
+    fn fail(&mut self, e: ParseError) -> Decoded {
+        self.buf.clear();
+        Decoded::Error(e)
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Decoded, Decoder};
+    use std::io::{Cursor, ErrorKind};
+    use std::str::FromStr;
+    use {Multiaddr, ParseError};
+
+    #[test]
+    fn test_delimited() {
+        let addrs: Vec<_> = ["/ip4/1.2.3.4/tcp/80", "/", "/ip6/::1/udp/53/quic"]
+            .iter()
+            .map(|s| Multiaddr::from_str(s).unwrap())
+            .collect();
+        let mut buf = Vec::new();
+        for ma in &addrs {
+            ma.write_delimited(&mut buf).unwrap();
+        }
+        assert_eq!(&buf[..9], &[8, 4, 1, 2, 3, 4, 6, 0, 80]);
+
+        let mut r = Cursor::new(&buf);
+        for ma in &addrs {
+            assert_eq!(&Multiaddr::read_delimited(&mut r).unwrap().unwrap(), ma);
+        }
+        assert!(Multiaddr::read_delimited(&mut r).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_delimited_errors() {
+        let read = |b: &[u8]| Multiaddr::read_delimited(&mut Cursor::new(b)).unwrap_err().kind();
+        assert_eq!(read(&[0x80]), ErrorKind::UnexpectedEof);
+        assert_eq!(read(&[3, 4, 1]), ErrorKind::UnexpectedEof);
+        assert_eq!(read(&[2, 4, 1]), ErrorKind::InvalidData);
+        assert_eq!(read(&[0xff, 0xff, 0xff, 0xff, 0x7f]), ErrorKind::InvalidData);
+        assert_eq!(read(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]), ErrorKind::InvalidData);
+
+        let e = Multiaddr::read_delimited(&mut Cursor::new(&[2, 4, 1])).unwrap_err();
+        let source = e.get_ref().and_then(|e| e.downcast_ref::<ParseError>()).unwrap();
+        assert_eq!(e.to_string(), source.to_string());
+    }
+
+    #[test]
+    fn test_decoder() {
+        let addrs: Vec<_> = ["/ip4/1.2.3.4/tcp/80", "/", "/ip6/::1/udp/53/quic"]
+            .iter()
+            .map(|s| Multiaddr::from_str(s).unwrap())
+            .collect();
+        let mut buf = Vec::new();
+        for ma in &addrs {
+            ma.write_delimited(&mut buf).unwrap();
+        }
+
+        // Feed a byte at a time
+        let mut decoder = Decoder::new();
+        let mut decoded = Vec::new();
+        for b in &buf {
+            decoder.feed(&[*b]);
+            loop {
+                match decoder.decode() {
+                    Decoded::Complete(ma) => decoded.push(ma),
+                    Decoded::NeedMoreData => break,
+                    Decoded::Error(e) => panic!("unexpected {:?}", e),
+                }
+            }
+        }
+        assert_eq!(decoded, addrs);
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn test_decoder_errors() {
+        let mut decoder = Decoder::new();
+        decoder.feed(&[2, 4, 1, 0]);
+        match decoder.decode() {
+            Decoded::Error(_) => {}
+            d => panic!("unexpected {:?}", d),
+        }
+        // The bad frame was skipped
+        match decoder.decode() {
+            Decoded::Complete(ref ma) if ma.is_empty() => {}
+            d => panic!("unexpected {:?}", d),
+        }
+
+        decoder.feed(&[0xff, 0xff, 0xff, 0xff, 0x0f, 1, 2]);
+        match decoder.decode() {
+            Decoded::Error(_) => {}
+            d => panic!("unexpected {:?}", d),
+        }
+        assert_eq!(decoder.buffered(), 0);
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use protocol::Protocol::*;
+use registry::Proto;
+use {message, write_checked_address, Multiaddr, ParseError, ParseOptions, Protocol, Registry, Size};
+
+/// A problem found by `Multiaddr::check_str`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The byte range of the offending part of the string.
+    pub range: Range<usize>,
+    pub message: String,
+    /// What was probably meant, e.g. "tcp" for "tpc".
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        try!(write!(f, "{}..{}: {}", self.range.start, self.range.end, self.message));
+        if let Some(ref s) = self.suggestion {
+            try!(write!(f, ", did you mean '{}'?", s));
+        }
+        Ok(())
+    }
+}
+
+impl Multiaddr {
+    /// Checks the textual form of a multiaddr like `from_str`, but rather
+    /// than stopping at the first problem, reports every invalid segment.
+    /// The result is empty if and only if `from_str` succeeds.
+    ///
+    /// After an unknown protocol, the next segment is taken to be its value
+    /// unless it is a protocol name.
+    pub fn check_str(s: &str) -> Vec<Diagnostic> {
+        let registry = Registry::new();
+        let opts = ParseOptions::default();
+        let mut diags = Vec::new();
+
+        if s.len() > 1 && s.ends_with('/') && !opts.trailing_slash() {
+            diags.push(diagnostic(s.len() - 1..s.len(), "Multiaddr must not end with '/'", None));
+        }
+        let rest = s.trim_end_matches('/');
+        if !rest.is_empty() && !rest.starts_with('/') {
+            diags.push(diagnostic(0..0, "Multiaddr must begin with '/'", Some(format!("/{}", s))));
+        }
+
+        // Segments with their byte offsets
+        let mut segs = Vec::new();
+        let mut pos = if rest.starts_with('/') { 1 } else { 0 };
+        if !rest.is_empty() {
+            for seg in rest[pos..].split('/') {
+                segs.push((pos, seg));
+                pos += seg.len() + 1;
+            }
+        }
+
+        let mut segs = segs.into_iter().peekable();
+        while let Some((pos, seg)) = segs.next() {
+            let range = pos..pos + seg.len();
+            if seg.is_empty() {
+                if !opts.empty_segments() {
+                    diags.push(diagnostic(range, "Empty segment", None));
+                }
+                continue;
+            }
+
+            let p = match registry.lookup_name_with(seg, &opts) {
+                Some(p) => p,
+                None => {
+                    diags.push(diagnostic(range, &format!("Unknown protocol '{}'", seg),
+                                          suggest(seg)));
+                    // Skip what is probably its value
+                    let next_is_value = match segs.peek() {
+                        Some(&(_, next)) => {
+                            !next.is_empty() && registry.lookup_name_with(next, &opts).is_none()
+                        }
+                        None => false,
+                    };
+                    if next_is_value {
+                        segs.next();
+                    }
+                    continue;
+                }
+            };
+            if let Size::Fixed(0) = p.size() {
+                continue;
+            }
+
+            let (pos, addr) = match segs.next() {
+                Some(next) => next,
+                None => {
+                    let message = format!("Address not found for protocol {}", p.name());
+                    diags.push(diagnostic(range, &message, None));
+                    break;
+                }
+            };
+            let range = pos..pos + addr.len();
+            let result = match p {
+                Proto::Builtin(TCP) | Proto::Builtin(UDP) | Proto::Builtin(SCTP) |
+                Proto::Builtin(DCCP) if !opts.zero_port() && addr.parse::<u16>() == Ok(0) => {
+                    Err(ParseError::invalid_address(format!("Port 0 is not allowed")))
+                }
+                Proto::Builtin(p) => write_checked_address(addr, p, &registry, &mut Vec::new()),
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                diags.push(diagnostic(range, &message(e), None));
+            }
+        }
+
+        // Problems with the whole address, such as its length
+        if diags.is_empty() {
+            if let Err(e) = Multiaddr::from_str(s) {
+                diags.push(diagnostic(0..s.len(), &message(e), None));
+            }
+        }
+        diags
+    }
+}
+
+fn diagnostic(range: Range<usize>, message: &str, suggestion: Option<String>) -> Diagnostic {
+    Diagnostic {
+        range: range,
+        message: message.to_string(),
+        suggestion: suggestion,
+    }
+}
+
+// The protocol name closest to `name`, if it is a plausible typo of it
+fn suggest(name: &str) -> Option<String> {
+    let lower = name.to_lowercase();
+    let names = Protocol::ALL.iter().map(|p| p.name()).chain(Some("p2p"));
+    names.map(|n| (edit_distance(&lower, n), n))
+         .filter(|&(d, n)| d <= 2 && d < n.len())
+         .min_by_key(|&(d, _)| d)
+         .map(|(_, n)| n.to_string())
+}
+
+// Levenshtein distance, counting a swap of adjacent characters as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..a.len() + 1 {
+        d[i][0] = i;
+    }
+    for j in 0..b.len() + 1 {
+        d[0][j] = j;
+    }
+    for i in 1..a.len() + 1 {
+        for j in 1..b.len() + 1 {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Diagnostic;
+    use Multiaddr;
+
+    #[test]
+    fn test_check_str() {
+        assert!(Multiaddr::check_str("/ip4/1.2.3.4/tcp/80").is_empty());
+        assert!(Multiaddr::check_str("/").is_empty());
+
+        let diags = Multiaddr::check_str("/ip4/1.2.3.256/tpc/80/ws//udp");
+        assert_eq!(diags.len(), 4);
+        assert_eq!(diags[0].range, 5..14);
+        assert_eq!(diags[1],
+                   Diagnostic {
+                       range: 15..18,
+                       message: "Unknown protocol 'tpc'".to_string(),
+                       suggestion: Some("tcp".to_string()),
+                   });
+        assert_eq!(diags[1].to_string(), "15..18: Unknown protocol 'tpc', did you mean 'tcp'?");
+        assert_eq!(diags[2].range, 25..25);
+        assert_eq!(diags[3].message, "Address not found for protocol udp");
+
+        let diags = Multiaddr::check_str("ip4/1.2.3.4/TCP/0/foo/bar");
+        assert_eq!(diags[0].suggestion, Some("/ip4/1.2.3.4/TCP/0/foo/bar".to_string()));
+        assert_eq!(diags[1].range, 18..21);
+        assert_eq!(diags[1].suggestion, None);
+        assert_eq!(diags.len(), 2);
+    }
+}
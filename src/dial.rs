@@ -0,0 +1,188 @@
+use std::cmp::Ordering;
+use std::net::IpAddr;
+
+use protocol::Protocol::*;
+use scope::Scope;
+use {Maddr, Multiaddr};
+
+// 0 for quic, 1 for tcp, 2 for anything else
+fn transport_rank(ma: &Maddr) -> u8 {
+    let mut rank = 2;
+    for (p, _) in ma.iter() {
+        match p {
+            QUIC => return 0,
+            TCP => rank = 1,
+            _ => (),
+        }
+    }
+    rank
+}
+
+/// Compares two addresses by how likely they are to be dialable: public
+/// before private before loopback before unroutable ones, then quic before
+/// tcp before other transports.
+pub fn dial_order(a: &Maddr, b: &Maddr) -> Ordering {
+    (rank_scope(a), transport_rank(a)).cmp(&(rank_scope(b), transport_rank(b)))
+}
+
+// Addresses without an ip or dns component go last
+fn rank_scope(ma: &Maddr) -> (bool, Option<Scope>) {
+    let scope = ma.scope();
+    (scope.is_none(), scope)
+}
+
+/// Sorts addresses for dialing, best first, according to `dial_order`.
+/// Within addresses that rank equally ip6 and ip4 alternate, starting with
+/// ip6, so that neither family is tried only after the other has been
+/// exhausted. The sort is otherwise stable.
+pub fn sort_for_dialing(addrs: &mut [Multiaddr]) {
+    addrs.sort_by(|a, b| dial_order(a, b));
+
+    let mut start = 0;
+    while start < addrs.len() {
+        let mut end = start + 1;
+        while end < addrs.len() && dial_order(&addrs[start], &addrs[end]) == Ordering::Equal {
+            end += 1;
+        }
+        interleave_families(&mut addrs[start..end]);
+        start = end;
+    }
+}
+
+/// Orders addresses as RFC 8305 (Happy Eyeballs) recommends for connection
+/// attempts: alternating between ip6 and ip4, starting with the family of
+/// the first address, and otherwise keeping the order they are given in.
+/// Unlike `sort_for_dialing`, the addresses aren't ranked, so the caller's
+/// (or resolver's) preference is kept. Addresses without an ip component
+/// go last.
+pub fn happy_eyeballs_order<I>(addrs: I) -> Vec<Multiaddr>
+    where I: IntoIterator<Item = Multiaddr>
+{
+    let mut v6 = Vec::new();
+    let mut v4 = Vec::new();
+    let mut other = Vec::new();
+    let mut v6_first = None;
+    for ma in addrs {
+        match ma.ip() {
+            Some(IpAddr::V6(_)) => {
+                v6_first.get_or_insert(true);
+                v6.push(ma);
+            }
+            Some(IpAddr::V4(_)) => {
+                v6_first.get_or_insert(false);
+                v4.push(ma);
+            }
+            None => other.push(ma),
+        }
+    }
+
+    let (first, second) = if v6_first == Some(true) { (v6, v4) } else { (v4, v6) };
+    let mut ordered = Vec::with_capacity(first.len() + second.len() + other.len());
+    let mut first = first.into_iter();
+    let mut second = second.into_iter();
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+    ordered.extend(other);
+    ordered
+}
+
+fn interleave_families(addrs: &mut [Multiaddr]) {
+    let (mut v6, mut rest): (Vec<_>, Vec<_>) = addrs.iter().cloned().partition(|ma| {
+        match ma.ip() {
+            Some(IpAddr::V6(_)) => true,
+            _ => false,
+        }
+    });
+    if v6.is_empty() || rest.is_empty() {
+        return;
+    }
+
+    let mut v6 = v6.drain(..);
+    let mut rest = rest.drain(..);
+    let mut i = 0;
+    loop {
+        let mut done = true;
+        if let Some(ma) = v6.next() {
+            addrs[i] = ma;
+            i += 1;
+            done = false;
+        }
+        if let Some(ma) = rest.next() {
+            addrs[i] = ma;
+            i += 1;
+            done = false;
+        }
+        if done {
+            break;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{happy_eyeballs_order, sort_for_dialing};
+    use std::str::FromStr;
+    use Multiaddr;
+
+    fn addrs(v: &[&str]) -> Vec<Multiaddr> {
+        v.iter().map(|s| Multiaddr::from_str(s).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_sort_for_dialing() {
+        let mut v = addrs(&["/ip4/127.0.0.1/tcp/4001",
+                            "/ip4/192.168.1.2/tcp/4001",
+                            "/ip4/8.8.8.8/udt",
+                            "/ip4/8.8.8.8/tcp/4001",
+                            "/ip4/8.8.4.4/udp/4001/quic",
+                            "/ip6/fe80::1/tcp/4001",
+                            "/ip6/::1/udp/4001/quic"]);
+        sort_for_dialing(&mut v);
+        assert_eq!(v, addrs(&["/ip4/8.8.4.4/udp/4001/quic",
+                              "/ip4/8.8.8.8/tcp/4001",
+                              "/ip4/8.8.8.8/udt",
+                              "/ip6/fe80::1/tcp/4001",
+                              "/ip4/192.168.1.2/tcp/4001",
+                              "/ip6/::1/udp/4001/quic",
+                              "/ip4/127.0.0.1/tcp/4001"]));
+    }
+
+    #[test]
+    fn test_interleave_families() {
+        let mut v = addrs(&["/ip4/1.1.1.1/tcp/1",
+                            "/ip4/1.1.1.2/tcp/1",
+                            "/ip4/1.1.1.3/tcp/1",
+                            "/ip6/2604:1380::1/tcp/1",
+                            "/ip6/2604:1380::2/tcp/1"]);
+        sort_for_dialing(&mut v);
+        assert_eq!(v, addrs(&["/ip6/2604:1380::1/tcp/1",
+                              "/ip4/1.1.1.1/tcp/1",
+                              "/ip6/2604:1380::2/tcp/1",
+                              "/ip4/1.1.1.2/tcp/1",
+                              "/ip4/1.1.1.3/tcp/1"]));
+    }
+
+    #[test]
+    fn test_happy_eyeballs_order() {
+        let v = addrs(&["/ip4/1.1.1.1/tcp/1",
+                        "/utp",
+                        "/ip4/1.1.1.2/tcp/1",
+                        "/ip6/2604:1380::1/tcp/1",
+                        "/ip4/1.1.1.3/udp/1/quic",
+                        "/ip6/2604:1380::2/tcp/1",
+                        "/ip6/2604:1380::3/tcp/1"]);
+        assert_eq!(happy_eyeballs_order(v), addrs(&["/ip4/1.1.1.1/tcp/1",
+                                                    "/ip6/2604:1380::1/tcp/1",
+                                                    "/ip4/1.1.1.2/tcp/1",
+                                                    "/ip6/2604:1380::2/tcp/1",
+                                                    "/ip4/1.1.1.3/udp/1/quic",
+                                                    "/ip6/2604:1380::3/tcp/1",
+                                                    "/utp"]));
+        assert!(happy_eyeballs_order(Vec::new()).is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+
+use protocol::Protocol::*;
+use {Maddr, Multiaddr};
+
+/// The changes between two lists of multiaddrs, as found by `diff`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AddrDiff {
+    /// Multiaddrs in the new list but not the old one, in their order there.
+    pub added: Vec<Multiaddr>,
+    /// Multiaddrs in the old list but not the new one, in their order there.
+    pub removed: Vec<Multiaddr>,
+}
+
+impl AddrDiff {
+    /// Returns true if the lists have the same multiaddrs.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compares two lists of multiaddrs, e.g. the addresses announced before
+/// and after an interface change. Order and duplicates don't matter.
+pub fn diff<'a, I, J>(old: I, new: J) -> AddrDiff
+    where I: IntoIterator<Item = &'a Multiaddr>,
+          J: IntoIterator<Item = &'a Multiaddr>
+{
+    diff_by(old, new, |ma| ma.to_owned())
+}
+
+/// Like `diff`, but a trailing /ipfs component is ignored, so
+/// "/ip4/1.2.3.4/tcp/1/ipfs/Qm..." and "/ip4/1.2.3.4/tcp/1" are the same.
+pub fn diff_ignoring_peer_id<'a, I, J>(old: I, new: J) -> AddrDiff
+    where I: IntoIterator<Item = &'a Multiaddr>,
+          J: IntoIterator<Item = &'a Multiaddr>
+{
+    diff_by(old, new, without_trailing_peer_id)
+}
+
+fn diff_by<'a, I, J, F>(old: I, new: J, key: F) -> AddrDiff
+    where I: IntoIterator<Item = &'a Multiaddr>,
+          J: IntoIterator<Item = &'a Multiaddr>,
+          F: Fn(&Maddr) -> Multiaddr
+{
+    let old: Vec<_> = old.into_iter().collect();
+    let new: Vec<_> = new.into_iter().collect();
+    let old_keys: HashSet<_> = old.iter().map(|ma| key(ma)).collect();
+    let new_keys: HashSet<_> = new.iter().map(|ma| key(ma)).collect();
+
+    let mut diff = AddrDiff::default();
+    let mut seen = HashSet::new();
+    for ma in new {
+        let k = key(ma);
+        if !old_keys.contains(&k) && seen.insert(k) {
+            diff.added.push(ma.clone());
+        }
+    }
+    for ma in old {
+        let k = key(ma);
+        if !new_keys.contains(&k) && seen.insert(k) {
+            diff.removed.push(ma.clone());
+        }
+    }
+    diff
+}
+
+fn without_trailing_peer_id(ma: &Maddr) -> Multiaddr {
+    match ma.iter().last() {
+        Some((IPFS, _)) => ma.decapsulate_code(IPFS),
+        _ => ma.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, diff_ignoring_peer_id};
+    use std::str::FromStr;
+    use Multiaddr;
+
+    fn addrs(v: &[&str]) -> Vec<Multiaddr> {
+        v.iter().map(|s| Multiaddr::from_str(s).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = addrs(&["/ip4/1.1.1.1/tcp/1", "/ip4/2.2.2.2/tcp/1", "/ip4/2.2.2.2/tcp/1"]);
+        let new = addrs(&["/ip4/2.2.2.2/tcp/1", "/ip4/3.3.3.3/tcp/1"]);
+        let d = diff(&old, &new);
+        assert_eq!(d.added, addrs(&["/ip4/3.3.3.3/tcp/1"]));
+        assert_eq!(d.removed, addrs(&["/ip4/1.1.1.1/tcp/1"]));
+        assert!(diff(&new, &new.iter().rev().cloned().collect::<Vec<_>>()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_ignoring_peer_id() {
+        let peer = "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+        let old = addrs(&["/ip4/1.1.1.1/tcp/1", "/ip4/2.2.2.2/tcp/1"]);
+        let new = addrs(&[&format!("/ip4/1.1.1.1/tcp/1{}", peer), "/ip4/2.2.2.2/tcp/1"]);
+        assert!(diff_ignoring_peer_id(&old, &new).is_empty());
+        assert_eq!(diff(&old, &new).added.len(), 1);
+    }
+}
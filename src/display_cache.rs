@@ -0,0 +1,112 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+use {Maddr, Multiaddr};
+
+/// A multiaddr that keeps its textual form once it has been displayed, so
+/// that logging the same address over and over formats it only once.
+///
+/// It derefs to `Maddr`, and compares and hashes like the multiaddr it
+/// wraps.
+pub struct DisplayCache {
+    ma: Multiaddr,
+    text: OnceLock<String>,
+}
+
+impl DisplayCache {
+    pub fn new(ma: Multiaddr) -> DisplayCache {
+        DisplayCache {
+            ma: ma,
+            text: OnceLock::new(),
+        }
+    }
+
+    /// Returns the textual form, formatting it on the first call.
+    pub fn as_str(&self) -> &str {
+        self.text.get_or_init(|| self.ma.to_string())
+    }
+
+    pub fn into_inner(self) -> Multiaddr {
+        self.ma
+    }
+}
+
+impl From<Multiaddr> for DisplayCache {
+    fn from(ma: Multiaddr) -> DisplayCache {
+        DisplayCache::new(ma)
+    }
+}
+
+impl Deref for DisplayCache {
+    type Target = Maddr;
+    fn deref(&self) -> &Maddr {
+        &self.ma
+    }
+}
+
+impl AsRef<Maddr> for DisplayCache {
+    fn as_ref(&self) -> &Maddr {
+        &self.ma
+    }
+}
+
+/// Clones keep the textual form if it has been computed.
+impl Clone for DisplayCache {
+    fn clone(&self) -> DisplayCache {
+        DisplayCache {
+            ma: self.ma.clone(),
+            text: self.text.clone(),
+        }
+    }
+}
+
+impl PartialEq for DisplayCache {
+    fn eq(&self, other: &DisplayCache) -> bool {
+        self.ma == other.ma
+    }
+}
+
+impl Eq for DisplayCache {}
+
+impl Hash for DisplayCache {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ma.hash(state)
+    }
+}
+
+impl fmt::Display for DisplayCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for DisplayCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Debug::fmt(&self.ma, f)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::DisplayCache;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_display_cache() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        let cached = DisplayCache::from(ma.clone());
+        assert!(cached.text.get().is_none());
+        assert_eq!(cached.to_string(), "/ip4/1.2.3.4/tcp/80");
+        assert_eq!(cached.text.get().map(|s| &s[..]), Some("/ip4/1.2.3.4/tcp/80"));
+
+        let clone = cached.clone();
+        assert!(clone.text.get().is_some());
+        assert_eq!(clone, cached);
+        assert_eq!(cached.port(), Some(80));
+        assert_eq!(cached.into_inner(), ma);
+    }
+}
@@ -0,0 +1,498 @@
+//! Resolution of dns, dns4, dns6 and dnsaddr components into ip addresses,
+//! enabled by the `dns` feature.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use protocol::Protocol::*;
+use {sort_srv_records, srv_name, write_component, Maddr, Multiaddr, SrvRecord, Transport};
+
+/// How many levels of dnsaddr records are followed before giving up.
+pub const MAX_DNSADDR_DEPTH: usize = 8;
+
+/// The lookups needed to resolve multiaddrs.
+pub trait Resolver {
+    /// Looks up the A and AAAA records of `name`.
+    fn lookup_ip(&self, name: &str) -> io::Result<Vec<IpAddr>>;
+
+    /// Looks up the TXT records of `name`.
+    fn lookup_txt(&self, name: &str) -> io::Result<Vec<String>>;
+
+    /// Like `lookup_ip`, also returning the TTL of the records if the
+    /// resolver knows it. The default implementation doesn't.
+    fn lookup_ip_with_ttl(&self, name: &str) -> io::Result<(Vec<IpAddr>, Option<Duration>)> {
+        self.lookup_ip(name).map(|ips| (ips, None))
+    }
+
+    /// Like `lookup_txt`, also returning the TTL of the records if the
+    /// resolver knows it. The default implementation doesn't.
+    fn lookup_txt_with_ttl(&self, name: &str) -> io::Result<(Vec<String>, Option<Duration>)> {
+        self.lookup_txt(name).map(|txts| (txts, None))
+    }
+
+    /// Looks up the SRV records of `name`. The default implementation fails
+    /// with `io::ErrorKind::Unsupported`, for resolvers that can't look them
+    /// up.
+    fn lookup_srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+        Err(io::Error::new(io::ErrorKind::Unsupported,
+                           format!("this resolver can't look up the SRV records of {}", name)))
+    }
+
+    /// Looks up the SRV records of `service` at `domain`, e.g. those of
+    /// "_ipfs._tcp.example.com", and converts them into multiaddrs like
+    /// "/dns4/node1.example.com/tcp/4001" in the order to try them. Records
+    /// that can't be converted, such as the "." of unavailable services,
+    /// are skipped.
+    fn resolve_srv(&self, service: &str, transport: Transport, domain: &str)
+                   -> io::Result<Vec<Multiaddr>> {
+        let mut records = try!(self.lookup_srv(&srv_name(service, transport, domain)));
+        sort_srv_records(&mut records);
+        Ok(records.iter().filter_map(|r| r.to_multiaddr(transport).ok()).collect())
+    }
+
+    /// Expands the first dns, dns4, dns6 or dnsaddr component of `ma` into
+    /// concrete addresses. A multiaddr without such a component resolves to
+    /// itself.
+    ///
+    /// dnsaddr components are looked up in the TXT records of
+    /// `_dnsaddr.<name>`, which are followed recursively up to
+    /// `MAX_DNSADDR_DEPTH` levels. If `ma` continues after the dnsaddr
+    /// component, e.g. with a peer id, records with a different peer id are
+    /// skipped.
+    fn resolve(&self, ma: &Maddr) -> io::Result<Vec<Multiaddr>> {
+        let result = resolve_with_depth(self, ma, MAX_DNSADDR_DEPTH);
+        if let Err(ref e) = result {
+            ::log_rejected("multiaddr resolution", &ma.to_string(), e);
+        }
+        result
+    }
+}
+
+/// Resolves names with the operating system's resolver, which only looks up
+/// addresses. TXT and SRV lookups, and so resolving dnsaddr components, fail
+/// with `io::ErrorKind::Unsupported`; implement `Resolver` on top of a DNS
+/// client library to resolve dnsaddr.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn lookup_ip(&self, name: &str) -> io::Result<Vec<IpAddr>> {
+        let addrs = try!((name, 0).to_socket_addrs());
+        let mut ips = Vec::new();
+        for addr in addrs {
+            if !ips.contains(&addr.ip()) {
+                ips.push(addr.ip());
+            }
+        }
+        Ok(ips)
+    }
+
+    fn lookup_txt(&self, _: &str) -> io::Result<Vec<String>> {
+        Err(io::Error::new(io::ErrorKind::Unsupported,
+                           "the system resolver can't look up TXT records"))
+    }
+}
+
+/// Wraps a resolver, caching the results of its lookups until their TTL
+/// expires, so that repeatedly resolving the same multiaddr doesn't repeat
+/// the lookups. Failed lookups are cached too, for the negative TTL.
+///
+/// ```ignore
+/// let resolver = CachingResolver::new(MyResolver).max_ttl(Duration::from_secs(600));
+/// let ma = Multiaddr::from_str("/dnsaddr/bootstrap.libp2p.io").unwrap();
+/// let addrs = resolver.resolve(&ma)?;
+/// ```
+#[derive(Debug)]
+pub struct CachingResolver<R> {
+    inner: R,
+    default_ttl: Duration,
+    max_ttl: Duration,
+    negative_ttl: Duration,
+    ips: Mutex<HashMap<String, CacheEntry<IpAddr>>>,
+    txts: Mutex<HashMap<String, CacheEntry<String>>>,
+    srvs: Mutex<HashMap<String, CacheEntry<SrvRecord>>>,
+}
+
+#[derive(Debug)]
+struct CacheEntry<T> {
+    // io::Error isn't Clone, so errors are cached as their kind and message
+    result: Result<Vec<T>, (io::ErrorKind, String)>,
+    expires: Instant,
+}
+
+impl<R: Resolver> CachingResolver<R> {
+    /// Caches records for a minute if `inner` doesn't report their TTL, at
+    /// most an hour, and failures for 5 seconds.
+    pub fn new(inner: R) -> CachingResolver<R> {
+        CachingResolver {
+            inner: inner,
+            default_ttl: Duration::from_secs(60),
+            max_ttl: Duration::from_secs(3600),
+            negative_ttl: Duration::from_secs(5),
+            ips: Mutex::new(HashMap::new()),
+            txts: Mutex::new(HashMap::new()),
+            srvs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets how long records are cached when `inner` doesn't report a TTL.
+    pub fn default_ttl(mut self, ttl: Duration) -> CachingResolver<R> {
+        self.default_ttl = ttl;
+        self
+    }
+
+    /// Sets the longest records are cached, whatever their TTL.
+    pub fn max_ttl(mut self, ttl: Duration) -> CachingResolver<R> {
+        self.max_ttl = ttl;
+        self
+    }
+
+    /// Sets how long failed lookups are cached. Zero disables negative
+    /// caching.
+    pub fn negative_ttl(mut self, ttl: Duration) -> CachingResolver<R> {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Forgets the cached records of `name`, including the dnsaddr records
+    /// at `_dnsaddr.<name>`.
+    pub fn invalidate(&self, name: &str) {
+        let dnsaddr = format!("_dnsaddr.{}", name);
+        self.ips.lock().unwrap().remove(name);
+        let mut txts = self.txts.lock().unwrap();
+        txts.remove(name);
+        txts.remove(&dnsaddr);
+        self.srvs.lock().unwrap().remove(name);
+    }
+
+    /// Forgets all cached records.
+    pub fn clear(&self) {
+        self.ips.lock().unwrap().clear();
+        self.txts.lock().unwrap().clear();
+        self.srvs.lock().unwrap().clear();
+    }
+
+    fn cached<T, F>(&self, cache: &Mutex<HashMap<String, CacheEntry<T>>>, name: &str, lookup: F)
+                    -> io::Result<(Vec<T>, Option<Duration>)>
+        where T: Clone,
+              F: FnOnce(&str) -> io::Result<(Vec<T>, Option<Duration>)>
+    {
+        let now = Instant::now();
+        if let Some(entry) = cache.lock().unwrap().get(name) {
+            if entry.expires > now {
+                let ttl = Some(entry.expires - now);
+                return match entry.result {
+                    Ok(ref records) => Ok((records.clone(), ttl)),
+                    Err((kind, ref message)) => Err(io::Error::new(kind, message.clone())),
+                };
+            }
+        }
+
+        // The lock isn't held during the lookup, so concurrent lookups of
+        // an uncached name may both reach the inner resolver
+        let (result, cached, ttl) = match lookup(name) {
+            Ok((records, ttl)) => {
+                let ttl = ::std::cmp::min(ttl.unwrap_or(self.default_ttl), self.max_ttl);
+                let cached = Ok(records.clone());
+                (Ok((records, Some(ttl))), cached, ttl)
+            }
+            Err(e) => {
+                let cached = Err((e.kind(), e.to_string()));
+                (Err(e), cached, self.negative_ttl)
+            }
+        };
+        if ttl > Duration::from_secs(0) {
+            let entry = CacheEntry {
+                result: cached,
+                expires: now + ttl,
+            };
+            cache.lock().unwrap().insert(name.to_string(), entry);
+        }
+        result
+    }
+}
+
+impl<R: Resolver> Resolver for CachingResolver<R> {
+    fn lookup_ip(&self, name: &str) -> io::Result<Vec<IpAddr>> {
+        self.lookup_ip_with_ttl(name).map(|(ips, _)| ips)
+    }
+
+    fn lookup_txt(&self, name: &str) -> io::Result<Vec<String>> {
+        self.lookup_txt_with_ttl(name).map(|(txts, _)| txts)
+    }
+
+    /// Returns the time left until the records expire from the cache.
+    fn lookup_ip_with_ttl(&self, name: &str) -> io::Result<(Vec<IpAddr>, Option<Duration>)> {
+        self.cached(&self.ips, name, |name| self.inner.lookup_ip_with_ttl(name))
+    }
+
+    /// Returns the time left until the records expire from the cache.
+    fn lookup_txt_with_ttl(&self, name: &str) -> io::Result<(Vec<String>, Option<Duration>)> {
+        self.cached(&self.txts, name, |name| self.inner.lookup_txt_with_ttl(name))
+    }
+
+    /// SRV records are cached for the default TTL.
+    fn lookup_srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+        let result = self.cached(&self.srvs, name, |name| {
+            self.inner.lookup_srv(name).map(|records| (records, None))
+        });
+        result.map(|(records, _)| records)
+    }
+}
+
+fn resolve_with_depth<R: Resolver + ?Sized>(resolver: &R, ma: &Maddr, depth: usize)
+                                            -> io::Result<Vec<Multiaddr>> {
+    let components: Vec<_> = ma.iter().collect();
+    let i = match components.iter().position(|&(p, _)| {
+        p == DNS || p == DNS4 || p == DNS6 || p == DNSADDR
+    }) {
+        Some(i) => i,
+        None => return Ok(vec![ma.to_owned()]),
+    };
+
+    let (proto, name) = components[i];
+    let name = String::from_utf8_lossy(name);
+    let before = Multiaddr::join(ma.split().into_iter().take(i));
+    let after = Multiaddr::join(ma.split().into_iter().skip(i + 1));
+
+    if proto == DNSADDR {
+        if depth == 0 {
+            return Err(io::Error::new(io::ErrorKind::Other,
+                                      format!("too many levels of dnsaddr records for {}", name)));
+        }
+
+        let mut resolved = Vec::new();
+        for record in try!(resolver.lookup_txt(&format!("_dnsaddr.{}", name))) {
+            let record = match record.trim().strip_prefix("dnsaddr=")
+                                     .and_then(|s| Multiaddr::from_str(s).ok()) {
+                Some(record) => record,
+                None => continue,
+            };
+            let full = if record.ends_with(&after) {
+                Multiaddr::join(&[&before, &record])
+            } else if record.value_bytes_for(IPFS).is_none() {
+                // e.g. another dnsaddr, whose records get filtered in turn
+                Multiaddr::join(&[&before, &record, &after])
+            } else {
+                continue;
+            };
+            resolved.extend(try!(resolve_with_depth(resolver, &full, depth - 1)));
+        }
+        return Ok(resolved);
+    }
+
+    let mut resolved = Vec::new();
+    for ip in try!(resolver.lookup_ip(&name)) {
+        let mut bytes = before.as_bytes().to_vec();
+        match ip {
+            IpAddr::V4(ip) if proto != DNS6 => write_component(IP4, &ip.octets(), &mut bytes),
+            IpAddr::V6(ip) if proto != DNS4 => write_component(IP6, &ip.octets(), &mut bytes),
+            _ => continue,
+        }
+        bytes.extend(after.as_bytes());
+        resolved.push(Multiaddr { bytes: bytes.into() });
+    }
+    Ok(resolved)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{CachingResolver, Resolver, SystemResolver};
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::io;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use {Multiaddr, SrvRecord, Transport};
+
+    struct MockResolver {
+        ips: HashMap<&'static str, Vec<&'static str>>,
+        txts: HashMap<&'static str, Vec<&'static str>>,
+        srvs: HashMap<&'static str, Vec<SrvRecord>>,
+    }
+
+    impl Resolver for MockResolver {
+        fn lookup_ip(&self, name: &str) -> io::Result<Vec<IpAddr>> {
+            Ok(self.ips.get(name).map_or(vec![], |v| {
+                v.iter().map(|s| IpAddr::from_str(s).unwrap()).collect()
+            }))
+        }
+
+        fn lookup_txt(&self, name: &str) -> io::Result<Vec<String>> {
+            Ok(self.txts.get(name).map_or(vec![], |v| v.iter().map(|s| s.to_string()).collect()))
+        }
+
+        fn lookup_srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+            Ok(self.srvs.get(name).cloned().unwrap_or_default())
+        }
+    }
+
+    fn mock() -> MockResolver {
+        let mut ips = HashMap::new();
+        ips.insert("example.com", vec!["1.2.3.4", "2001:db8::1"]);
+        let mut txts = HashMap::new();
+        txts.insert("_dnsaddr.bootstrap.io", vec![
+            "dnsaddr=/dnsaddr/ams.bootstrap.io",
+            "dnsaddr=/ip4/5.6.7.8/tcp/4001/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC",
+            "not a dnsaddr record",
+        ]);
+        txts.insert("_dnsaddr.ams.bootstrap.io", vec![
+            "dnsaddr=/dns4/example.com/tcp/4001/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC",
+            "dnsaddr=/ip4/9.9.9.9/tcp/4001/ipfs/QmaCpDMGvV2BGHeYERUEnRQAwe3N8SzbUtfsmvsqQLuvuJ",
+        ]);
+        txts.insert("_dnsaddr.loop.io", vec!["dnsaddr=/dnsaddr/loop.io"]);
+        let mut srvs = HashMap::new();
+        srvs.insert("_ipfs._tcp.example.com", vec![
+            SrvRecord::new(20, 0, 4001, "backup.example.com."),
+            SrvRecord::new(10, 0, 4001, "node1.example.com."),
+            SrvRecord::new(10, 0, 0, "."),
+        ]);
+        MockResolver { ips: ips, txts: txts, srvs: srvs }
+    }
+
+    fn resolve(s: &str) -> io::Result<Vec<String>> {
+        let ma = Multiaddr::from_str(s).unwrap();
+        mock().resolve(&ma).map(|v| v.iter().map(|ma| ma.to_string()).collect())
+    }
+
+    #[test]
+    fn test_resolve_dns() {
+        assert_eq!(resolve("/dns/example.com/tcp/80").unwrap(),
+                   vec!["/ip4/1.2.3.4/tcp/80", "/ip6/2001:db8::1/tcp/80"]);
+        assert_eq!(resolve("/dns4/example.com/tcp/80").unwrap(), vec!["/ip4/1.2.3.4/tcp/80"]);
+        assert_eq!(resolve("/dns6/example.com/tcp/80/ws").unwrap(), vec!["/ip6/2001:db8::1/tcp/80/ws"]);
+        assert!(resolve("/dns4/unknown.com/tcp/80").unwrap().is_empty());
+        assert_eq!(resolve("/ip4/1.2.3.4/tcp/80").unwrap(), vec!["/ip4/1.2.3.4/tcp/80"]);
+    }
+
+    #[test]
+    fn test_resolve_dnsaddr() {
+        let peer = "QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+        assert_eq!(resolve("/dnsaddr/bootstrap.io").unwrap().len(), 3);
+        assert_eq!(resolve(&format!("/dnsaddr/bootstrap.io/ipfs/{}", peer)).unwrap(),
+                   vec![format!("/ip4/1.2.3.4/tcp/4001/ipfs/{}", peer),
+                        format!("/ip4/5.6.7.8/tcp/4001/ipfs/{}", peer)]);
+        assert!(resolve("/dnsaddr/loop.io").is_err());
+    }
+
+    #[test]
+    fn test_resolve_srv() {
+        let resolved = mock().resolve_srv("ipfs", Transport::Tcp, "example.com").unwrap();
+        let resolved: Vec<_> = resolved.iter().map(|ma| ma.to_string()).collect();
+        assert_eq!(resolved, vec!["/dns4/node1.example.com/tcp/4001",
+                                  "/dns4/backup.example.com/tcp/4001"]);
+        assert!(mock().resolve_srv("ipfs", Transport::Udp, "example.com").unwrap().is_empty());
+        let e = SystemResolver.resolve_srv("ipfs", Transport::Tcp, "example.com").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::Unsupported);
+    }
+
+    // Counts the lookups that reach it, and fails names starting with "fail"
+    struct CountingResolver {
+        lookups: Cell<usize>,
+        ttl: Option<Duration>,
+    }
+
+    impl Resolver for CountingResolver {
+        fn lookup_ip(&self, _: &str) -> io::Result<Vec<IpAddr>> {
+            unreachable!()
+        }
+
+        fn lookup_txt(&self, _: &str) -> io::Result<Vec<String>> {
+            unreachable!()
+        }
+
+        fn lookup_ip_with_ttl(&self, name: &str) -> io::Result<(Vec<IpAddr>, Option<Duration>)> {
+            self.lookups.set(self.lookups.get() + 1);
+            if name.starts_with("fail") {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+            }
+            Ok((vec![IpAddr::from_str("1.2.3.4").unwrap()], self.ttl))
+        }
+
+        fn lookup_txt_with_ttl(&self, name: &str) -> io::Result<(Vec<String>, Option<Duration>)> {
+            self.lookups.set(self.lookups.get() + 1);
+            let record = format!("dnsaddr=/dns4/{}/tcp/4001", name.trim_start_matches("_dnsaddr."));
+            Ok((vec![record], self.ttl))
+        }
+    }
+
+    fn counting(ttl: Option<Duration>) -> CachingResolver<CountingResolver> {
+        CachingResolver::new(CountingResolver { lookups: Cell::new(0), ttl: ttl })
+    }
+
+    #[test]
+    fn test_caching_resolver() {
+        let resolver = counting(None);
+        let ma = Multiaddr::from_str("/dnsaddr/bootstrap.io").unwrap();
+        for _ in 0..3 {
+            let resolved = resolver.resolve(&ma).unwrap();
+            assert_eq!(resolved, vec![Multiaddr::from_str("/ip4/1.2.3.4/tcp/4001").unwrap()]);
+        }
+        // One TXT and one A lookup
+        assert_eq!(resolver.inner().lookups.get(), 2);
+        let (_, ttl) = resolver.lookup_ip_with_ttl("bootstrap.io").unwrap();
+        assert!(ttl.unwrap() <= Duration::from_secs(60));
+
+        resolver.invalidate("bootstrap.io");
+        resolver.resolve(&ma).unwrap();
+        assert_eq!(resolver.inner().lookups.get(), 4);
+        resolver.clear();
+        resolver.resolve(&ma).unwrap();
+        assert_eq!(resolver.inner().lookups.get(), 6);
+    }
+
+    #[test]
+    fn test_caching_resolver_ttl() {
+        // Records with a TTL of 0 aren't cached
+        let resolver = counting(Some(Duration::from_secs(0)));
+        resolver.lookup_ip("example.com").unwrap();
+        resolver.lookup_ip("example.com").unwrap();
+        assert_eq!(resolver.inner().lookups.get(), 2);
+
+        let resolver = counting(Some(Duration::from_secs(86400)));
+        let (_, ttl) = resolver.lookup_ip_with_ttl("example.com").unwrap();
+        assert!(ttl.unwrap() <= Duration::from_secs(3600));
+        let resolver = resolver.max_ttl(Duration::from_secs(0));
+        resolver.lookup_ip("example.org").unwrap();
+        resolver.lookup_ip("example.org").unwrap();
+        assert_eq!(resolver.inner().lookups.get(), 3);
+    }
+
+    #[test]
+    fn test_caching_resolver_negative() {
+        let resolver = counting(None);
+        for _ in 0..2 {
+            let e = resolver.lookup_ip("fail.com").unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        }
+        assert_eq!(resolver.inner().lookups.get(), 1);
+
+        let resolver = resolver.negative_ttl(Duration::from_secs(0));
+        assert!(resolver.lookup_ip("fail.org").is_err());
+        assert!(resolver.lookup_ip("fail.org").is_err());
+        assert_eq!(resolver.inner().lookups.get(), 3);
+    }
+
+    #[test]
+    fn test_system_resolver() {
+        let ma = Multiaddr::from_str("/dns4/localhost/tcp/80").unwrap();
+        if let Ok(resolved) = SystemResolver.resolve(&ma) {
+            assert!(resolved.iter().all(|ma| ma.is_loopback()));
+        }
+        let dnsaddr = Multiaddr::from_str("/dnsaddr/localhost").unwrap();
+        let e = SystemResolver.resolve(&dnsaddr).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::Unsupported);
+        let e = CachingResolver::new(SystemResolver).resolve(&dnsaddr).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,243 @@
+use std::error::Error;
+use std::fmt;
+use std::net::AddrParseError;
+use std::num::ParseIntError;
+
+/// The error from parsing or validating a multiaddr.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ErrorKind,
+    message: String,
+    source: Option<Source>,
+}
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// The category of a `ParseError`. More kinds may be added, so matches on
+/// it need a wildcard arm.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A protocol name or code that isn't known
+    InvalidCode,
+    /// A protocol's address that is missing or invalid
+    InvalidAddress,
+    /// Bytes that end in the middle of a component or varint
+    Truncated,
+    /// A multiaddr or address longer than the limit, or a length that
+    /// overflows
+    TooLong,
+    /// Anything else, such as text that doesn't begin with '/'
+    Other,
+}
+
+impl ErrorKind {
+    /// Returns a number identifying the kind, for FFI, metrics and logs
+    /// shared with other languages. The codes don't change between
+    /// versions, and new kinds get new codes.
+    pub fn code(&self) -> u32 {
+        match *self {
+            ErrorKind::InvalidCode => 1,
+            ErrorKind::InvalidAddress => 2,
+            ErrorKind::Truncated => 3,
+            ErrorKind::TooLong => 4,
+            ErrorKind::Other => 255,
+        }
+    }
+
+    /// The kind with the given `code`, if there is one.
+    pub fn from_code(code: u32) -> Option<ErrorKind> {
+        match code {
+            1 => Some(ErrorKind::InvalidCode),
+            2 => Some(ErrorKind::InvalidAddress),
+            3 => Some(ErrorKind::Truncated),
+            4 => Some(ErrorKind::TooLong),
+            255 => Some(ErrorKind::Other),
+            _ => None,
+        }
+    }
+}
+
+/// The error from reading a varint in the binary form of a multiaddr.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VarintError {
+    /// The value doesn't fit in a u32
+    Overflow,
+    /// The varint has trailing zero groups
+    NotMinimal,
+    /// The bytes end in the middle of the varint
+    UnexpectedEnd,
+}
+
+// The error a ParseError was made from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Source {
+    Ip(AddrParseError),
+    Int(ParseIntError),
+    Varint(VarintError),
+}
+
+impl ParseError {
+    pub fn invalid_code(message: String) -> ParseError {
+        ParseError::new(ErrorKind::InvalidCode, message)
+    }
+
+    pub fn invalid_address(message: String) -> ParseError {
+        ParseError::new(ErrorKind::InvalidAddress, message)
+    }
+
+    pub fn truncated(message: String) -> ParseError {
+        ParseError::new(ErrorKind::Truncated, message)
+    }
+
+    pub fn too_long(message: String) -> ParseError {
+        ParseError::new(ErrorKind::TooLong, message)
+    }
+
+    pub fn other(message: String) -> ParseError {
+        ParseError::new(ErrorKind::Other, message)
+    }
+
+    // The error for a varint that couldn't be read, of kind `kind` unless
+    // the varint is cut short or overflows
+    pub(crate) fn varint(kind: ErrorKind, message: String, e: VarintError) -> ParseError {
+        let kind = match e {
+            VarintError::UnexpectedEnd => ErrorKind::Truncated,
+            VarintError::Overflow => ErrorKind::TooLong,
+            VarintError::NotMinimal => kind,
+        };
+        ParseError::new(kind, message).with_source(e)
+    }
+
+    pub fn new(kind: ErrorKind, message: String) -> ParseError {
+        ParseError {
+            kind: kind,
+            message: message,
+            source: None,
+        }
+    }
+
+    // Keeps `source` as the error this one was caused by
+    pub(crate) fn with_source<S: Into<Source>>(mut self, source: S) -> ParseError {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(&self.message)
+    }
+}
+
+/// `source` is the error from parsing an ip address, a number or a varint,
+/// when that is what failed.
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.source {
+            Some(Source::Ip(ref e)) => Some(e),
+            Some(Source::Int(ref e)) => Some(e),
+            Some(Source::Varint(ref e)) => Some(e),
+            None => None,
+        }
+    }
+}
+
+impl fmt::Display for VarintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(match *self {
+            VarintError::Overflow => "Varint overflows a u32",
+            VarintError::NotMinimal => "Varint is not minimally encoded",
+            VarintError::UnexpectedEnd => "Unexpected end of bytes while reading varint",
+        })
+    }
+}
+
+impl Error for VarintError {}
+
+impl From<AddrParseError> for Source {
+    fn from(e: AddrParseError) -> Source {
+        Source::Ip(e)
+    }
+}
+
+impl From<ParseIntError> for Source {
+    fn from(e: ParseIntError) -> Source {
+        Source::Int(e)
+    }
+}
+
+impl From<VarintError> for Source {
+    fn from(e: VarintError) -> Source {
+        Source::Varint(e)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{ErrorKind, VarintError};
+    use std::error::Error;
+    use std::net::AddrParseError;
+    use std::num::ParseIntError;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_error_kind_and_source() {
+        let e = Multiaddr::from_str("/ip4/1.2.3.256").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidAddress);
+        assert!(e.source().unwrap().downcast_ref::<AddrParseError>().is_some());
+        assert_eq!(e.to_string(), format!("Error parsing ip4 address: {}", e.source().unwrap()));
+        assert_eq!(e.clone(), e);
+
+        let e = Multiaddr::from_str("/tcp/65536").unwrap_err();
+        assert!(e.source().unwrap().downcast_ref::<ParseIntError>().is_some());
+
+        let e = Multiaddr::from_bytes(vec![0x80]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Truncated);
+        let source = e.source().unwrap().downcast_ref::<VarintError>();
+        assert_eq!(source, Some(&VarintError::UnexpectedEnd));
+
+        let e = Multiaddr::from_str("/foo").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidCode);
+        assert!(e.source().is_none());
+        assert_eq!(Multiaddr::from_str("ip4").unwrap_err().kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let kind = |b: Vec<u8>| Multiaddr::from_bytes(b).unwrap_err().kind();
+        assert_eq!(kind(vec![4, 1, 2, 3]), ErrorKind::Truncated);
+        // ipfs, code 421, has a variable size
+        assert_eq!(kind(vec![0xa5, 3, 5, 0x12]), ErrorKind::Truncated);
+        assert_eq!(kind(vec![0xa5, 3, 0x80, 0x80, 0x80, 0x80, 0x80, 1]), ErrorKind::TooLong);
+        assert_eq!(kind(vec![0xa5, 3, 0x80, 0x08]), ErrorKind::TooLong);
+        assert_eq!(kind(vec![0x84, 0x00]), ErrorKind::InvalidCode);
+        assert_eq!(kind(vec![4, 1, 2, 3, 4].repeat(300)), ErrorKind::TooLong);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let kinds = [ErrorKind::InvalidCode,
+                     ErrorKind::InvalidAddress,
+                     ErrorKind::Truncated,
+                     ErrorKind::TooLong,
+                     ErrorKind::Other];
+        let codes: Vec<_> = kinds.iter().map(|k| k.code()).collect();
+        // These must never change
+        assert_eq!(codes, vec![1, 2, 3, 4, 255]);
+        for &kind in &kinds {
+            assert_eq!(ErrorKind::from_code(kind.code()), Some(kind));
+        }
+        assert_eq!(ErrorKind::from_code(0), None);
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use protocol::Protocol::*;
+use {read_ip4, read_ip6, write_component, Maddr, Multiaddr, ParseError, ParseResult};
+
+/// A range of ip addresses, written either as "1.2.3.0/24" or as the
+/// multiaddr "/ip4/1.2.3.0/ipcidr/24".
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, prefix_len: u8) -> ParseResult<Cidr> {
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix_len > max {
+            return Err(ParseError::invalid_address(format!(
+                "Prefix length {} is too long for {}", prefix_len, addr)));
+        }
+        Ok(Cidr {
+            addr: addr,
+            prefix_len: prefix_len,
+        })
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Checks whether `ip` is in the range. ip4 addresses are never in an
+    /// ip6 range and vice versa.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, *ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_eq(&net.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_eq(&net.octets(), &ip.octets(), self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the range as an ip4 or ip6 multiaddr followed by ipcidr.
+    pub fn to_multiaddr(&self) -> Multiaddr {
+        let mut bytes = Vec::new();
+        match self.addr {
+            IpAddr::V4(ip) => write_component(IP4, &ip.octets(), &mut bytes),
+            IpAddr::V6(ip) => write_component(IP6, &ip.octets(), &mut bytes),
+        }
+        write_component(IPCIDR, &[self.prefix_len], &mut bytes);
+        Multiaddr { bytes: bytes.into() }
+    }
+
+    /// Reads a range from a multiaddr consisting of an ip4 or ip6 component
+    /// followed by an ipcidr one.
+    pub fn from_multiaddr(ma: &Maddr) -> ParseResult<Cidr> {
+        let mut components = ma.iter();
+        let addr = match components.next() {
+            Some((IP4, addr)) => IpAddr::V4(read_ip4(addr)),
+            Some((IP6, addr)) => IpAddr::V6(read_ip6(addr)),
+            _ => return Err(ParseError::invalid_code(format!("Expected ip4 or ip6 in {}", ma))),
+        };
+        match (components.next(), components.next()) {
+            (Some((IPCIDR, bits)), None) => Cidr::new(addr, bits[0]),
+            _ => {
+                Err(ParseError::invalid_code(format!("Expected a single ipcidr after the ip in {}",
+                                                    ma)))
+            }
+        }
+    }
+}
+
+// Compares the first `bits` bits of two addresses
+fn prefix_eq(a: &[u8], b: &[u8], bits: u8) -> bool {
+    let whole = bits as usize / 8;
+    if a[..whole] != b[..whole] {
+        return false;
+    }
+    let rest = bits % 8;
+    if rest == 0 {
+        return true;
+    }
+    let mask = 0xffu8 << (8 - rest);
+    a[whole] & mask == b[whole] & mask
+}
+
+impl FromStr for Cidr {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with('/') {
+            return Cidr::from_multiaddr(&try!(Multiaddr::from_str(s)));
+        }
+
+        let mut parts = s.splitn(2, '/');
+        let addr = try!(parts.next().unwrap().parse::<IpAddr>().map_err(|e| {
+            ParseError::invalid_address(format!("Error parsing ip address in {}: {}", s, e))
+        }));
+        let prefix_len = try!(parts.next().and_then(|n| n.parse::<u8>().ok()).ok_or_else(|| {
+            ParseError::invalid_address(format!("Invalid prefix length in {}", s))
+        }));
+        Cidr::new(addr, prefix_len)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Accept,
+    Deny,
+}
+
+/// A set of rules for accepting or denying multiaddrs by their ip address,
+/// like go-multiaddr-filter.
+///
+/// A multiaddr gets the action of the last rule whose range contains its
+/// first ip address, or the default action if there is no such rule or no
+/// ip address.
+#[derive(Clone, Debug)]
+pub struct Filters {
+    default: Action,
+    rules: Vec<(Cidr, Action)>,
+}
+
+impl Filters {
+    /// Creates a set of filters with no rules that accepts by default.
+    pub fn new() -> Filters {
+        Filters {
+            default: Action::Accept,
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn set_default(&mut self, action: Action) {
+        self.default = action;
+    }
+
+    pub fn default_action(&self) -> Action {
+        self.default
+    }
+
+    pub fn add_rule(&mut self, cidr: Cidr, action: Action) {
+        self.rules.push((cidr, action));
+    }
+
+    /// Adds a rule accepting the range `cidr`, in either of the forms
+    /// `Cidr` parses.
+    pub fn allow(&mut self, cidr: &str) -> ParseResult<()> {
+        self.add_rule(try!(Cidr::from_str(cidr)), Action::Accept);
+        Ok(())
+    }
+
+    /// Adds a rule denying the range `cidr`, in either of the forms `Cidr`
+    /// parses.
+    pub fn deny(&mut self, cidr: &str) -> ParseResult<()> {
+        self.add_rule(try!(Cidr::from_str(cidr)), Action::Deny);
+        Ok(())
+    }
+
+    /// Removes every rule for exactly the range `cidr`, returning whether
+    /// there were any.
+    pub fn remove_rule(&mut self, cidr: &Cidr) -> bool {
+        let len = self.rules.len();
+        self.rules.retain(|&(ref c, _)| c != cidr);
+        self.rules.len() != len
+    }
+
+    pub fn rules(&self) -> &[(Cidr, Action)] {
+        &self.rules
+    }
+
+    pub fn action_for(&self, ma: &Maddr) -> Action {
+        match ma.ip() {
+            Some(ip) => self.action_for_ip(&ip),
+            None => self.default,
+        }
+    }
+
+    pub fn action_for_ip(&self, ip: &IpAddr) -> Action {
+        self.rules.iter()
+                  .rev()
+                  .find(|&&(ref cidr, _)| cidr.contains(ip))
+                  .map_or(self.default, |&(_, action)| action)
+    }
+
+    pub fn is_blocked(&self, ma: &Maddr) -> bool {
+        self.action_for(ma) == Action::Deny
+    }
+}
+
+impl Default for Filters {
+    fn default() -> Filters {
+        Filters::new()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Action, Cidr, Filters};
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    fn ma(s: &str) -> Multiaddr {
+        Multiaddr::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_cidr() {
+        let cidr = Cidr::from_str("10.1.0.0/14").unwrap();
+        assert_eq!(Cidr::from_str("/ip4/10.1.0.0/ipcidr/14").unwrap(), cidr);
+        assert_eq!(cidr.to_multiaddr().to_string(), "/ip4/10.1.0.0/ipcidr/14");
+        assert_eq!(cidr.to_string(), "10.1.0.0/14");
+
+        assert!(cidr.contains(&IpAddr::from_str("10.3.255.255").unwrap()));
+        assert!(!cidr.contains(&IpAddr::from_str("10.4.0.0").unwrap()));
+        assert!(!cidr.contains(&IpAddr::from_str("::a01:0").unwrap()));
+
+        let cidr = Cidr::from_str("2001:db8::/32").unwrap();
+        assert!(cidr.contains(&IpAddr::from_str("2001:db8:ffff::1").unwrap()));
+        assert!(!cidr.contains(&IpAddr::from_str("2001:db9::1").unwrap()));
+        assert!(Cidr::from_str("0.0.0.0/0").unwrap().contains(&IpAddr::from_str("1.2.3.4").unwrap()));
+
+        for s in &["10.0.0.0/33", "::/129", "10.0.0.0", "10.0.0.0/x", "/ip4/10.0.0.0",
+                   "/ip4/10.0.0.0/ipcidr/8/tcp/1", "/tcp/1/ipcidr/8"] {
+            assert!(Cidr::from_str(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut filters = Filters::new();
+        filters.deny("10.0.0.0/8").unwrap();
+        filters.allow("/ip4/10.1.0.0/ipcidr/16").unwrap();
+        filters.deny("fe80::/10").unwrap();
+
+        assert!(filters.is_blocked(&ma("/ip4/10.2.3.4/tcp/1")));
+        assert!(!filters.is_blocked(&ma("/ip4/10.1.3.4/tcp/1")));
+        assert!(filters.is_blocked(&ma("/ip6/fe80::1/udp/1/quic")));
+        assert!(!filters.is_blocked(&ma("/ip4/1.2.3.4")));
+        #[cfg(feature = "proto-dns")]
+        assert!(!filters.is_blocked(&ma("/dns4/example.com/tcp/1")));
+
+        filters.set_default(Action::Deny);
+        assert!(filters.is_blocked(&ma("/ip4/1.2.3.4")));
+        assert!(!filters.is_blocked(&ma("/ip4/10.1.3.4/tcp/1")));
+
+        assert!(filters.remove_rule(&Cidr::from_str("10.1.0.0/16").unwrap()));
+        assert!(filters.is_blocked(&ma("/ip4/10.1.3.4/tcp/1")));
+        assert_eq!(filters.rules().len(), 2);
+    }
+}
@@ -0,0 +1,148 @@
+//! Conversions between multiaddrs and the host:port strings most other
+//! software is configured with.
+
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use std::net::SocketAddr;
+
+use protocol::Protocol::{self, *};
+use {read_ip4, read_ip6, write_component, write_protocol, Maddr, Multiaddr, ParseError,
+     ParseResult};
+
+/// The transport of the port in a host:port string.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+impl Transport {
+    pub fn protocol(&self) -> Protocol {
+        match *self {
+            Transport::Tcp => TCP,
+            Transport::Udp => UDP,
+        }
+    }
+}
+
+impl Multiaddr {
+    /// Converts a host:port string into a multiaddr, e.g. "example.com:443"
+    /// with `Transport::Tcp` becomes "/dns4/example.com/tcp/443", and
+    /// "[::1]:53" with `Transport::Udp` becomes "/ip6/::1/udp/53". Ip6
+    /// addresses must be in brackets.
+    pub fn from_host_port(s: &str, transport: Transport) -> ParseResult<Multiaddr> {
+        let mut bytes = Vec::new();
+        let port = match s.parse::<SocketAddr>() {
+            Ok(SocketAddr::V4(addr)) => {
+                write_component(IP4, &addr.ip().octets(), &mut bytes);
+                addr.port()
+            }
+            Ok(SocketAddr::V6(addr)) => {
+                write_component(IP6, &addr.ip().octets(), &mut bytes);
+                addr.port()
+            }
+            Err(_) => try!(write_domain(s, &mut bytes)),
+        };
+        write_protocol(transport.protocol(), &mut bytes);
+        bytes.write_u16::<BigEndian>(port).unwrap();
+        Ok(Multiaddr { bytes: bytes.into() })
+    }
+}
+
+impl Maddr {
+    /// Returns the host:port string of a multiaddr made of an ip4, ip6 or
+    /// dns component and a tcp or udp port, the reverse of
+    /// `Multiaddr::from_host_port`. Multiaddrs with other components have
+    /// none.
+    pub fn to_host_port(&self) -> Option<String> {
+        let mut components = self.iter();
+        let (host, port) = match (components.next(), components.next(), components.next()) {
+            (Some(host), Some((TCP, port)), None) | (Some(host), Some((UDP, port)), None) => {
+                (host, BigEndian::read_u16(port))
+            }
+            _ => return None,
+        };
+        let host = match host {
+            (IP4, addr) => read_ip4(addr).to_string(),
+            (IP6, addr) => format!("[{}]", read_ip6(addr)),
+            #[cfg(feature = "proto-dns")]
+            (DNS, name) | (DNS4, name) | (DNS6, name) => String::from_utf8_lossy(name).into_owned(),
+            _ => return None,
+        };
+        Some(format!("{}:{}", host, port))
+    }
+}
+
+// Writes the dns4 component of a domain name:port string, returning the
+// port
+#[cfg(feature = "proto-dns")]
+fn write_domain(s: &str, bytes: &mut Vec<u8>) -> ParseResult<u16> {
+    let (host, port) = match s.rfind(':') {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => return Err(ParseError::invalid_address(format!("{} has no port", s))),
+    };
+    if host.contains(':') {
+        return Err(ParseError::invalid_address(format!("{} has an ip6 address without \
+                                                        brackets", s)));
+    }
+    let port = try!(port.parse::<u16>().map_err(|e| {
+        ParseError::invalid_address(format!("Error parsing port of {}: {}", s, e)).with_source(e)
+    }));
+    write_protocol(DNS4, bytes);
+    try!(::write_checked_address(host, DNS4, &::Registry::new(), bytes));
+    Ok(port)
+}
+
+#[cfg(not(feature = "proto-dns"))]
+fn write_domain(s: &str, _: &mut Vec<u8>) -> ParseResult<u16> {
+    Err(ParseError::invalid_address(format!("{} is not an ip address and port, and domain names \
+                                            need the proto-dns feature", s)))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Transport;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    fn from_host_port(s: &str, transport: Transport) -> Option<String> {
+        Multiaddr::from_host_port(s, transport).ok().map(|ma| ma.to_string())
+    }
+
+    fn to_host_port(s: &str) -> Option<String> {
+        Multiaddr::from_str(s).unwrap().to_host_port()
+    }
+
+    #[test]
+    fn test_from_host_port() {
+        assert_eq!(from_host_port("1.2.3.4:80", Transport::Tcp).unwrap(), "/ip4/1.2.3.4/tcp/80");
+        assert_eq!(from_host_port("[::1]:53", Transport::Udp).unwrap(), "/ip6/::1/udp/53");
+        assert!(from_host_port("::1:53", Transport::Udp).is_none());
+        assert!(from_host_port("1.2.3.4", Transport::Tcp).is_none());
+        assert!(from_host_port("1.2.3.4:65536", Transport::Tcp).is_none());
+    }
+
+    #[cfg(feature = "proto-dns")]
+    #[test]
+    fn test_from_host_port_domain() {
+        assert_eq!(from_host_port("example.com:443", Transport::Tcp).unwrap(),
+                   "/dns4/example.com/tcp/443");
+        assert!(from_host_port("example.com", Transport::Tcp).is_none());
+        assert!(from_host_port("example.com:https", Transport::Tcp).is_none());
+        assert!(from_host_port(":443", Transport::Tcp).is_none());
+        assert_eq!(to_host_port("/dns6/example.com/udp/53").unwrap(), "example.com:53");
+    }
+
+    #[test]
+    fn test_to_host_port() {
+        assert_eq!(to_host_port("/ip4/1.2.3.4/tcp/80").unwrap(), "1.2.3.4:80");
+        assert_eq!(to_host_port("/ip6/::1/udp/53").unwrap(), "[::1]:53");
+        assert!(to_host_port("/ip4/1.2.3.4/tcp/80/ws").is_none());
+        assert!(to_host_port("/ip4/1.2.3.4").is_none());
+        assert!(to_host_port("/tcp/80/ip4/1.2.3.4").is_none());
+
+        let ma = Multiaddr::from_str("/ip6/2001:db8::1/tcp/4001").unwrap();
+        let s = ma.to_host_port().unwrap();
+        assert_eq!(Multiaddr::from_host_port(&s, Transport::Tcp).unwrap(), ma);
+    }
+}
@@ -0,0 +1,291 @@
+//! Internationalized domain names in dns components. Names are stored in
+//! their ASCII form, with non-ASCII labels punycode-encoded behind an "xn--"
+//! prefix (RFC 3492), so that they can be resolved as they are.
+
+use std::borrow::Cow;
+use std::char;
+
+use protocol::Protocol::*;
+use Maddr;
+
+const ACE_PREFIX: &'static str = "xn--";
+
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+impl Maddr {
+    /// Returns the textual form of the multiaddr with the punycode labels
+    /// of dns components decoded, e.g. "/dns4/bücher.example" rather than
+    /// "/dns4/xn--bcher-kva.example". Only meant for showing to people: the
+    /// result parses back to the same multiaddr, but other implementations
+    /// may not accept it.
+    pub fn to_unicode_string(&self) -> String {
+        ::to_string_with(self, |proto, addr| {
+            match proto {
+                DNS | DNS4 | DNS6 | DNSADDR => Some(to_unicode(&String::from_utf8_lossy(addr))),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// Converts a domain name to the form stored in dns components, then checks
+/// it with `check_hostname`. Labels with non-ASCII characters are lowercased
+/// and punycode-encoded; ASCII labels are kept as they are.
+pub(crate) fn to_ascii<'a>(name: &'a str) -> Result<Cow<'a, str>, String> {
+    let ascii = if name.is_ascii() {
+        Cow::Borrowed(name)
+    } else {
+        let labels: Vec<_> = name.split('.').map(|label| {
+            // Longer labels are rejected below, and could overflow encode
+            if label.is_ascii() || label.chars().count() > 63 {
+                label.to_string()
+            } else {
+                format!("{}{}", ACE_PREFIX, encode(&label.to_lowercase()))
+            }
+        }).collect();
+        Cow::Owned(labels.join("."))
+    };
+    try!(check_hostname(&ascii));
+    Ok(ascii)
+}
+
+/// Checks that `name` is an ASCII domain name: at most 253 characters,
+/// made of dot-separated labels of 1 to 63 letters, digits, hyphens and
+/// underscores, the latter for service names such as "_dnsaddr". A label
+/// can't begin or end with a hyphen, and the name may end with a dot.
+pub(crate) fn check_hostname(name: &str) -> Result<(), String> {
+    let trimmed = if name.ends_with('.') { &name[..name.len() - 1] } else { name };
+    if trimmed.is_empty() {
+        return Err(format!("Empty domain name"));
+    }
+    if trimmed.len() > 253 {
+        return Err(format!("Domain name is {} characters, more than 253", trimmed.len()));
+    }
+    for label in trimmed.split('.') {
+        if label.is_empty() {
+            return Err(format!("Empty label in domain name {}", name));
+        }
+        if label.len() > 63 {
+            return Err(format!("Label {} in domain name is more than 63 characters", label));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(format!("Label {} in domain name begins or ends with '-'", label));
+        }
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if let Some(c) = label.chars().find(|&c| !valid(c)) {
+            return Err(format!("Invalid character {:?} in domain name {}", c, name));
+        }
+    }
+    Ok(())
+}
+
+// Decodes the punycode labels of an ASCII domain name, leaving the labels
+// that aren't valid punycode, or decode to control characters, as they are.
+fn to_unicode(name: &str) -> String {
+    let labels: Vec<_> = name.split('.').map(|label| {
+        let prefix = ACE_PREFIX.len();
+        if label.len() > prefix && label[..prefix].eq_ignore_ascii_case(ACE_PREFIX) {
+            if let Some(decoded) = decode(&label[prefix..]) {
+                return decoded;
+            }
+        }
+        label.to_string()
+    }).collect();
+    labels.join(".")
+}
+
+fn adapt(mut delta: u32, num_points: u32, first_time: bool) -> u32 {
+    delta /= if first_time { DAMP } else { 2 };
+    delta += delta / num_points;
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+fn threshold(k: u32, bias: u32) -> u32 {
+    if k <= bias {
+        T_MIN
+    } else if k >= bias + T_MAX {
+        T_MAX
+    } else {
+        k - bias
+    }
+}
+
+fn encode_digit(d: u32) -> char {
+    (if d < 26 { b'a' + d as u8 } else { b'0' + (d - 26) as u8 }) as char
+}
+
+fn decode_digit(c: char) -> Option<u32> {
+    match c {
+        'a'..='z' => Some(c as u32 - 'a' as u32),
+        'A'..='Z' => Some(c as u32 - 'A' as u32),
+        '0'..='9' => Some(c as u32 - '0' as u32 + 26),
+        _ => None,
+    }
+}
+
+// Punycode-encodes a label, without the "xn--" prefix
+fn encode(label: &str) -> String {
+    let input: Vec<u32> = label.chars().map(|c| c as u32).collect();
+    let mut out: String = label.chars().filter(|c| c.is_ascii()).collect();
+    let basic = out.len() as u32;
+    if basic > 0 {
+        out.push('-');
+    }
+
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut handled = basic;
+    while (handled as usize) < input.len() {
+        let m = *input.iter().filter(|&&c| c >= n).min().unwrap();
+        delta += (m - n) * (handled + 1);
+        n = m;
+        for &c in &input {
+            if c < n {
+                delta += 1;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = threshold(k, bias);
+                    if q < t {
+                        break;
+                    }
+                    out.push(encode_digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                out.push(encode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta += 1;
+        n += 1;
+    }
+    out
+}
+
+// Decodes a punycode label, without the "xn--" prefix
+fn decode(input: &str) -> Option<String> {
+    if !input.is_ascii() {
+        return None;
+    }
+    let (basic, extended) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input),
+    };
+    let mut out: Vec<char> = basic.chars().collect();
+
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = extended.chars();
+    loop {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        let mut first = true;
+        loop {
+            let c = match digits.next() {
+                Some(c) => c,
+                // The input may only end between two code points
+                None if first => {
+                    let unicode = out.iter().any(|c| !c.is_ascii());
+                    return if unicode && !out.iter().any(|c| c.is_control()) {
+                        Some(out.into_iter().collect())
+                    } else {
+                        None
+                    };
+                }
+                None => return None,
+            };
+            first = false;
+            let digit = match decode_digit(c) {
+                Some(d) => d,
+                None => return None,
+            };
+            i = match digit.checked_mul(w).and_then(|d| i.checked_add(d)) {
+                Some(i) => i,
+                None => return None,
+            };
+            let t = threshold(k, bias);
+            if digit < t {
+                break;
+            }
+            w = match w.checked_mul(BASE - t) {
+                Some(w) => w,
+                None => return None,
+            };
+            k += BASE;
+        }
+        let len = out.len() as u32 + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = match n.checked_add(i / len).and_then(char::from_u32) {
+            Some(c) => c as u32,
+            None => return None,
+        };
+        i %= len;
+        out.insert(i as usize, char::from_u32(n).unwrap());
+        i += 1;
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{check_hostname, decode, encode, to_ascii, to_unicode};
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_punycode() {
+        let cases = [("bücher", "bcher-kva"),
+                     ("münchen", "mnchen-3ya"),
+                     ("例え", "r8jz45g"),
+                     ("☃", "n3h")];
+        for &(unicode, puny) in &cases {
+            assert_eq!(encode(unicode), puny);
+            assert_eq!(decode(puny).unwrap(), unicode);
+        }
+        assert_eq!(to_ascii("Bücher.example").unwrap(), "xn--bcher-kva.example");
+        assert_eq!(to_unicode("xn--bcher-kva.example"), "bücher.example");
+        assert_eq!(to_unicode("xn--abc.example"), "xn--abc.example");
+    }
+
+    #[test]
+    fn test_check_hostname() {
+        for name in &["example.com", "example.com.", "_dnsaddr.a-b.io", "localhost"] {
+            assert!(check_hostname(name).is_ok(), "{}", name);
+        }
+        let long_label = "a".repeat(64);
+        let long_name = vec!["a"; 127].join(".") + ".ab";
+        for name in &["", ".", "a..b", ".a", "-a.b", "a-.b", "a b", "a,b", &long_label, &long_name] {
+            assert!(check_hostname(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_idn_multiaddr() {
+        let ma = Multiaddr::from_str("/dns4/Bücher.example/tcp/443").unwrap();
+        assert_eq!(ma.to_string(), "/dns4/xn--bcher-kva.example/tcp/443");
+        assert_eq!(ma.to_unicode_string(), "/dns4/bücher.example/tcp/443");
+        assert_eq!(Multiaddr::from_str(&ma.to_unicode_string()).unwrap(), ma);
+
+        assert!(Multiaddr::from_str("/dns/exa mple.com").is_err());
+        assert!(Multiaddr::from_bytes(vec![53, 3, b'a', b'.', b'.']).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Expansion of unspecified listen addresses into the addresses of the
+//! local interfaces, enabled by the `interfaces` feature.
+
+use if_addrs;
+use std::io;
+use std::net::IpAddr;
+
+use protocol::Protocol::*;
+use {write_component, Maddr, Multiaddr, Protocol};
+
+/// Expands a multiaddr starting with 0.0.0.0 or :: into one multiaddr per
+/// address of the local interfaces of the same family, like go-multiaddr's
+/// ResolveUnspecifiedAddress. Listeners bound to a wildcard address use this
+/// to find the addresses worth advertising.
+///
+/// A multiaddr that doesn't start with an unspecified address resolves to
+/// itself.
+pub fn resolve_unspecified(ma: &Maddr) -> io::Result<Vec<Multiaddr>> {
+    if unspecified_family(ma).is_none() {
+        return Ok(vec![ma.to_owned()]);
+    }
+    let ips: Vec<_> = try!(if_addrs::get_if_addrs()).iter().map(|i| i.ip()).collect();
+    Ok(resolve_unspecified_with(ma, &ips))
+}
+
+/// Like `resolve_unspecified`, but with the interface addresses given.
+pub fn resolve_unspecified_with(ma: &Maddr, ips: &[IpAddr]) -> Vec<Multiaddr> {
+    let family = match unspecified_family(ma) {
+        Some(family) => family,
+        None => return vec![ma.to_owned()],
+    };
+    let mut components = ma.iter();
+    components.next();
+    let rest = components.remaining();
+
+    let mut resolved = Vec::new();
+    for ip in ips {
+        let mut bytes = Vec::with_capacity(ma.byte_len());
+        match (family, *ip) {
+            (IP4, IpAddr::V4(ip)) => write_component(IP4, &ip.octets(), &mut bytes),
+            (IP6, IpAddr::V6(ip)) => write_component(IP6, &ip.octets(), &mut bytes),
+            _ => continue,
+        }
+        bytes.extend(rest);
+        resolved.push(Multiaddr { bytes: bytes.into() });
+    }
+    resolved
+}
+
+// Returns IP4 or IP6 if the multiaddr starts with 0.0.0.0 or ::
+fn unspecified_family(ma: &Maddr) -> Option<Protocol> {
+    match ma.iter().next() {
+        Some((p @ IP4, addr)) | Some((p @ IP6, addr)) if addr.iter().all(|&b| b == 0) => Some(p),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::resolve_unspecified_with;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_resolve_unspecified() {
+        let ips: Vec<IpAddr> = ["127.0.0.1", "192.168.1.5", "::1", "fe80::1"].iter()
+            .map(|s| s.parse().unwrap()).collect();
+
+        let ma = Multiaddr::from_str("/ip4/0.0.0.0/tcp/4001").unwrap();
+        assert_eq!(resolve_unspecified_with(&ma, &ips),
+                   vec![Multiaddr::from_str("/ip4/127.0.0.1/tcp/4001").unwrap(),
+                        Multiaddr::from_str("/ip4/192.168.1.5/tcp/4001").unwrap()]);
+
+        let ma = Multiaddr::from_str("/ip6/::/udp/4001/quic").unwrap();
+        assert_eq!(resolve_unspecified_with(&ma, &ips),
+                   vec![Multiaddr::from_str("/ip6/::1/udp/4001/quic").unwrap(),
+                        Multiaddr::from_str("/ip6/fe80::1/udp/4001/quic").unwrap()]);
+
+        let ma = Multiaddr::from_str("/ip4/10.0.0.1/tcp/4001").unwrap();
+        assert_eq!(resolve_unspecified_with(&ma, &ips), vec![ma.clone()]);
+    }
+}
@@ -0,0 +1,156 @@
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use {Maddr, Multiaddr};
+
+/// A multiaddr shared with a `MultiaddrInterner`. Cloning it only bumps a
+/// reference count, and comparing two handles from the same interner is a
+/// pointer comparison.
+#[derive(Clone)]
+pub struct SharedMultiaddr(Arc<Multiaddr>);
+
+impl SharedMultiaddr {
+    /// Checks whether both handles point to the same interned multiaddr.
+    pub fn ptr_eq(a: &SharedMultiaddr, b: &SharedMultiaddr) -> bool {
+        Arc::ptr_eq(&a.0, &b.0)
+    }
+}
+
+impl Deref for SharedMultiaddr {
+    type Target = Maddr;
+    fn deref(&self) -> &Maddr {
+        &self.0
+    }
+}
+
+impl Borrow<Maddr> for SharedMultiaddr {
+    fn borrow(&self) -> &Maddr {
+        self
+    }
+}
+
+impl AsRef<Maddr> for SharedMultiaddr {
+    fn as_ref(&self) -> &Maddr {
+        self
+    }
+}
+
+impl From<SharedMultiaddr> for Multiaddr {
+    fn from(ma: SharedMultiaddr) -> Multiaddr {
+        (*ma.0).clone()
+    }
+}
+
+/// Handles from different interners, or from none, are compared by value.
+impl PartialEq for SharedMultiaddr {
+    fn eq(&self, other: &SharedMultiaddr) -> bool {
+        SharedMultiaddr::ptr_eq(self, other) || **self == **other
+    }
+}
+
+impl Eq for SharedMultiaddr {}
+
+impl PartialEq<Multiaddr> for SharedMultiaddr {
+    fn eq(&self, other: &Multiaddr) -> bool {
+        **self == **other
+    }
+}
+
+impl Hash for SharedMultiaddr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl fmt::Display for SharedMultiaddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl fmt::Debug for SharedMultiaddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Deduplicates multiaddrs that are stored many times, such as the
+/// bootstrap and relay addresses in a peer table, so that each is kept in
+/// memory once.
+///
+/// Interned multiaddrs stay alive as long as the interner does, even if no
+/// handle to them is left; call `purge` now and then to drop those.
+#[derive(Clone, Debug, Default)]
+pub struct MultiaddrInterner {
+    addrs: HashSet<SharedMultiaddr>,
+}
+
+impl MultiaddrInterner {
+    pub fn new() -> MultiaddrInterner {
+        MultiaddrInterner::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.addrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addrs.is_empty()
+    }
+
+    /// Returns the shared handle for `ma`, adding it if it isn't interned
+    /// yet.
+    pub fn intern(&mut self, ma: &Maddr) -> SharedMultiaddr {
+        if let Some(shared) = self.addrs.get(ma) {
+            return shared.clone();
+        }
+        let shared = SharedMultiaddr(Arc::new(Maddr::to_owned(ma)));
+        self.addrs.insert(shared.clone());
+        shared
+    }
+
+    /// Returns the shared handle for `ma` if it is interned.
+    pub fn get(&self, ma: &Maddr) -> Option<SharedMultiaddr> {
+        self.addrs.get(ma).cloned()
+    }
+
+    /// Drops the multiaddrs that only the interner holds on to.
+    pub fn purge(&mut self) {
+        self.addrs.retain(|shared| Arc::strong_count(&shared.0) > 1);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{MultiaddrInterner, SharedMultiaddr};
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_interner() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/4001").unwrap();
+        let mut interner = MultiaddrInterner::new();
+        let a = interner.intern(&ma);
+        let b = interner.intern(&ma.clone());
+        assert!(SharedMultiaddr::ptr_eq(&a, &b));
+        assert_eq!(a, ma);
+        assert_eq!(a.port(), Some(4001));
+        assert_eq!(interner.len(), 1);
+
+        let other = interner.intern(&Multiaddr::from_str("/ip6/::1/tcp/4001").unwrap());
+        assert!(a != other);
+        assert!(interner.get(&ma).is_some());
+
+        drop(other);
+        interner.purge();
+        assert_eq!(interner.len(), 1);
+        drop((a, b));
+        interner.purge();
+        assert!(interner.is_empty() && interner.get(&ma).is_none());
+    }
+}
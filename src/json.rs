@@ -0,0 +1,49 @@
+//! JSON introspection, enabled by the `json` feature.
+
+use serde_json::{Map, Value};
+
+use {address_bytes_to_string, Maddr};
+
+impl Maddr {
+    /// Describes the components of the multiaddr as a JSON array of
+    /// `{"protocol", "code", "value"}` objects, for tools that show address
+    /// details. The value is the one in the textual form, or null for
+    /// protocols without one:
+    ///
+    /// ```ignore
+    /// [{"code":4,"protocol":"ip4","value":"1.2.3.4"},
+    ///  {"code":477,"protocol":"ws","value":null}]
+    /// ```
+    pub fn to_components_json(&self) -> Value {
+        let components = self.iter().map(|(proto, addr)| {
+            let mut obj = Map::new();
+            obj.insert("protocol".to_string(), Value::from(proto.name()));
+            obj.insert("code".to_string(), Value::from(proto.code()));
+            let value = if proto.requires_value() {
+                Value::from(address_bytes_to_string(addr, &proto))
+            } else {
+                Value::Null
+            };
+            obj.insert("value".to_string(), value);
+            Value::Object(obj)
+        });
+        Value::Array(components.collect())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_components_json() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80/ws").unwrap();
+        assert_eq!(ma.to_components_json().to_string(),
+                   "[{\"code\":4,\"protocol\":\"ip4\",\"value\":\"1.2.3.4\"},\
+                    {\"code\":6,\"protocol\":\"tcp\",\"value\":\"80\"},\
+                    {\"code\":477,\"protocol\":\"ws\",\"value\":null}]");
+        assert_eq!(Multiaddr::empty().to_components_json().to_string(), "[]");
+    }
+}
@@ -1,22 +1,212 @@
 extern crate byteorder;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "url")]
+extern crate url;
+#[cfg(feature = "borsh")]
+extern crate borsh;
+#[cfg(feature = "rkyv")]
+extern crate rkyv;
+#[cfg(feature = "interfaces")]
+extern crate if_addrs;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "tracing")]
+#[macro_use]
+extern crate tracing;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "codec")]
+extern crate tokio_util;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "prost")]
+extern crate prost;
+#[cfg(feature = "rand")]
+extern crate rand;
+#[cfg(feature = "multihash")]
 extern crate rust_multihash;
 extern crate varint;
 
-use byteorder::{BigEndian, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+#[cfg(feature = "multihash")]
 use rust_multihash::Multihash;
-use std::io::{Cursor, Write};
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::ops::Deref;
 use std::str::FromStr;
-use varint::{VarintWrite, VarintRead};
+use varint::VarintWrite;
 
-use protocol::Protocol;
 use protocol::Protocol::*;
+use registry::Proto;
 
+pub use builder::Builder;
+#[cfg(feature = "codec")]
+pub use codec::MultiaddrCodec;
+pub use component::{AddrComponent, Components, IntoIter};
+pub use delimited::{Decoded, Decoder};
+pub use diagnostics::Diagnostic;
+pub use diff::{diff, diff_ignoring_peer_id, AddrDiff};
+pub use dial::{dial_order, happy_eyeballs_order, sort_for_dialing};
+pub use display_cache::DisplayCache;
+pub use error::{ErrorKind, ParseError, ParseResult, VarintError};
+pub use filter::{Action, Cidr, Filters};
+pub use host_port::Transport;
+pub use interner::{MultiaddrInterner, SharedMultiaddr};
+pub use list::{ListError, ParsedMany};
+pub use options::ParseOptions;
+pub use multibase::Base;
+#[cfg(feature = "proto-onion")]
+pub use onion::{Onion3Addr, OnionAddr};
+pub use protocol::{Protocol, Size};
+pub use redacted::Redacted;
+pub use report::{ComponentInfo, ValidationReport, Warning};
+pub use set::MultiaddrSet;
+#[cfg(feature = "proto-dns")]
+pub use srv::{sort_srv_records, srv_name, SrvRecord};
+pub use static_multiaddr::StaticMultiaddr;
+pub use template::Template;
+pub use transport::TransportKind;
+pub use registry::{MultihashValidator, OpaqueBytes, PeerIdRules, Registry, RegistryComponents};
+#[cfg(feature = "rkyv")]
+pub use rkyv_impls::ArchivedMultiaddr;
+
+#[macro_use]
+mod macros;
+mod builder;
+mod canonical;
+mod cid;
+mod component;
+mod delimited;
+mod diagnostics;
+mod diff;
+mod dial;
+mod display_cache;
+mod error;
+mod filter;
+mod host_port;
+mod interner;
+mod list;
+pub mod net;
+mod options;
+pub mod mafmt;
+mod multibase;
 mod protocol;
+mod redacted;
+mod registry;
+mod report;
+pub mod rewrite;
+mod scope;
+mod set;
+mod static_multiaddr;
+mod template;
+mod transport;
+pub mod validate;
+#[doc(hidden)]
+pub mod const_parse;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+#[cfg(feature = "borsh")]
+mod borsh_impls;
+#[cfg(feature = "codec")]
+mod codec;
+#[cfg(feature = "defmt")]
+mod defmt_impls;
+#[cfg(feature = "dns")]
+pub mod dns;
+#[cfg(feature = "proto-dns")]
+mod idn;
+#[cfg(feature = "interfaces")]
+pub mod interfaces;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "proto-onion")]
+mod onion;
+#[cfg(feature = "prost")]
+mod prost_impls;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "rand")]
+pub mod random;
+#[cfg(feature = "rkyv")]
+mod rkyv_impls;
+#[cfg(feature = "proto-dns")]
+mod srv;
+#[cfg(not(feature = "bytes"))]
+mod storage;
+#[cfg(feature = "async")]
+mod tokio_net;
+#[cfg(feature = "url")]
+mod urls;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct Multiaddr {
-    bytes: Vec<u8>,
+    bytes: Storage,
+}
+
+// The buffer behind a Multiaddr. With the `bytes` feature it is reference
+// counted, so clones don't copy the address.
+#[cfg(not(feature = "bytes"))]
+type Storage = storage::SmallBytes;
+#[cfg(feature = "bytes")]
+type Storage = bytes::Bytes;
+
+#[cfg(not(feature = "bytes"))]
+fn storage_from_slice(b: &[u8]) -> Storage {
+    storage::SmallBytes::from_slice(b)
+}
+#[cfg(feature = "bytes")]
+fn storage_from_slice(b: &[u8]) -> Storage {
+    bytes::Bytes::copy_from_slice(b)
+}
+
+#[cfg(not(feature = "bytes"))]
+fn storage_with_capacity(n: usize) -> Storage {
+    storage::SmallBytes::with_capacity(n)
+}
+// Bytes is immutable, so there is nothing to reserve
+#[cfg(feature = "bytes")]
+fn storage_with_capacity(_: usize) -> Storage {
+    bytes::Bytes::new()
+}
+
+#[cfg(not(feature = "bytes"))]
+fn storage_append(s: &mut Storage, b: &[u8]) {
+    s.extend_from_slice(b)
+}
+#[cfg(feature = "bytes")]
+fn storage_append(s: &mut Storage, b: &[u8]) {
+    let mut v = s.to_vec();
+    v.extend_from_slice(b);
+    *s = v.into();
+}
+
+#[cfg(not(feature = "bytes"))]
+fn storage_into_vec(s: Storage) -> Vec<u8> {
+    s.into()
+}
+#[cfg(feature = "bytes")]
+fn storage_into_vec(s: Storage) -> Vec<u8> {
+    s.to_vec()
 }
 
 impl PartialEq for Multiaddr {
@@ -27,31 +217,732 @@ impl PartialEq for Multiaddr {
 
 impl Eq for Multiaddr { }
 
+/// Parses the string and compares; a string that isn't a valid multiaddr
+/// is never equal.
+impl PartialEq<str> for Multiaddr {
+    fn eq(&self, other: &str) -> bool {
+        match Multiaddr::from_str(other) {
+            Ok(ma) => *self == ma,
+            Err(_) => false,
+        }
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Multiaddr {
+    fn eq(&self, other: &&'a str) -> bool {
+        *self == **other
+    }
+}
+
+/// A socket address is equal to `/ip4/<ip>/tcp/<port>` or
+/// `/ip6/<ip>/tcp/<port>`.
+impl PartialEq<SocketAddr> for Multiaddr {
+    fn eq(&self, other: &SocketAddr) -> bool {
+        let mut iter = self.iter();
+        let ip_matches = match (iter.next(), other.ip()) {
+            (Some((IP4, addr)), IpAddr::V4(ip)) => addr == &ip.octets()[..],
+            (Some((IP6, addr)), IpAddr::V6(ip)) => addr == &ip.octets()[..],
+            _ => false,
+        };
+        let port_matches = match iter.next() {
+            Some((TCP, addr)) => BigEndian::read_u16(addr) == other.port(),
+            _ => false,
+        };
+        ip_matches && port_matches && iter.next().is_none()
+    }
+}
+
+// Hashing and ordering go through Maddr, as Borrow<Maddr> requires
+impl Hash for Multiaddr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl PartialOrd for Multiaddr {
+    fn partial_cmp(&self, other: &Multiaddr) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Multiaddrs are ordered by their binary form.
+impl Ord for Multiaddr {
+    fn cmp(&self, other: &Multiaddr) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
 impl FromStr for Multiaddr {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let bytes = try!(parse_str_to_bytes(s));
-        Ok(Multiaddr { bytes: bytes })
+        Registry::new().parse(s)
+    }
+}
+
+impl fmt::Display for Multiaddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Display::fmt(&**self, f)
     }
 }
 
-#[derive(Debug)]
-pub enum ParseError {
-    InvalidCode(String),
-    InvalidAddress(String),
-    Other(String),
+impl fmt::Debug for Multiaddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Debug::fmt(&**self, f)
+    }
 }
 
-pub type ParseResult<T> = Result<T, ParseError>;
+impl Deref for Multiaddr {
+    type Target = Maddr;
+    fn deref(&self) -> &Maddr {
+        Maddr::from_bytes_unchecked(&self.bytes[..])
+    }
+}
+
+impl Borrow<Maddr> for Multiaddr {
+    fn borrow(&self) -> &Maddr {
+        self
+    }
+}
+
+impl AsRef<Maddr> for Multiaddr {
+    fn as_ref(&self) -> &Maddr {
+        self
+    }
+}
+
+impl AsRef<[u8]> for Multiaddr {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<Multiaddr> for Vec<u8> {
+    fn from(ma: Multiaddr) -> Vec<u8> {
+        storage_into_vec(ma.bytes)
+    }
+}
+
+/// A borrowed multiaddr, which is to `Multiaddr` what `Path` is to `PathBuf`.
+///
+/// This is an unsized type wrapping the bytes of a valid multiaddr, so it is
+/// always used behind a pointer such as `&Maddr`.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Maddr {
+    bytes: [u8],
+}
+
+impl Maddr {
+    /// Validates `b` and borrows it as a multiaddr.
+    pub fn from_bytes(b: &[u8]) -> ParseResult<&Maddr> {
+        try!(Multiaddr::validate(b));
+        Ok(Maddr::from_bytes_unchecked(b))
+    }
+
+    fn from_bytes_unchecked(b: &[u8]) -> &Maddr {
+        // Maddr is a plain wrapper around [u8], so this cast is sound
+        unsafe { &*(b as *const [u8] as *const Maddr) }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the length of the binary form of the multiaddr.
+    pub fn byte_len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns the number of bytes written by `write_to` and `encode_into`,
+    /// the same as `byte_len`.
+    pub fn encoded_len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Writes the binary form of the multiaddr.
+    pub fn write_to<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.bytes)
+    }
+
+    /// Copies the binary form of the multiaddr to the start of `buf`,
+    /// returning the number of bytes written.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, BufferTooSmall> {
+        let len = self.bytes.len();
+        if buf.len() < len {
+            return Err(BufferTooSmall { needed: len, available: buf.len() });
+        }
+        buf[..len].copy_from_slice(&self.bytes);
+        Ok(len)
+    }
+
+    /// Returns the number of components, counting without allocating.
+    pub fn component_count(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns the protocols of the multiaddr in order.
+    pub fn protocols(&self) -> Vec<Protocol> {
+        self.iter().map(|(p, _)| p).collect()
+    }
+
+    /// Returns the names of the protocols of the multiaddr in order, e.g.
+    /// `["ip4", "tcp"]` for "/ip4/1.2.3.4/tcp/80".
+    pub fn protocol_stack(&self) -> Vec<&'static str> {
+        self.iter().map(|(p, _)| p.to_str()).collect()
+    }
+
+    /// Returns the textual form of the address of the first occurrence of
+    /// `proto`, like go-multiaddr's ValueForProtocol. Protocols that don't
+    /// have an address give an empty string.
+    pub fn value_for(&self, proto: Protocol) -> Option<String> {
+        self.value_bytes_for(proto).map(|addr| {
+            match proto.size() {
+                Size::Fixed(0) => String::new(),
+                _ => address_bytes_to_string(addr, &proto),
+            }
+        })
+    }
+
+    /// Returns the bytes of the address of the first occurrence of `proto`.
+    pub fn value_bytes_for(&self, proto: Protocol) -> Option<&[u8]> {
+        self.iter().find(|&(p, _)| p == proto).map(|(_, addr)| addr)
+    }
+
+    /// Returns the first ip4 or ip6 address.
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.iter().filter_map(|(p, addr)| {
+            match p {
+                IP4 => Some(IpAddr::V4(read_ip4(addr))),
+                IP6 => Some(IpAddr::V6(read_ip6(addr))),
+                _ => None,
+            }
+        }).next()
+    }
+
+    /// Returns the first tcp or udp port.
+    pub fn port(&self) -> Option<u16> {
+        self.iter().filter_map(|(p, addr)| {
+            match p {
+                TCP | UDP => Some(BigEndian::read_u16(addr)),
+                _ => None,
+            }
+        }).next()
+    }
+
+    /// Checks whether the multiaddr starts with the ip address and port of
+    /// `addr`, as tcp or udp, ignoring any components after them. Unlike
+    /// `==`, this matches "/ip4/1.2.3.4/udp/443/quic" to 1.2.3.4:443.
+    pub fn matches_socket(&self, addr: &SocketAddr) -> bool {
+        let mut iter = self.iter();
+        let ip_matches = match (iter.next(), addr.ip()) {
+            (Some((IP4, b)), IpAddr::V4(ip)) => b == &ip.octets()[..],
+            (Some((IP6, b)), IpAddr::V6(ip)) => b == &ip.octets()[..],
+            _ => false,
+        };
+        ip_matches && match iter.next() {
+            Some((TCP, b)) | Some((UDP, b)) => BigEndian::read_u16(b) == addr.port(),
+            _ => false,
+        }
+    }
+
+    /// Returns the textual form with each ip6zone merged into the ip6
+    /// address after it, as in "/ip6/fe80::1%eth0/tcp/80", the form that
+    /// parses back to "/ip6zone/eth0/ip6/fe80::1/tcp/80".
+    pub fn to_scoped_string(&self) -> String {
+        let mut s = String::new();
+        let mut iter = self.iter();
+        let mut zone = None;
+        while !iter.remaining().is_empty() {
+            let (proto, addr) = match iter.next() {
+                Some(c) => c,
+                None => return self.to_string(),
+            };
+            if let Some(zone) = zone.take() {
+                if proto == IP6 {
+                    s.push_str(&format!("/ip6/{}%{}", read_ip6(addr), zone));
+                    continue;
+                }
+                s.push_str(&format!("/ip6zone/{}", zone));
+            }
+            if proto == IP6ZONE {
+                zone = Some(String::from_utf8_lossy(addr));
+                continue;
+            }
+            s.push('/');
+            s.push_str(proto.name());
+            if proto.requires_value() {
+                s.push('/');
+                s.push_str(&address_bytes_to_string(addr, &proto));
+            }
+        }
+        if let Some(zone) = zone {
+            s.push_str(&format!("/ip6zone/{}", zone));
+        }
+        s
+    }
+
+    /// Returns the multihash of the first ipfs address.
+    #[cfg(feature = "multihash")]
+    pub fn peer_id(&self) -> Option<Multihash> {
+        self.value_bytes_for(IPFS).and_then(|addr| Multihash::from_bytes(addr.to_vec()).ok())
+    }
+
+    /// Checks whether the leading components of the multiaddr are those of
+    /// `prefix`.
+    pub fn starts_with(&self, prefix: &Maddr) -> bool {
+        let mut components = self.iter();
+        prefix.iter().all(|c| components.next() == Some(c))
+    }
+
+    /// Checks whether the trailing components of the multiaddr are those of
+    /// `suffix`. Unlike comparing bytes, this never matches part of a
+    /// component.
+    pub fn ends_with(&self, suffix: &Maddr) -> bool {
+        let components: Vec<_> = self.iter().collect();
+        let suffix: Vec<_> = suffix.iter().collect();
+        components.ends_with(&suffix)
+    }
+
+    /// Checks whether the components of `other` appear consecutively in the
+    /// multiaddr.
+    pub fn contains(&self, other: &Maddr) -> bool {
+        let components: Vec<_> = self.iter().collect();
+        let other: Vec<_> = other.iter().collect();
+        other.is_empty() || components.windows(other.len()).any(|w| w == &other[..])
+    }
+
+    /// Returns a copy of the multiaddr with the address of the first
+    /// occurrence of `proto` replaced by `value`, which is in textual form.
+    /// Components after a custom protocol are copied unchanged; use
+    /// `Registry::replace` to look past them.
+    pub fn replace(&self, proto: Protocol, value: &str) -> ParseResult<Multiaddr> {
+        Registry::new().replace(self, proto, value)
+    }
+
+    /// Returns the "thin waist" of the multiaddr: its leading ip4 or ip6
+    /// component together with the tcp or udp component right after it, if
+    /// any. Returns None if the multiaddr doesn't start with an ip address.
+    /// Everything after those two is left out, custom protocols included.
+    pub fn thin_waist(&self) -> Option<Multiaddr> {
+        let mut components = self.iter();
+        let mut bytes = Vec::new();
+        match components.next() {
+            Some((p @ IP4, addr)) | Some((p @ IP6, addr)) => write_component(p, addr, &mut bytes),
+            _ => return None,
+        }
+        if let Some((p @ TCP, addr)) | Some((p @ UDP, addr)) = components.next() {
+            write_component(p, addr, &mut bytes);
+        }
+        Some(Multiaddr { bytes: bytes.into() })
+    }
+
+    /// Replaces the first /tcp/0 or /udp/0 by the port that was actually
+    /// bound, for announcing a listener that was given port 0. The
+    /// multiaddr is returned unchanged if it has no zero port. Components
+    /// after a custom protocol are copied unchanged.
+    pub fn fill_port(&self, bound: u16) -> Multiaddr {
+        let mut bytes = Vec::with_capacity(self.bytes.len());
+        let mut filled = false;
+        let mut components = self.iter();
+        for (p, addr) in &mut components {
+            if (p == TCP || p == UDP) && addr == [0, 0] && !filled {
+                write_protocol(p, &mut bytes);
+                bytes.write_u16::<BigEndian>(bound).unwrap();
+                filled = true;
+            } else {
+                write_component(p, addr, &mut bytes);
+            }
+        }
+        bytes.extend(components.remaining());
+        Multiaddr { bytes: bytes.into() }
+    }
+
+    /// Returns a copy of the multiaddr with its first ip4 or ip6 component
+    /// replaced by `ip`, which may be of the other family, e.g. once the
+    /// external address of a host is known. Returns None if the multiaddr
+    /// has no ip address. Components after a custom protocol, which can only
+    /// be decoded by its `Registry`, are copied unchanged.
+    pub fn with_ip(&self, ip: IpAddr) -> Option<Multiaddr> {
+        let mut bytes = Vec::with_capacity(self.bytes.len() + 12);
+        let mut replaced = false;
+        let mut components = self.iter();
+        for (p, addr) in &mut components {
+            match p {
+                IP4 | IP6 if !replaced => {
+                    match ip {
+                        IpAddr::V4(ip) => {
+                            write_protocol(IP4, &mut bytes);
+                            write_ip4_to_vec(&ip, &mut bytes);
+                        }
+                        IpAddr::V6(ip) => {
+                            write_protocol(IP6, &mut bytes);
+                            write_ip6_to_vec(&ip, &mut bytes);
+                        }
+                    }
+                    replaced = true;
+                }
+                _ => write_component(p, addr, &mut bytes),
+            }
+        }
+        // Custom protocols are copied through undecoded
+        bytes.extend(components.remaining());
+        if replaced { Some(Multiaddr { bytes: bytes.into() }) } else { None }
+    }
+
+    /// Returns a copy of the multiaddr with its first tcp or udp port
+    /// replaced by `port`. Unlike `fill_port`, the port is replaced whatever
+    /// its value. Returns None if the multiaddr has no tcp or udp port. As
+    /// with `with_ip`, components after a custom protocol are copied
+    /// unchanged.
+    pub fn with_port(&self, port: u16) -> Option<Multiaddr> {
+        let mut bytes = Vec::with_capacity(self.bytes.len());
+        let mut replaced = false;
+        let mut components = self.iter();
+        for (p, addr) in &mut components {
+            match p {
+                TCP | UDP if !replaced => {
+                    write_protocol(p, &mut bytes);
+                    bytes.write_u16::<BigEndian>(port).unwrap();
+                    replaced = true;
+                }
+                _ => write_component(p, addr, &mut bytes),
+            }
+        }
+        bytes.extend(components.remaining());
+        if replaced { Some(Multiaddr { bytes: bytes.into() }) } else { None }
+    }
+
+    /// Removes the last occurrence of `proto` and everything after it, like
+    /// go-multiaddr's DecapsulateCode, e.g. to drop a trailing /ipfs
+    /// component. Returns the multiaddr unchanged if `proto` isn't in it.
+    pub fn decapsulate_code(&self, proto: Protocol) -> Multiaddr {
+        let mut end = self.bytes.len();
+        let mut components = self.iter();
+        let mut start = 0;
+        while let Some((p, _)) = components.next() {
+            if p == proto {
+                end = start;
+            }
+            start = self.bytes.len() - components.remaining().len();
+        }
+        Multiaddr { bytes: storage_from_slice(&self.bytes[..end]) }
+    }
+
+    /// Splits off a trailing /ipfs (or /p2p) component, returning the
+    /// transport address to dial and the multihash of the peer expected at
+    /// it, if any. Only the last component is looked at.
+    pub fn without_p2p(&self) -> (&Maddr, Option<&[u8]>) {
+        let mut components = self.iter();
+        let mut start = 0;
+        let mut last = None;
+        while let Some((p, addr)) = components.next() {
+            last = Some((start, p, addr));
+            start = self.bytes.len() - components.remaining().len();
+        }
+        match last {
+            Some((start, IPFS, peer_id)) => {
+                (Maddr::from_bytes_unchecked(&self.bytes[..start]), Some(peer_id))
+            }
+            _ => (self, None),
+        }
+    }
+
+    /// Splits the multiaddr into single-component multiaddrs, like
+    /// go-multiaddr's Split. `Multiaddr::join` reverses this. A custom
+    /// protocol can't be told apart from what follows it without its
+    /// `Registry`, so the last piece holds it and everything after it.
+    pub fn split(&self) -> Vec<Multiaddr> {
+        let mut components = self.iter();
+        let mut parts: Vec<_> = components.by_ref().map(|(p, addr)| {
+            let mut bytes = Vec::new();
+            write_component(p, addr, &mut bytes);
+            Multiaddr { bytes: bytes.into() }
+        }).collect();
+        if !components.remaining().is_empty() {
+            parts.push(Multiaddr { bytes: storage_from_slice(components.remaining()) });
+        }
+        parts
+    }
+
+    /// Checks whether this is a relayed address, i.e. contains /p2p-circuit.
+    pub fn is_relayed(&self) -> bool {
+        self.iter().any(|(p, _)| p == P2P_CIRCUIT)
+    }
+
+    /// Splits a relayed address at its first /p2p-circuit into the address
+    /// of the relay and the address of the destination behind it, either of
+    /// which may be empty. Returns None if the address isn't relayed. Only
+    /// a /p2p-circuit before any custom protocol is found, as with `split`.
+    pub fn split_circuit(&self) -> Option<(Multiaddr, Multiaddr)> {
+        let parts = self.split();
+        parts.iter()
+             .position(|part| part.iter().next().map(|(p, _)| p) == Some(P2P_CIRCUIT))
+             .map(|i| (Multiaddr::join(&parts[..i]), Multiaddr::join(&parts[i + 1..])))
+    }
+
+    /// Iterates over the protocols of the multiaddr along with the bytes of
+    /// their addresses. Nothing is decoded or allocated.
+    pub fn iter<'a>(&'a self) -> Iter<'a> {
+        Iter { bytes: &self.bytes }
+    }
+}
+
+impl<'a> IntoIterator for &'a Maddr {
+    type Item = (Protocol, &'a [u8]);
+    type IntoIter = Iter<'a>;
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the components of a multiaddr, yielding each protocol
+/// and the bytes of its address, which are borrowed from the multiaddr.
+///
+/// The address bytes of a variable-sized protocol don't include the
+/// length prefix, and protocols without an address yield an empty slice.
+/// Iteration stops at the first protocol that isn't built in, such as one
+/// registered with a `Registry`.
+///
+/// Components can only be decoded front to back, so `next_back` scans the
+/// remaining bytes. Multiaddrs are short enough for this not to matter.
+#[derive(Clone)]
+pub struct Iter<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iter<'a> {
+    // The bytes that haven't been iterated over yet, including a custom
+    // protocol that iteration stopped at and everything after it
+    fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (Protocol, &'a [u8]);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        // Custom protocols can only be decoded by their registry, so they and
+        // everything after them are left in the remaining bytes
+        read_builtin_component(&mut self.bytes)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Every component takes at least one byte
+        (0, Some(self.bytes.len()))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Iter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        // Find the start of the last component
+        let mut rest = self.bytes;
+        let mut last = None;
+        while !rest.is_empty() {
+            let start = self.bytes.len() - rest.len();
+            match read_builtin_component(&mut rest) {
+                Some((p, addr)) => last = Some((start, p, addr)),
+                None => break,
+            }
+        }
+        match last {
+            Some((start, p, addr)) => {
+                self.bytes = &self.bytes[..start];
+                Some((p, addr))
+            }
+            None => {
+                self.bytes = &[];
+                None
+            }
+        }
+    }
+}
+
+impl AsRef<Maddr> for Maddr {
+    fn as_ref(&self) -> &Maddr {
+        self
+    }
+}
+
+impl AsRef<[u8]> for Maddr {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl ToOwned for Maddr {
+    type Owned = Multiaddr;
+    fn to_owned(&self) -> Multiaddr {
+        Multiaddr { bytes: storage_from_slice(&self.bytes) }
+    }
+}
+
+/// Shows the textual form, e.g. `"/ip4/1.2.3.4/tcp/80"`, or with `{:#?}`
+/// both the textual form and the bytes in hex. Addresses containing custom
+/// protocols only show their bytes.
+impl fmt::Debug for Maddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let text = match verify_bytes(&self.bytes, &Registry::new()) {
+            Ok(()) => Some(self.to_string()),
+            Err(_) => None,
+        };
+        let hex: String = self.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        match text {
+            Some(ref text) if !f.alternate() => write!(f, "{:?}", text),
+            Some(text) => {
+                f.debug_struct("Multiaddr")
+                 .field("text", &text)
+                 .field("bytes", &hex)
+                 .finish()
+            }
+            None => f.debug_struct("Multiaddr").field("bytes", &hex).finish(),
+        }
+    }
+}
+
+/// Writes the textual form of the multiaddr. The alternate form, `{:#}`,
+/// instead writes a line per component with its protocol name, code,
+/// decoded value and the value's bytes in hex, for debugging.
+///
+/// Components that only a `Registry` can decode are written as their code
+/// followed by the rest of the bytes in hex, e.g. `/3145729/0a0b`; use
+/// `Registry::display` to show them by name.
+impl fmt::Display for Maddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt_multiaddr_bytes(&self.bytes, &Registry::new(), f)
+    }
+}
+
+/// The error from `Maddr::encode_into` when the multiaddr doesn't fit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BufferTooSmall {
+    pub needed: usize,
+    pub available: usize,
+}
+
+impl fmt::Display for BufferTooSmall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "Buffer of {} bytes is too small, {} needed", self.available, self.needed)
+    }
+}
+
+impl std::error::Error for BufferTooSmall {}
+
+/// Bounds enforced when validating the bytes of a multiaddr, which often
+/// come from untrusted peers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum length of the whole multiaddr, in bytes
+    pub max_len: usize,
+    /// Maximum length of the address of a single protocol, in bytes
+    pub max_component_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_len: 1024,
+            max_component_len: 512,
+        }
+    }
+}
 
 impl Multiaddr {
+    /// Creates a multiaddr with no components, the same as parsing "/".
+    pub fn empty() -> Multiaddr {
+        Multiaddr { bytes: storage_with_capacity(0) }
+    }
+
+    /// Creates an empty multiaddr with room for `n` bytes, to be filled by
+    /// `extend`.
+    pub fn with_capacity(n: usize) -> Multiaddr {
+        Multiaddr { bytes: storage_with_capacity(n) }
+    }
+
     pub fn from_bytes(b: Vec<u8>) -> ParseResult<Multiaddr> {
-        try!(verify_multiaddr_bytes(&b[..]));
-        Ok(Multiaddr { bytes: b })
+        Registry::new().from_bytes(b)
     }
 
-    pub fn as_bytes(&self) -> &[u8] {
-        &self.bytes[..]
+    /// Concatenates multiaddrs into one, like go-multiaddr's Join.
+    pub fn join<I, M>(addrs: I) -> Multiaddr
+        where I: IntoIterator<Item = M>,
+              M: AsRef<Maddr>
+    {
+        let mut bytes = Vec::new();
+        for addr in addrs {
+            bytes.extend(addr.as_ref().as_bytes());
+        }
+        Multiaddr { bytes: bytes.into() }
+    }
+
+    /// Checks that `b` is a valid multiaddr without copying it.
+    pub fn validate(b: &[u8]) -> ParseResult<()> {
+        verify_multiaddr_bytes(b, &Registry::new())
+    }
+
+    /// Reads a multiaddr from the front of `b`, returning it along with the
+    /// number of bytes it took up. Since binary multiaddrs aren't delimited,
+    /// it ends at the end of `b` or at the first byte that doesn't start a
+    /// known protocol code. A component that is cut short or has an invalid
+    /// address is an error.
+    pub fn parse_prefix(b: &[u8]) -> ParseResult<(Multiaddr, usize)> {
+        let registry = Registry::new();
+        let mut rest = b;
+        while !rest.is_empty() {
+            let mut peek = rest;
+            match read_varint(&mut peek) {
+                Ok(code) if registry.lookup_code(code).is_some() => {}
+                _ => break,
+            }
+            if let (Proto::Builtin(p), addr) = try!(read_component(&mut rest, &registry)) {
+                try!(registry.check_address(p, addr).map_err(ParseError::invalid_address));
+            }
+        }
+
+        let len = b.len() - rest.len();
+        let max_len = registry.limits().max_len;
+        if len > max_len {
+            return Err(ParseError::too_long(format!(
+                "Multiaddr is {} bytes, more than the limit of {}",
+                len,
+                max_len
+            )));
+        }
+        Ok((Multiaddr { bytes: storage_from_slice(&b[..len]) }, len))
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Multiaddr {
+    type Error = ParseError;
+    fn try_from(b: &'a [u8]) -> Result<Self, Self::Error> {
+        try!(Multiaddr::validate(b));
+        Ok(Multiaddr { bytes: storage_from_slice(b) })
+    }
+}
+
+impl TryFrom<Vec<u8>> for Multiaddr {
+    type Error = ParseError;
+    fn try_from(b: Vec<u8>) -> Result<Self, Self::Error> {
+        Multiaddr::from_bytes(b)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Multiaddr {
+    type Error = ParseError;
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        Multiaddr::from_str(s)
+    }
+}
+
+impl TryFrom<String> for Multiaddr {
+    type Error = ParseError;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Multiaddr::from_str(&s)
     }
 }
 
@@ -60,7 +951,16 @@ pub trait ToMultiaddr {
 }
 
 fn write_protocol(proto: Protocol, buf: &mut Vec<u8>) {
-    buf.write_unsigned_varint_32(u16::from(proto) as u32).unwrap();
+    buf.write_unsigned_varint_32(u32::from(proto)).unwrap();
+}
+
+// Appends a component as yielded by Iter
+fn write_component(proto: Protocol, addr: &[u8], buf: &mut Vec<u8>) {
+    write_protocol(proto, buf);
+    if let Size::Variable = proto.size() {
+        buf.write_unsigned_varint_32(addr.len() as u32).unwrap();
+    }
+    buf.extend(addr);
 }
 
 impl ToMultiaddr for Ipv4Addr {
@@ -81,146 +981,609 @@ impl ToMultiaddr for Ipv6Addr {
     }
 }
 
-fn write_ip4_to_vec(ip: &Ipv4Addr, vec: &mut Vec<u8>) {
-    vec.extend(ip.octets().iter());
+/// /ip6zone/<scope id>/ip6/<ip>/tcp/<port>, without the ip6zone if the
+/// scope id is 0. Use `net::udp_multiaddr` for udp.
+impl ToMultiaddr for SocketAddrV6 {
+    fn to_multiaddr(&self) -> ParseResult<Multiaddr> {
+        Ok(net::tcp_multiaddr(&SocketAddr::V6(*self)))
+    }
+}
+
+fn write_ip4_to_vec(ip: &Ipv4Addr, vec: &mut Vec<u8>) {
+    vec.extend(ip.octets().iter());
+}
+
+fn write_ip6_to_vec(ip: &Ipv6Addr, vec: &mut Vec<u8>) {
+    for &seg in ip.segments().iter() {
+        vec.write_u16::<BigEndian>(seg).unwrap()
+    }
+}
+
+fn parse_str_to_bytes(s: &str, registry: &Registry, opts: &ParseOptions)
+                      -> ParseResult<Vec<u8>> {
+    // The binary form is nearly always shorter than the text
+    let mut ma = Vec::with_capacity(s.len());
+    try!(parse_str_into(s, registry, opts, &mut ma));
+    Ok(ma)
+}
+
+// Like parse_str_to_bytes, but writes into `ma`, which is cleared first, so
+// that its allocation can be reused.
+fn parse_str_into(s: &str, registry: &Registry, opts: &ParseOptions, ma: &mut Vec<u8>)
+                  -> ParseResult<()> {
+    ma.clear();
+    if s.len() > 1 && s.ends_with('/') && !opts.trailing_slash() {
+        return Err(ParseError::other(format!("Multiaddr must not end with '/'")));
+    }
+    let s = s.trim_end_matches('/');
+    let mut segs = s.split('/');
+
+    if segs.next() != Some("") {
+        // TODO: should this become InvalidCode instead of Other?
+        return Err(ParseError::other(format!("Multiaddr must begin with '/'")));
+    }
+    let mut segs = segs.filter(|seg| !seg.is_empty() || !opts.empty_segments());
+
+    while let Some(seg) = segs.next() {
+        let p = try!(registry.lookup_name_with(seg, opts).ok_or_else(|| {
+            ParseError::invalid_code(format!("Invalid protocol: {}", seg))
+        }));
+        let start = ma.len();
+
+        // I don't think these can fail?
+        ma.write_unsigned_varint_32(p.code()).unwrap();
+
+        if let Size::Fixed(0) = p.size() {
+            continue;
+        }
+
+        // If we reach here, we are looking for an address
+        let addr = try!(segs.next().ok_or_else(|| {
+            ParseError::invalid_address(format!("Address not found for protocol {}", p.name()))
+        }));
+
+        if let Proto::Builtin(TCP) | Proto::Builtin(UDP) | Proto::Builtin(SCTP) |
+               Proto::Builtin(DCCP) = p {
+            if !opts.zero_port() && addr.parse::<u16>() == Ok(0) {
+                return Err(ParseError::invalid_address(format!("Port 0 is not allowed")));
+            }
+        }
+
+        // A scoped ip6 literal, fe80::1%eth0, becomes /ip6zone/eth0/ip6/fe80::1
+        if let (Proto::Builtin(IP6), Some(i)) = (p, addr.find('%')) {
+            ma.truncate(start);
+            write_protocol(IP6ZONE, ma);
+            try!(write_checked_address(&addr[i + 1..], IP6ZONE, registry, ma));
+            write_protocol(IP6, ma);
+            try!(write_checked_address(&addr[..i], IP6, registry, ma));
+            continue;
+        }
+
+        try!(match p {
+            Proto::Builtin(p) => write_checked_address(addr, p, registry, ma),
+            Proto::Custom(c) => {
+                registry::write_custom_address(addr, c, ma).map_err(ParseError::invalid_address)
+            }
+            Proto::Unknown(_) => {
+                if write_length_prefixed(ma, |ma| multibase::base16_decode_into(addr, ma)) {
+                    Ok(())
+                } else {
+                    Err(ParseError::invalid_address(format!("Invalid hex value for unknown \
+                                                             protocol {}", seg)))
+                }
+            }
+        });
+    }
+
+    Ok(())
+}
+
+// Like write_address, but also applies the registry's checks to the value
+fn write_checked_address(s: &str, proto: Protocol, registry: &Registry, v: &mut Vec<u8>)
+                         -> ParseResult<()> {
+    let start = v.len();
+    try!(write_address(s, &proto, v));
+    let mut addr = &v[start..];
+    if let Size::Variable = proto.size() {
+        try!(read_varint(&mut addr).map_err(|e| {
+            ParseError::invalid_address(e.to_string()).with_source(e)
+        }));
+    }
+    registry.check_address(proto, addr).map_err(ParseError::invalid_address)
+}
+
+// Appends the value `write` appends, prefixed with its length as a varint,
+// or appends nothing if `write` fails. The prefix is inserted once the value
+// is written, so that the value needs no buffer of its own.
+fn write_length_prefixed<F: FnOnce(&mut Vec<u8>) -> bool>(v: &mut Vec<u8>, write: F) -> bool {
+    let start = v.len();
+    if !write(v) {
+        return false;
+    }
+    let mut prefix = [0; 5];
+    let n = {
+        let mut cursor = io::Cursor::new(&mut prefix[..]);
+        cursor.write_unsigned_varint_32((v.len() - start) as u32).unwrap();
+        cursor.position() as usize
+    };
+    v.splice(start..start, prefix[..n].iter().cloned());
+    true
+}
+
+// Parses the address of `proto` and appends its binary form to `v`.
+fn write_address(s: &str, proto: &Protocol, v: &mut Vec<u8>) -> ParseResult<()> {
+    match *proto {
+        IP4 => {
+            match Ipv4Addr::from_str(s) {
+                Err(e) => {
+                    let message = format!("Error parsing ip4 address: {}", e);
+                    Err(ParseError::invalid_address(message).with_source(e))
+                }
+                Ok(ip) => {
+                    write_ip4_to_vec(&ip, v);
+                    Ok(())
+                }
+            }
+        }
+        IP6 => {
+            match Ipv6Addr::from_str(s) {
+                Err(e) => {
+                    let message = format!("Error parsing ip6 address: {}", e);
+                    Err(ParseError::invalid_address(message).with_source(e))
+                }
+                Ok(ip) => {
+                    write_ip6_to_vec(&ip, v);
+                    Ok(())
+                }
+            }
+        }
+        IP6ZONE => {
+            if s.is_empty() || s.contains('/') {
+                return Err(ParseError::invalid_address(format!("Invalid ip6zone: {:?}", s)));
+            }
+            v.write_unsigned_varint_32(s.len() as u32).unwrap();
+            v.extend(s.as_bytes());
+            Ok(())
+        }
+        IPCIDR => {
+            match s.parse::<u8>() {
+                Err(e) => {
+                    let message = format!("Error parsing ipcidr prefix length: {}", e);
+                    Err(ParseError::invalid_address(message).with_source(e))
+                }
+                Ok(bits) => {
+                    v.push(bits);
+                    Ok(())
+                }
+            }
+        }
+        MEMORY => {
+            match s.parse::<u64>() {
+                Err(e) => {
+                    let message = format!("Error parsing memory id: {}", e);
+                    Err(ParseError::invalid_address(message).with_source(e))
+                }
+                Ok(id) => {
+                    v.extend(&id.to_be_bytes());
+                    Ok(())
+                }
+            }
+        }
+        #[cfg(feature = "proto-dns")]
+        DNS | DNS4 | DNS6 | DNSADDR => {
+            let name = try!(idn::to_ascii(s).map_err(|e| {
+                ParseError::invalid_address(format!("{} for {}", e, proto))
+            }));
+            v.write_unsigned_varint_32(name.len() as u32).unwrap();
+            v.extend(name.as_bytes());
+            Ok(())
+        }
+        IPFS => {
+            // the registry decides which multihashes are acceptable
+            let written = write_length_prefixed(v, |v| {
+                cid::write_peer_id_from_cid(s, v) || multibase::base58_decode_into(s, v)
+            });
+            if written {
+                Ok(())
+            } else {
+                let message = format!("Invalid base58 or CIDv1 in ipfs address: {}", s);
+                Err(ParseError::invalid_address(message))
+            }
+        }
+        TCP | UDP | SCTP | DCCP => {
+            match s.parse::<u16>() {
+                Err(e) => {
+                    let message = format!("Error parsing tcp/udp/sctp/dccp port number: {}", e);
+                    Err(ParseError::invalid_address(message).with_source(e))
+                }
+                Ok(port) => {
+                    v.write_u16::<BigEndian>(port).unwrap();
+                    Ok(())
+                }
+            }
+        }
+        // The base32 address without ".onion", a colon and the port
+        #[cfg(feature = "proto-onion")]
+        ONION | ONION3 => {
+            let mut parts = s.splitn(2, ':');
+            let host = parts.next().unwrap();
+            let port = match parts.next().map(u16::from_str) {
+                Some(Ok(port)) if port > 0 => port,
+                _ => return Err(ParseError::invalid_address(format!("Invalid port in {} address {}", proto, s))),
+            };
+            let start = v.len();
+            let len = if *proto == ONION { 10 } else { 35 };
+            if !multibase::base32_decode_into(host, v) || v.len() - start != len {
+                v.truncate(start);
+                return Err(ParseError::invalid_address(format!("Invalid {} address: {}", proto, s)));
+            }
+            v.write_u16::<BigEndian>(port).unwrap();
+            Ok(())
+        }
+
+        // this function should not be called on the other protocols because they have no
+        // address to parse
+        _ => unreachable!(),
+    }
+}
+
+// Writes the textual form of `ma`, with the addresses `value` returns Some
+// for replaced. Falls back to the plain textual form if `ma` has custom
+// protocols, which can't be displayed without their registry.
+fn to_string_with<F>(ma: &Maddr, value: F) -> String
+    where F: Fn(Protocol, &[u8]) -> Option<String>
+{
+    let mut s = String::new();
+    let mut iter = ma.iter();
+    while !iter.remaining().is_empty() {
+        let (proto, addr) = match iter.next() {
+            Some(c) => c,
+            None => return ma.to_string(),
+        };
+        s.push('/');
+        s.push_str(proto.name());
+        if proto.requires_value() {
+            s.push('/');
+            match value(proto, addr) {
+                Some(v) => s.push_str(&v),
+                None => s.push_str(&address_bytes_to_string(addr, &proto)),
+            }
+        }
+    }
+    s
+}
+
+fn address_bytes_to_string(b: &[u8], proto: &Protocol) -> String {
+    match *proto {
+        IP4 => read_ip4(b).to_string(),
+        IP6 => read_ip6(b).to_string(),
+        IPFS => multibase::base58_encode(b),
+        IPCIDR => b[0].to_string(),
+        IP6ZONE => String::from_utf8_lossy(b).into_owned(),
+        #[cfg(feature = "proto-dns")]
+        DNS | DNS4 | DNS6 | DNSADDR => String::from_utf8_lossy(b).into_owned(),
+        TCP | UDP | SCTP | DCCP => BigEndian::read_u16(b).to_string(),
+        MEMORY => {
+            let mut id = [0; 8];
+            id.copy_from_slice(b);
+            u64::from_be_bytes(id).to_string()
+        }
+        #[cfg(feature = "proto-onion")]
+        ONION | ONION3 => {
+            let (host, port) = b.split_at(b.len() - 2);
+            format!("{}:{}", base32_encode(host), BigEndian::read_u16(port))
+        }
+
+        // the other protocols have no address to display
+        _ => unreachable!(),
+    }
+}
+
+fn read_ip4(b: &[u8]) -> Ipv4Addr {
+    Ipv4Addr::new(b[0], b[1], b[2], b[3])
 }
 
-fn write_ip6_to_vec(ip: &Ipv6Addr, vec: &mut Vec<u8>) {
-    for &seg in ip.segments().iter() {
-        vec.write_u16::<BigEndian>(seg).unwrap()
+fn read_ip6(b: &[u8]) -> Ipv6Addr {
+    let mut segs = [0u16; 8];
+    for (i, seg) in segs.iter_mut().enumerate() {
+        *seg = BigEndian::read_u16(&b[2 * i..]);
     }
+    Ipv6Addr::new(segs[0], segs[1], segs[2], segs[3],
+                  segs[4], segs[5], segs[6], segs[7])
 }
 
-fn parse_str_to_bytes(s: &str) -> ParseResult<Vec<u8>> {
-    let s = s.trim_right_matches('/');
-    let segs_vec: Vec<_> = s.split('/').collect();
-
-    if segs_vec[0] != "" {
-        // TODO: should this become InvalidCode instead of Other?
-        return Err(ParseError::Other(format!("Multiaddr must begin with '/'")));
+// RFC 4648 base32, lowercase and without padding, as used by onion addresses
+fn base32_encode(b: &[u8]) -> String {
+    const ALPHABET: &'static [u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut s = String::new();
+    let mut buf = 0u32;
+    let mut bits = 0;
+    for &byte in b {
+        buf = (buf << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            s.push(ALPHABET[((buf >> bits) & 31) as usize] as char);
+        }
     }
+    if bits > 0 {
+        s.push(ALPHABET[((buf << (5 - bits)) & 31) as usize] as char);
+    }
+    s
+}
 
-    let mut segs = &segs_vec[1..];
-    let mut ma = Cursor::new(Vec::new());
-
-    while segs.len() > 0 {
-        let p = try!(Protocol::from_str(segs[0]).map_err(|_| {
-            ParseError::InvalidCode(format!("Invalid protocol: {}", segs[0]))
-        }));
-
-        segs = &segs[1..];
+// Reads an unsigned varint from the front of `bytes`. Encodings that are
+// longer than necessary or that overflow a u32 are rejected.
+fn read_varint(bytes: &mut &[u8]) -> Result<u32, VarintError> {
+    let mut n: u32 = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        let bits = (b & 0x7f) as u32;
+        // a u32 takes at most 5 bytes, the last of which holds 4 bits
+        if i > 4 || (i == 4 && bits > 0x0f) {
+            return Err(VarintError::Overflow);
+        }
+        n |= bits << (7 * i);
 
-        if let protocol::Size::Fixed(0) = p.size() {
-            continue;
+        if b & 0x80 == 0 {
+            if b == 0 && i > 0 {
+                return Err(VarintError::NotMinimal);
+            }
+            *bytes = &bytes[i + 1..];
+            return Ok(n);
         }
+    }
+    Err(VarintError::UnexpectedEnd)
+}
 
-        // If we reach here, we are looking for an address
-        if segs.len() == 0 {
-            return Err(ParseError::InvalidAddress(format!(
-                "Address not found for protocol {}",
-                p)));
+// Reads the next protocol code and its address from the front of `bytes`.
+fn read_component<'a, 'r>(bytes: &mut &'a [u8], registry: &'r Registry)
+                          -> ParseResult<(Proto<'r>, &'a [u8])> {
+    //   read varint (protocol type code)
+    //   if fixed-length, read that number of bytes
+    //   if variable length, read varint and then that number of bytes.
+    let code = try!(read_varint(bytes).map_err(|e| {
+        ParseError::varint(ErrorKind::InvalidCode, format!("Error reading varint: {}", e), e)
+    }));
+    let proto_type = try!(registry.lookup_code(code).ok_or_else(|| {
+        ParseError::invalid_code(format!("Invalid protocol type code: {}", code))
+    }));
+    let addr_size = match proto_type.size() {
+        Size::Fixed(bits) => (bits / 8 + (bits % 8 != 0) as u32) as usize,
+        Size::Variable => {
+            try!(read_varint(bytes).map_err(|e| {
+                let message = format!("Error reading varint: {}", e);
+                ParseError::varint(ErrorKind::InvalidAddress, message, e)
+            })) as usize
         }
+    };
 
-        let bytes = try!(address_string_to_bytes(segs[0], &p)
-                             .map_err(|e| ParseError::InvalidAddress(e)));
-        // I don't think these can fail?
-        ma.write_unsigned_varint_32(u16::from(p) as u32).unwrap();
-        ma.write_all(&bytes[..]).unwrap();
+    let max_size = registry.limits().max_component_len;
+    if addr_size > max_size {
+        return Err(ParseError::too_long(format!(
+            "Address for protocol {} is {} bytes, more than the limit of {}",
+            proto_type.name(),
+            addr_size,
+            max_size
+        )));
+    }
 
-        segs = &segs[1..];
+    if bytes.len() < addr_size {
+        return Err(ParseError::truncated(format!(
+            "Unexpected end of bytes, expected {} more, found {}",
+            addr_size,
+            bytes.len()
+        )));
     }
 
-    Ok(ma.into_inner())
+    let (addr, rest) = bytes.split_at(addr_size);
+    if addr.last().map_or(false, |b| b & proto_type.size().padding_mask() != 0) {
+        return Err(ParseError::invalid_address(format!(
+            "Padding bits of the address for protocol {} are not zero",
+            proto_type.name()
+        )));
+    }
+    *bytes = rest;
+    Ok((proto_type, addr))
 }
 
-fn address_string_to_bytes(s: &str, proto: &Protocol) -> Result<Vec<u8>, String> {
-    let mut v = Vec::new();
-    match *proto {
-        IP4 => {
-            match Ipv4Addr::from_str(s) {
-                Err(e) => Err(format!("Error parsing ip4 address: {}", e)),
-                Ok(ip) => {
-                    write_ip4_to_vec(&ip, &mut v);
-                    Ok(v)
-                }
-            }
+// Like read_component, but only for builtin protocols and without building an
+// error, so that iterating doesn't allocate. The bytes of a Maddr have already
+// been verified, so this only guards against reading out of bounds.
+fn read_builtin_component<'a>(bytes: &mut &'a [u8]) -> Option<(Protocol, &'a [u8])> {
+    let mut rest = *bytes;
+    let code = match read_varint(&mut rest) {
+        Ok(code) => code,
+        Err(_) => return None,
+    };
+    let proto = match Protocol::from_code(code) {
+        Ok(proto) => proto,
+        Err(()) => return None,
+    };
+    let addr_size = match proto.size() {
+        Size::Fixed(bits) => (bits / 8 + (bits % 8 != 0) as u32) as usize,
+        Size::Variable => match read_varint(&mut rest) {
+            Ok(n) => n as usize,
+            Err(_) => return None,
+        },
+    };
+    if rest.len() < addr_size {
+        return None;
+    }
+    let (addr, rest) = rest.split_at(addr_size);
+    *bytes = rest;
+    Some((proto, addr))
+}
+
+fn verify_multiaddr_bytes(bytes: &[u8], registry: &Registry) -> Result<(), ParseError> {
+    let result = verify_bytes(bytes, registry);
+    if let Err(ref e) = result {
+        log_rejected("multiaddr bytes", bytes, e);
+    }
+    result
+}
+
+fn verify_bytes(mut bytes: &[u8], registry: &Registry) -> Result<(), ParseError> {
+    let max_len = registry.limits().max_len;
+    if bytes.len() > max_len {
+        return Err(ParseError::too_long(format!(
+            "Multiaddr is {} bytes, more than the limit of {}",
+            bytes.len(),
+            max_len
+        )));
+    }
+
+    while bytes.len() > 0 {
+        if let (Proto::Builtin(p), addr) = try!(read_component(&mut bytes, registry)) {
+            try!(registry.check_address(p, addr).map_err(ParseError::invalid_address));
         }
-        IP6 => {
-            match Ipv6Addr::from_str(s) {
-                Err(e) => Err(format!("Error parsing ip6 address: {}", e)),
-                Ok(ip) => {
-                    write_ip6_to_vec(&ip, &mut v);
-                    Ok(v)
-                }
+    }
+    Ok(())
+}
+
+// Reports an input that was rejected as a tracing event, so that services
+// taking addresses from untrusted peers can watch what they reject and why.
+#[cfg(feature = "tracing")]
+fn log_rejected<T: fmt::Debug + ?Sized, E: fmt::Debug>(what: &'static str, input: &T, error: &E) {
+    debug!(input = ?input, error = ?error, "rejected {}", what);
+}
+
+#[cfg(not(feature = "tracing"))]
+fn log_rejected<T: ?Sized, E>(_: &'static str, _: &T, _: &E) {}
+
+// Checks the parts of an address that can't be checked by its length alone,
+// so that every valid multiaddr has a textual form that parses back to it.
+// ipfs addresses are checked by the registry's MultihashValidator.
+#[cfg_attr(not(feature = "proto-dns"), allow(unused_variables))]
+pub(crate) fn check_address_bytes(b: &[u8], proto: Protocol) -> Result<(), String> {
+    match proto {
+        IP6ZONE => {
+            match std::str::from_utf8(b) {
+                Ok(zone) if !zone.is_empty() && !zone.contains('/') => Ok(()),
+                _ => Err(format!("Invalid ip6zone: {:?}", String::from_utf8_lossy(b))),
             }
         }
-        IPFS => {
-            // verify string is a valid Multihash and convert it to bytes
-            let mut bytes = try!(Multihash::from_base58_str(s)).into_bytes();
-            let mut cursor = Cursor::new(v);
-            cursor.write_unsigned_varint_32(bytes.len() as u32).unwrap();
-            let mut v = cursor.into_inner();
-            v.append(&mut bytes);
-            Ok(v)
-        }
-        TCP | UDP | SCTP | DCCP => {
-            match s.parse::<u16>() {
-                Err(e) => Err(format!("Error parsing tcp/udp/sctp/dccp port number: {}", e)),
-                Ok(port) => {
-                    v.write_u16::<BigEndian>(port).unwrap();
-                    Ok(v)
-                }
+        #[cfg(feature = "proto-dns")]
+        DNS | DNS4 | DNS6 | DNSADDR => {
+            match std::str::from_utf8(b) {
+                Ok(name) => idn::check_hostname(name).map_err(|e| format!("{} for {}", e, proto)),
+                Err(_) => Err(format!("Domain name for {} is not valid UTF-8", proto)),
             }
         }
-        ONION => unimplemented!(),
+        #[cfg(feature = "proto-onion")]
+        ONION | ONION3 if b.ends_with(&[0, 0]) => Err(format!("Port of {} address is 0", proto)),
+        _ => Ok(()),
+    }
+}
 
-        // this function should not be called on the other protocols because they have no
-        // address to parse
-        _ => unreachable!(),
+// Peer ids are sha2-256 multihashes, or identity multihashes inlining a
+// public key of at most 42 bytes.
+pub(crate) fn check_peer_id(b: &[u8]) -> Result<(), String> {
+    if b.len() < 2 || b[1] as usize != b.len() - 2 {
+        return Err(format!("Multihash digest length doesn't match"));
+    }
+    match (b[0], b[1]) {
+        (0x12, 32) => Ok(()),
+        (0x12, n) => Err(format!("sha2-256 digest is {} bytes instead of 32", n)),
+        (0x00, n) if n <= 42 => Ok(()),
+        (0x00, n) => Err(format!("Identity multihash is {} bytes, more than 42", n)),
+        (code, _) => Err(format!("Multihash code {:#x} isn't allowed in a peer id", code)),
     }
 }
 
-fn verify_multiaddr_bytes(mut bytes: &[u8]) -> Result<(), ParseError> {
-    // while not end of input:
-    //   read varint (protocol type code)
-    //   if fixed-length, read that number of bytes
-    //   if variable length, read varint and then that number of bytes.
-    //
+fn fmt_multiaddr_bytes(mut bytes: &[u8], registry: &Registry, f: &mut fmt::Formatter)
+                       -> Result<(), fmt::Error> {
+    if f.alternate() {
+        return fmt_breakdown(bytes, registry, f);
+    }
     while bytes.len() > 0 {
-        let code = try!(bytes.read_unsigned_varint_32().map_err(|e| {
-            ParseError::InvalidCode(format!("Error reading varint: {}", e))
-        })) as u16;
-        let proto_type = try!(Protocol::from_code(code).map_err(|_| {
-            ParseError::InvalidCode(format!("Invalid protocol type code: {}", code))
-        }));
-        let addr_size = match proto_type.size() {
-            protocol::Size::Fixed(0) => continue,
-            protocol::Size::Fixed(n) => n,
-            protocol::Size::Variable => {
-                try!(bytes.read_unsigned_varint_32().map_err(|e| {
-                    ParseError::InvalidAddress(format!("Error reading varint: {}", e))
-                }))
-            }
+        // A Multiaddr's bytes were validated against some registry, which
+        // may not be this one. Anything we can't decode is written in hex.
+        let start = bytes;
+        let (proto, addr) = match read_component(&mut bytes, registry) {
+            Ok(c) => c,
+            Err(_) => return fmt_undecodable(start, f),
         };
+        if let Proto::Unknown(code) = proto {
+            try!(write!(f, "/{}/", code));
+            for b in addr {
+                try!(write!(f, "{:02x}", b));
+            }
+            continue;
+        }
+        try!(write!(f, "/{}", proto.name()));
+        if let Size::Fixed(0) = proto.size() {
+            continue;
+        }
+        try!(match proto {
+            Proto::Builtin(ref p) => write!(f, "/{}", address_bytes_to_string(addr, p)),
+            Proto::Custom(c) => write!(f, "/{}", registry::custom_bytes_to_string(addr, c)),
+            Proto::Unknown(_) => unreachable!(),
+        });
+    }
+    Ok(())
+}
 
-        if bytes.len() < addr_size as usize {
-            return Err(ParseError::InvalidAddress(format!(
-                "Unexpected end of bytes, expected {} more, found {}",
-                addr_size,
-                bytes.len()
-            )));
+// Writes bytes that can't be decoded as their protocol code, if there is one,
+// and everything after it in hex, since where the component ends is unknown.
+fn fmt_undecodable(bytes: &[u8], f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    let mut rest = bytes;
+    match read_varint(&mut rest) {
+        Ok(code) => try!(write!(f, "/{}/", code)),
+        Err(_) => {
+            rest = bytes;
+            try!(write!(f, "/"));
         }
+    }
+    for b in rest {
+        try!(write!(f, "{:02x}", b));
+    }
+    Ok(())
+}
 
-        bytes = &bytes[addr_size as usize..];
+// The alternate form, "{:#}": a line per component with its protocol, code,
+// value and the value's bytes in hex. Bytes that can't be decoded are shown in hex on a
+// last line, along with the reason.
+fn fmt_breakdown(mut bytes: &[u8], registry: &Registry, f: &mut fmt::Formatter)
+                 -> Result<(), fmt::Error> {
+    let mut first = true;
+    while bytes.len() > 0 {
+        if !first {
+            try!(writeln!(f));
+        }
+        first = false;
+
+        let start = bytes;
+        let (proto, addr) = match read_component(&mut bytes, registry) {
+            Ok(c) => c,
+            Err(e) => {
+                let hex: String = start.iter().map(|b| format!("{:02x}", b)).collect();
+                return write!(f, "{:<8} {:>7}  {:<24} {}", "invalid", "", hex, message(e));
+            }
+        };
+        let value = match proto {
+            Proto::Builtin(p) if p.requires_value() => address_bytes_to_string(addr, &p),
+            Proto::Custom(c) if proto.size() != Size::Fixed(0) => {
+                registry::custom_bytes_to_string(addr, c)
+            }
+            _ => String::new(),
+        };
+        let hex: String = addr.iter().map(|b| format!("{:02x}", b)).collect();
+        let line = format!("{:<8} {:>7}  {:<24} {}", proto.name(), proto.code(), value, hex);
+        try!(write!(f, "{}", line.trim_end()));
     }
     Ok(())
 }
 
+fn message(e: ParseError) -> String {
+    e.message().to_string()
+}
 
 #[cfg(test)]
 mod test {
-    use super::{Multiaddr, ToMultiaddr};
-    use std::net::{Ipv4Addr, Ipv6Addr};
+    use super::{BufferTooSmall, Limits, Maddr, Multiaddr, Protocol, Registry, ToMultiaddr};
+    use std::borrow::Borrow;
+    use std::convert::TryFrom;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
     use std::str::FromStr;
 
     #[test]
@@ -236,13 +1599,14 @@ mod test {
                      "/sctp",
                      "/udp/65536",
                      "/tcp/65536",
-                     // "/onion/9imaq4ygg2iegci7:80",
-                     // "/onion/aaimaq4ygg2iegci7:80",
-                     // "/onion/timaq4ygg2iegci7:0",
-                     // "/onion/timaq4ygg2iegci7:-1",
-                     // "/onion/timaq4ygg2iegci7",
-                     // "/onion/timaq4ygg2iegci@:666",
-                     //
+                     "/onion/9imaq4ygg2iegci7:80",
+                     "/onion/aaimaq4ygg2iegci7:80",
+                     "/onion/timaq4ygg2iegci7:0",
+                     "/onion/timaq4ygg2iegci7:-1",
+                     "/onion/timaq4ygg2iegci7",
+                     "/onion/timaq4ygg2iegci@:666",
+                     "/onion3/timaq4ygg2iegci7:80",
+                     "/onion3/vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd",
                      "/udp/1234/sctp",
                      "/udp/1234/udt/1234",
                      "/udp/1234/utp/1234",
@@ -265,8 +1629,6 @@ mod test {
                      "/ip4/0.0.0.0",
                      "/ip6/::1",
                      "/ip6/2601:9:4f81:9700:803e:ca65:66e8:c21",
-                     // "/onion/timaq4ygg2iegci7:1234"),
-                     // "/onion/timaq4ygg2iegci7:80/http"),
                      "/udp/0",
                      "/tcp/0",
                      "/sctp/0",
@@ -294,6 +1656,26 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(feature = "proto-onion")]
+    fn test_onion_construct() {
+        let cases = ["/onion/timaq4ygg2iegci7:1234",
+                     "/onion/timaq4ygg2iegci7:80/http",
+                     "/onion3/vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd:1234"];
+
+        for case in &cases {
+            let ma = Multiaddr::from_str(case).unwrap();
+            assert_eq!(ma.to_string(), *case);
+            assert_eq!(Multiaddr::from_bytes(ma.as_bytes().to_vec()).unwrap(), ma);
+        }
+        assert_eq!(Multiaddr::from_str("/onion/timaq4ygg2iegci7:1234").unwrap().as_bytes(),
+                   &[0xbc, 0x03, 0x9a, 0x18, 0x08, 0x73, 0x06, 0x36, 0x90, 0x43, 0x09, 0x1f,
+                     0x04, 0xd2][..]);
+        // A port of 0 can't be written
+        assert!(Multiaddr::from_bytes(vec![0xbc, 0x03, 0x9a, 0x18, 0x08, 0x73, 0x06, 0x36,
+                                           0x90, 0x43, 0x09, 0x1f, 0, 0]).is_err());
+    }
+
     #[test]
     fn test_ip4_tomultiaddr() {
         let ip = Ipv4Addr::from_str("1.2.3.4").unwrap();
@@ -314,4 +1696,566 @@ mod test {
                        Multiaddr::from_str(addr).unwrap());
         }
     }
+
+    #[test]
+    fn test_ip6zone() {
+        let ma = Multiaddr::from_str("/ip6/fe80::1%eth0/tcp/80").unwrap();
+        assert_eq!(ma.to_string(), "/ip6zone/eth0/ip6/fe80::1/tcp/80");
+        assert_eq!(ma.to_scoped_string(), "/ip6/fe80::1%eth0/tcp/80");
+        assert_eq!(Multiaddr::from_bytes(ma.as_bytes().to_vec()).unwrap(), ma);
+        assert_eq!(ma.ip(), Some(IpAddr::V6(Ipv6Addr::from_str("fe80::1").unwrap())));
+        assert_eq!(Multiaddr::from_str("/ip6zone/eth0/ip6/fe80::1/tcp/80").unwrap(), ma);
+
+        assert!(Multiaddr::from_str("/ip6/fe80::1%").is_err());
+        assert!(Multiaddr::from_str("/ip6/%eth0").is_err());
+        assert!(Multiaddr::from_str("/ip4/1.2.3.4%eth0").is_err());
+        assert!(Multiaddr::from_bytes(vec![42, 0]).is_err());
+
+        // A zone without an ip6 address after it is kept as it is
+        let ma = Multiaddr::from_str("/ip6zone/eth0/tcp/80").unwrap();
+        assert_eq!(ma.to_scoped_string(), "/ip6zone/eth0/tcp/80");
+
+        let addr = SocketAddrV6::from_str("[fe80::1%3]:4001").unwrap();
+        assert_eq!(addr.to_multiaddr().unwrap().to_scoped_string(), "/ip6/fe80::1%3/tcp/4001");
+    }
+
+    #[test]
+    fn test_large_protocol_code() {
+        // plaintextv2 = 7367777 doesn't fit in a u16
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/plaintextv2").unwrap();
+        assert_eq!(ma.as_bytes(), &[4, 1, 2, 3, 4, 0xe1, 0xd8, 0xc1, 0x03]);
+        assert_eq!(Multiaddr::from_bytes(ma.as_bytes().to_vec()).unwrap(), ma);
+        assert_eq!(ma.to_string(), "/ip4/1.2.3.4/plaintextv2");
+    }
+
+    #[test]
+    fn test_fail_from_bytes() {
+        let cases: &[&[u8]] = &[
+            // truncated ip4 address
+            &[4, 1, 2, 3],
+            // overlong encodings of the ip4 code
+            &[0x84, 0x00, 1, 2, 3, 4],
+            &[0x84, 0x80, 0x80, 0x80, 0x00, 1, 2, 3, 4],
+            // code overflowing a u32
+            &[0xff, 0xff, 0xff, 0xff, 0x7f],
+            // truncated varint
+            &[0xa5],
+            // ipfs with a length much larger than the input
+            &[0xa5, 0x03, 0xff, 0xff, 0xff, 0xff, 0x0f, 1, 2],
+        ];
+
+        for case in cases {
+            assert!(Multiaddr::validate(case).is_err());
+            assert!(Multiaddr::try_from(*case).is_err());
+            assert!(Multiaddr::from_bytes(case.to_vec()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_try_from_slice() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/1234").unwrap();
+        let buf = [ma.as_bytes(), &[0xff][..]].concat();
+
+        assert!(Multiaddr::validate(&buf[..buf.len() - 1]).is_ok());
+        assert_eq!(Multiaddr::try_from(&buf[..buf.len() - 1]).unwrap(), ma);
+        assert!(Multiaddr::validate(&buf[..]).is_err());
+    }
+
+    #[test]
+    fn test_conversions() {
+        let ma = Multiaddr::try_from("/ip4/1.2.3.4/tcp/1234").unwrap();
+        assert_eq!(Multiaddr::try_from(ma.to_string()).unwrap(), ma);
+        assert!(Multiaddr::try_from("/ip4").is_err());
+
+        let bytes: &[u8] = ma.as_ref();
+        assert_eq!(bytes, ma.as_bytes());
+        let v: Vec<u8> = ma.clone().into();
+        assert_eq!(v, ma.as_bytes());
+        assert_eq!(Multiaddr::try_from(v).unwrap(), ma);
+        assert!(Multiaddr::try_from(vec![4, 1, 2, 3]).is_err());
+
+        let long = Multiaddr::from_str("/ip4/1.2.3.4/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").unwrap();
+        let v: Vec<u8> = long.clone().into();
+        assert_eq!(v, long.as_bytes());
+    }
+
+    #[test]
+    fn test_limits() {
+        let bytes = Multiaddr::from_str("/ip4/1.2.3.4/tcp/1234").unwrap().as_bytes().to_vec();
+        let mut registry = Registry::new();
+        assert!(registry.from_bytes(bytes.clone()).is_ok());
+
+        registry.set_limits(Limits { max_len: 7, ..Limits::default() });
+        assert!(registry.from_bytes(bytes.clone()).is_err());
+
+        registry.set_limits(Limits { max_component_len: 3, ..Limits::default() });
+        assert!(registry.from_bytes(bytes.clone()).is_err());
+
+        let too_long = vec![0; Limits::default().max_len + 1];
+        assert!(Multiaddr::from_bytes(too_long).is_err());
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/1234").unwrap();
+        let borrowed = Maddr::from_bytes(ma.as_bytes()).unwrap();
+        assert_eq!(borrowed, &*ma);
+        assert_eq!(borrowed.to_string(), "/ip4/1.2.3.4/tcp/1234");
+        assert_eq!(borrowed.to_owned(), ma);
+        assert!(Maddr::from_bytes(&ma.as_bytes()[..3]).is_err());
+
+        let b: &Maddr = ma.borrow();
+        assert_eq!(b, borrowed);
+    }
+
+    #[test]
+    fn test_clone() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/1234").unwrap();
+        let clone = ma.clone();
+        assert_eq!(clone, ma);
+        assert_eq!(clone.as_bytes(), ma.as_bytes());
+    }
+
+    #[test]
+    fn test_iter() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/1234/http/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").unwrap();
+        let components: Vec<_> = ma.iter().collect();
+        assert_eq!(components.len(), 4);
+        assert_eq!(components[0], (Protocol::IP4, &[1, 2, 3, 4][..]));
+        assert_eq!(components[1], (Protocol::TCP, &[4, 210][..]));
+        assert_eq!(components[2], (Protocol::HTTP, &[][..]));
+        assert_eq!(components[3].0, Protocol::IPFS);
+        assert_eq!(components[3].1.len(), 34);
+
+        // the payloads point into the multiaddr's own bytes
+        let bytes = ma.as_bytes();
+        assert_eq!(components[0].1.as_ptr(), bytes[1..].as_ptr());
+
+        assert_eq!(Multiaddr::from_str("/").unwrap().iter().count(), 0);
+
+        let mut iter = ma.iter();
+        assert_eq!(iter.clone().count(), 4);
+        assert_eq!(iter.next_back().unwrap().0, Protocol::IPFS);
+        assert_eq!(iter.next(), Some(components[0]));
+        assert_eq!(iter.clone().count(), 2);
+        assert_eq!(iter.rev().collect::<Vec<_>>(), [components[2], components[1]]);
+    }
+
+    #[test]
+    fn test_iter_custom_protocol() {
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", ::Size::Fixed(0)).unwrap();
+        let ma = registry.parse("/tcp/80/mytag/udp/53").unwrap();
+        assert_eq!(ma.iter().count(), 1);
+        assert_eq!(ma.iter().rev().map(|(p, _)| p).collect::<Vec<_>>(), [Protocol::TCP]);
+    }
+
+    #[test]
+    fn test_protocols() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/1234/http").unwrap();
+        assert_eq!(ma.protocols(), vec![Protocol::IP4, Protocol::TCP, Protocol::HTTP]);
+        assert_eq!(ma.protocol_stack(), vec!["ip4", "tcp", "http"]);
+        assert!(Multiaddr::from_str("/").unwrap().protocols().is_empty());
+    }
+
+    #[test]
+    fn test_value_for() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/1234/http/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC/tcp/80").unwrap();
+        assert_eq!(ma.value_for(Protocol::IP4).unwrap(), "1.2.3.4");
+        assert_eq!(ma.value_for(Protocol::TCP).unwrap(), "1234");
+        assert_eq!(ma.value_for(Protocol::HTTP).unwrap(), "");
+        assert_eq!(ma.value_for(Protocol::IPFS).unwrap(),
+                   "QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC");
+        assert_eq!(ma.value_for(Protocol::UDP), None);
+        assert_eq!(ma.value_bytes_for(Protocol::TCP), Some(&[4, 210][..]));
+    }
+
+    #[test]
+    fn test_typed_accessors() {
+        let ma = Multiaddr::from_str("/ip6/::1/udp/1234/utp/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").unwrap();
+        assert_eq!(ma.ip(), Some(IpAddr::from_str("::1").unwrap()));
+        assert_eq!(ma.port(), Some(1234));
+        #[cfg(feature = "multihash")]
+        assert_eq!(ma.peer_id().unwrap().to_base58_string(),
+                   "QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC");
+
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/sctp/80").unwrap();
+        assert_eq!(ma.ip(), Some(IpAddr::from_str("1.2.3.4").unwrap()));
+        assert_eq!(ma.port(), None);
+        #[cfg(feature = "multihash")]
+        assert!(ma.peer_id().is_none());
+    }
+
+    #[test]
+    fn test_component_matching() {
+        let ma = Multiaddr::from_str("/ip4/1.6.0.80/tcp/1234/http").unwrap();
+        let yes = ["/", "/ip4/1.6.0.80", "/ip4/1.6.0.80/tcp/1234", "/ip4/1.6.0.80/tcp/1234/http"];
+        for case in &yes {
+            assert!(ma.starts_with(&Multiaddr::from_str(case).unwrap()), "{}", case);
+        }
+        assert!(!ma.starts_with(&Multiaddr::from_str("/tcp/1234").unwrap()));
+        assert!(!ma.starts_with(&Multiaddr::from_str("/ip4/1.6.0.80/tcp/1234/http/utp").unwrap()));
+
+        assert!(ma.ends_with(&Multiaddr::from_str("/tcp/1234/http").unwrap()));
+        assert!(ma.ends_with(&Multiaddr::from_str("/").unwrap()));
+        assert!(!ma.ends_with(&Multiaddr::from_str("/tcp/1234").unwrap()));
+
+        // the bytes of /tcp/80 are a suffix of those of /ip4/1.6.0.80
+        let ip = Multiaddr::from_str("/ip4/1.6.0.80").unwrap();
+        let tcp = Multiaddr::from_str("/tcp/80").unwrap();
+        assert!(ip.as_bytes().ends_with(tcp.as_bytes()));
+        assert!(!ip.ends_with(&tcp));
+        assert!(!ip.contains(&tcp));
+
+        assert!(ma.contains(&Multiaddr::from_str("/tcp/1234").unwrap()));
+        assert!(ma.contains(&Multiaddr::from_str("/ip4/1.6.0.80/tcp/1234/http").unwrap()));
+        assert!(!ma.contains(&Multiaddr::from_str("/ip4/1.6.0.80/http").unwrap()));
+    }
+
+    #[test]
+    fn test_replace() {
+        let ma = Multiaddr::from_str("/ip4/10.0.0.1/tcp/1234/ip4/10.0.0.2/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").unwrap();
+        assert_eq!(ma.replace(Protocol::IP4, "1.2.3.4").unwrap().to_string(),
+                   "/ip4/1.2.3.4/tcp/1234/ip4/10.0.0.2/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC");
+        assert_eq!(ma.replace(Protocol::TCP, "80").unwrap().value_for(Protocol::TCP).unwrap(), "80");
+
+        assert!(ma.replace(Protocol::UDP, "80").is_err());
+        assert!(ma.replace(Protocol::TCP, "65536").is_err());
+        assert!(ma.replace(Protocol::HTTP, "").is_err());
+
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", ::Size::Fixed(16)).unwrap();
+        let ma = registry.parse("/tcp/80/mytag/0102/udp/53").unwrap();
+        let replaced = ma.replace(Protocol::TCP, "81").unwrap();
+        assert_eq!(registry.display(&replaced).to_string(), "/tcp/81/mytag/0102/udp/53");
+        // only a registry that knows mytag finds the udp port after it
+        assert!(ma.replace(Protocol::UDP, "54").is_err());
+        let replaced = registry.replace(&ma, Protocol::UDP, "54").unwrap();
+        assert_eq!(registry.display(&replaced).to_string(), "/tcp/80/mytag/0102/udp/54");
+
+        // the value is checked with the registry's multihash validator
+        let ma = Multiaddr::from_str("/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").unwrap();
+        let sha1 = "5dqoE3aJcBjKUmXmAMuKzTFXqFrcU3";
+        assert!(ma.replace(Protocol::IPFS, sha1).is_err());
+        let mut registry = Registry::new();
+        registry.set_multihash_validator(::OpaqueBytes);
+        assert!(registry.replace(&ma, Protocol::IPFS, sha1).is_ok());
+    }
+
+    #[test]
+    fn test_split_join() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/1234/http/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").unwrap();
+        let parts = ma.split();
+        let strs: Vec<_> = parts.iter().map(|p| p.to_string()).collect();
+        assert_eq!(strs, ["/ip4/1.2.3.4", "/tcp/1234", "/http",
+                          "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC"]);
+        assert_eq!(Multiaddr::join(&parts), ma);
+        assert_eq!(Multiaddr::join(parts[1..3].iter().rev()).to_string(), "/http/tcp/1234");
+
+        assert!(Multiaddr::from_str("/").unwrap().split().is_empty());
+        assert_eq!(Multiaddr::join(Vec::<Multiaddr>::new()).as_bytes(), &[]);
+
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", ::Size::Fixed(16)).unwrap();
+        let ma = registry.parse("/tcp/80/mytag/0102/udp/53").unwrap();
+        let parts = ma.split();
+        let strs: Vec<_> = parts.iter().map(|p| registry.display(p).to_string()).collect();
+        assert_eq!(strs, ["/tcp/80", "/mytag/0102/udp/53"]);
+        assert_eq!(Multiaddr::join(&parts), ma);
+
+        let ma = registry.parse("/ip4/1.2.3.4/p2p-circuit/mytag/0102/udp/53").unwrap();
+        let (relay, dest) = ma.split_circuit().unwrap();
+        assert_eq!(relay.to_string(), "/ip4/1.2.3.4");
+        assert_eq!(registry.display(&dest).to_string(), "/mytag/0102/udp/53");
+    }
+
+    #[test]
+    fn test_split_circuit() {
+        let relay = "/ip4/1.2.3.4/tcp/4001/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+        let dest = "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+        let ma = Multiaddr::from_str(&format!("{}/p2p-circuit{}", relay, dest)).unwrap();
+        assert!(ma.is_relayed());
+        let (r, d) = ma.split_circuit().unwrap();
+        assert_eq!(r.to_string(), relay);
+        assert_eq!(d.to_string(), dest);
+
+        let ma = Multiaddr::from_str("/p2p-circuit").unwrap();
+        let (r, d) = ma.split_circuit().unwrap();
+        assert!(r.as_bytes().is_empty() && d.as_bytes().is_empty());
+
+        let ma = Multiaddr::from_str(relay).unwrap();
+        assert!(!ma.is_relayed());
+        assert!(ma.split_circuit().is_none());
+    }
+
+    #[test]
+    fn test_sizes() {
+        let empty = Multiaddr::empty();
+        assert!(empty.is_empty());
+        assert_eq!(empty.component_count(), 0);
+        assert_eq!(empty, Multiaddr::from_str("/").unwrap());
+
+        let ma = Multiaddr::from_str("/ip6/::1/tcp/4001/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").unwrap();
+        assert!(!ma.is_empty());
+        assert_eq!(ma.byte_len(), ma.as_bytes().len());
+        assert_eq!(ma.component_count(), 3);
+
+        let mut built = Multiaddr::with_capacity(ma.byte_len());
+        built.extend(ma.clone());
+        assert_eq!(built, ma);
+    }
+
+    #[test]
+    fn test_hash_and_ord() {
+        use std::collections::{BTreeSet, HashMap};
+
+        let a = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        let b = Multiaddr::from_str("/ip4/1.2.3.4/udp/80").unwrap();
+        let c = Multiaddr::from_str("/ip6/::1/tcp/80").unwrap();
+
+        let mut book = HashMap::new();
+        book.insert(a.clone(), "a");
+        book.insert(b.clone(), "b");
+        assert_eq!(book[&a], "a");
+        let borrowed: &Maddr = &b;
+        assert_eq!(book.get(borrowed), Some(&"b"));
+        assert!(!book.contains_key(&c));
+
+        let set: BTreeSet<_> = vec![c.clone(), b.clone(), a.clone(), a.clone()].into_iter().collect();
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), vec![a.clone(), b.clone(), c]);
+        assert!(a < b);
+    }
+
+    #[cfg(feature = "proto-dns")]
+    #[test]
+    fn test_dns() {
+        let ma = Multiaddr::from_str("/dns4/example.com/tcp/443").unwrap();
+        assert_eq!(ma.as_bytes(), &[54, 11, b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'c',
+                                    b'o', b'm', 6, 1, 187][..]);
+        assert_eq!(ma.to_string(), "/dns4/example.com/tcp/443");
+        assert_eq!(ma.value_for(Protocol::DNS4).unwrap(), "example.com");
+
+        assert!(Multiaddr::from_str("/dns6//tcp/443").is_err());
+        assert!(Multiaddr::from_bytes(vec![53, 2, 0xc3, 0x28]).is_err());
+        assert!(Multiaddr::from_bytes(vec![53, 0]).is_err());
+        assert!(Multiaddr::from_bytes(vec![53, 1, b'/']).is_err());
+    }
+
+    #[test]
+    fn test_thin_waist() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/0/http").unwrap();
+        assert_eq!(ma.thin_waist().unwrap().to_string(), "/ip4/1.2.3.4/tcp/0");
+        assert_eq!(ma.fill_port(4001).to_string(), "/ip4/1.2.3.4/tcp/4001/http");
+
+        let ma = Multiaddr::from_str("/ip6/::1/udp/0/quic").unwrap();
+        assert_eq!(ma.thin_waist().unwrap().to_string(), "/ip6/::1/udp/0");
+        assert_eq!(ma.fill_port(443).to_string(), "/ip6/::1/udp/443/quic");
+
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/utp/tcp/80").unwrap();
+        assert_eq!(ma.thin_waist().unwrap().to_string(), "/ip4/1.2.3.4");
+        assert_eq!(ma.fill_port(4001), ma);
+        assert!(Multiaddr::from_str("/tcp/80/ip4/1.2.3.4").unwrap().thin_waist().is_none());
+
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", ::Size::Fixed(16)).unwrap();
+        let ma = registry.parse("/ip4/1.2.3.4/tcp/0/mytag/0102/udp/0").unwrap();
+        let filled = ma.fill_port(4001);
+        assert_eq!(registry.display(&filled).to_string(), "/ip4/1.2.3.4/tcp/4001/mytag/0102/udp/0");
+        assert_eq!(ma.thin_waist().unwrap().to_string(), "/ip4/1.2.3.4/tcp/0");
+        let ma = registry.parse("/ip4/1.2.3.4/mytag/0102/tcp/0").unwrap();
+        assert_eq!(ma.thin_waist().unwrap().to_string(), "/ip4/1.2.3.4");
+        assert_eq!(ma.fill_port(4001), ma);
+    }
+
+    #[test]
+    fn test_legacy_star_protocols() {
+        let s = "/ip4/1.2.3.4/tcp/9090/ws/p2p-webrtc-star/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+        let ma = Multiaddr::from_str(s).unwrap();
+        assert_eq!(ma.to_string(), s);
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/443/wss/p2p-websocket-star").unwrap();
+        assert_eq!(ma.protocols(), vec![Protocol::IP4, Protocol::TCP, Protocol::WSS,
+                                        Protocol::P2P_WEBSOCKET_STAR]);
+        assert_eq!(&ma.as_bytes()[ma.as_bytes().len() - 2..], &[0xdf, 0x03]);
+    }
+
+    #[test]
+    fn test_webrtc() {
+        let s = "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC/p2p-circuit/webrtc/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+        let ma = Multiaddr::from_str(s).unwrap();
+        assert_eq!(ma.to_string(), s);
+        let (_, dest) = ma.split_circuit().unwrap();
+        assert_eq!(&dest.as_bytes()[..2], &[0x99, 0x02]);
+    }
+
+    #[test]
+    fn test_without_p2p() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/4001/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").unwrap();
+        let (transport, peer_id) = ma.without_p2p();
+        assert_eq!(transport.to_string(), "/ip4/1.2.3.4/tcp/4001");
+        assert_eq!(peer_id, ma.value_bytes_for(Protocol::IPFS));
+
+        let relayed = Multiaddr::from_str("/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC/p2p-circuit").unwrap();
+        assert_eq!(relayed.without_p2p(), (&*relayed, None));
+        let empty = Multiaddr::from_str("/").unwrap();
+        assert_eq!(empty.without_p2p(), (&*empty, None));
+    }
+
+    #[test]
+    fn test_with_ip_port() {
+        let ma = Multiaddr::from_str("/ip4/10.0.0.1/udp/4001/quic/ip4/10.0.0.2/tcp/80").unwrap();
+        let ip = IpAddr::from_str("1.2.3.4").unwrap();
+        assert_eq!(ma.with_ip(ip).unwrap().to_string(),
+                   "/ip4/1.2.3.4/udp/4001/quic/ip4/10.0.0.2/tcp/80");
+        let ip = IpAddr::from_str("2001:db8::1").unwrap();
+        assert_eq!(ma.with_ip(ip).unwrap().to_string(),
+                   "/ip6/2001:db8::1/udp/4001/quic/ip4/10.0.0.2/tcp/80");
+        assert_eq!(ma.with_port(5001).unwrap().to_string(),
+                   "/ip4/10.0.0.1/udp/5001/quic/ip4/10.0.0.2/tcp/80");
+
+        let ma = Multiaddr::from_str("/utp/ws").unwrap();
+        assert!(ma.with_ip(ip).is_none());
+        assert!(ma.with_port(80).is_none());
+
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", ::Size::Fixed(16)).unwrap();
+        let ma = registry.parse("/ip4/1.2.3.4/tcp/80/mytag/0102/udp/53").unwrap();
+        let with_port = ma.with_port(81).unwrap();
+        assert_eq!(registry.display(&with_port).to_string(),
+                   "/ip4/1.2.3.4/tcp/81/mytag/0102/udp/53");
+        let with_ip = ma.with_ip(ip).unwrap();
+        assert_eq!(registry.display(&with_ip).to_string(),
+                   "/ip6/2001:db8::1/tcp/80/mytag/0102/udp/53");
+        // the udp port after the custom protocol isn't found
+        let ma = registry.parse("/mytag/0102/udp/53").unwrap();
+        assert!(ma.with_port(81).is_none());
+    }
+
+    #[test]
+    fn test_peer_id_validation() {
+        // sha1, a short sha2-256 and an identity multihash that's too long
+        for s in &["5dqoE3aJcBjKUmXmAMuKzTFXqFrcU3",
+                   "5uapmdgoHPBsaeGS6sTGgHiMhz8EmZ",
+                   "1Eyy5ThQpnMdwLZUFGfmqkLbU7gYyZrSy7qf5EPu8bBwwvqnrQzFhxM46SAQS"] {
+            assert!(Multiaddr::from_str(&format!("/ipfs/{}", s)).is_err(), "{}", s);
+        }
+        assert!(Multiaddr::from_str("/ipfs/13hC12xCn").is_ok());
+
+        let mut sha1 = vec![0xa5, 0x03, 22, 0x11, 20];
+        sha1.extend(&[7; 20]);
+        assert!(Multiaddr::from_bytes(sha1).is_err());
+        let mut truncated = vec![0xa5, 0x03, 33, 0x12, 32];
+        truncated.extend(&[7; 31]);
+        assert!(Multiaddr::from_bytes(truncated).is_err());
+    }
+
+    #[test]
+    fn test_decapsulate_code() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC/p2p-circuit/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").unwrap();
+        assert_eq!(ma.decapsulate_code(Protocol::IPFS).to_string(),
+                   "/ip4/1.2.3.4/tcp/80/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC/p2p-circuit");
+        assert_eq!(ma.decapsulate_code(Protocol::TCP).to_string(), "/ip4/1.2.3.4");
+        assert!(ma.decapsulate_code(Protocol::IP4).is_empty());
+        assert_eq!(ma.decapsulate_code(Protocol::UDP), ma);
+    }
+
+    #[test]
+    fn test_debug() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        assert_eq!(format!("{:?}", ma), "\"/ip4/1.2.3.4/tcp/80\"");
+        assert_eq!(format!("{:?}", &*ma), "\"/ip4/1.2.3.4/tcp/80\"");
+        assert_eq!(format!("{:#?}", ma),
+                   "Multiaddr {\n    text: \"/ip4/1.2.3.4/tcp/80\",\n    bytes: \"0401020304060050\",\n}");
+
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", ::Size::Fixed(0)).unwrap();
+        let ma = registry.parse("/mytag").unwrap();
+        assert_eq!(format!("{:?}", ma), "Multiaddr { bytes: \"8180c001\" }");
+    }
+
+    #[test]
+    fn test_eq_str_and_socket_addr() {
+        let ma = Multiaddr::from_str("/ip4/127.0.0.1/tcp/80").unwrap();
+        assert!(ma == "/ip4/127.0.0.1/tcp/80");
+        assert!(ma == *"/ip4/127.0.0.1/tcp/80");
+        assert!(ma != "/ip4/127.0.0.1/tcp/81");
+        assert!(ma != "not a multiaddr");
+
+        let sa: SocketAddr = "127.0.0.1:80".parse().unwrap();
+        assert!(ma == sa);
+        assert!(ma != "127.0.0.1:81".parse::<SocketAddr>().unwrap());
+        assert!(ma != "[::1]:80".parse::<SocketAddr>().unwrap());
+        let ma6 = Multiaddr::from_str("/ip6/::1/tcp/80").unwrap();
+        assert!(ma6 == "[::1]:80".parse::<SocketAddr>().unwrap());
+        let udp = Multiaddr::from_str("/ip4/127.0.0.1/udp/80").unwrap();
+        assert!(udp != sa);
+        let longer = Multiaddr::from_str("/ip4/127.0.0.1/tcp/80/ws").unwrap();
+        assert!(longer != sa);
+
+        assert!(longer.matches_socket(&sa));
+        assert!(udp.matches_socket(&sa));
+        assert!(!longer.matches_socket(&"127.0.0.1:81".parse().unwrap()));
+        assert!(!ma6.matches_socket(&sa));
+        assert!(!Multiaddr::from_str("/ip4/127.0.0.1").unwrap().matches_socket(&sa));
+        assert!(!Multiaddr::from_str("/ip4/127.0.0.1/ws/tcp/80").unwrap().matches_socket(&sa));
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        let mut record = ma.as_bytes().to_vec();
+        record.extend(&[0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+        assert_eq!(Multiaddr::parse_prefix(&record).unwrap(), (ma.clone(), 8));
+        assert_eq!(Multiaddr::parse_prefix(ma.as_bytes()).unwrap(), (ma, 8));
+        assert_eq!(Multiaddr::parse_prefix(&[0, 1]).unwrap(), (Multiaddr::empty(), 0));
+        // a truncated component
+        assert!(Multiaddr::parse_prefix(&[4, 1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_encode_into() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        assert_eq!(ma.encoded_len(), 8);
+
+        let mut buf = [0xff; 10];
+        assert_eq!(ma.encode_into(&mut buf), Ok(8));
+        assert_eq!(&buf[..], &[4, 1, 2, 3, 4, 6, 0, 80, 0xff, 0xff]);
+        assert_eq!(ma.encode_into(&mut buf[..7]), Err(BufferTooSmall { needed: 8, available: 7 }));
+
+        let mut v = Vec::new();
+        ma.write_to(&mut v).unwrap();
+        assert_eq!(v, ma.as_bytes());
+    }
+
+    #[test]
+    fn test_display_alternate() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80/ws").unwrap();
+        assert_eq!(format!("{:#}", ma),
+                   "ip4            4  1.2.3.4                  01020304\n\
+                    tcp            6  80                       0050\n\
+                    ws           477");
+        assert_eq!(format!("{:#}", Multiaddr::empty()), "");
+
+        // a registry that doesn't know the last protocol
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", ::Size::Fixed(0)).unwrap();
+        let ma = registry.parse("/tcp/80/mytag").unwrap();
+        assert_eq!(format!("{:#}", Registry::new().display(&ma)),
+                   "tcp            6  80                       0050\n\
+                    invalid           8180c001                 Invalid protocol type code: 3145729");
+    }
+
+    #[test]
+    fn test_display() {
+        let cases = ["/ip4/1.2.3.4",
+                     "/ip6/2601:9:4f81:9700:803e:ca65:66e8:c21",
+                     "/ip6/::1",
+                     "/udp/1234/sctp/1234",
+                     "/udp/1234/utp",
+                     "/tcp/1234/https",
+                     "/ip4/127.0.0.1/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC/tcp/1234"];
+
+        for case in &cases {
+            assert_eq!(Multiaddr::from_str(case).unwrap().to_string(), *case);
+        }
+    }
 }
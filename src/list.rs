@@ -0,0 +1,168 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use {log_rejected, parse_str_into, storage_from_slice, Multiaddr, ParseError, ParseOptions,
+     ParseResult, Registry};
+
+/// The error from `Multiaddr::parse_list`, saying which entry failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListError {
+    /// Position of the entry among all the entries of the list, counting
+    /// from 0 and including blank ones
+    pub index: usize,
+    pub entry: String,
+    pub error: ParseError,
+}
+
+/// The result of `Multiaddr::parse_many`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParsedMany {
+    /// The multiaddrs that parsed, in input order
+    pub addrs: Vec<Multiaddr>,
+    /// The inputs that didn't, with their position among all the inputs
+    pub errors: Vec<ListError>,
+}
+
+impl ParsedMany {
+    fn push(&mut self, index: usize, entry: &str, result: ParseResult<Multiaddr>) {
+        match result {
+            Ok(ma) => self.addrs.push(ma),
+            Err(e) => {
+                self.errors.push(ListError {
+                    index: index,
+                    entry: entry.to_string(),
+                    error: e,
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "entry {} ({}): {}", self.index, self.entry, self.error)
+    }
+}
+
+impl Error for ListError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl Multiaddr {
+    /// Parses a list of multiaddrs separated by commas and/or newlines, as
+    /// bootstrap lists usually are. Whitespace around entries is ignored,
+    /// as are blank entries and lines starting with '#'.
+    pub fn parse_list(s: &str) -> Result<Vec<Multiaddr>, ListError> {
+        let mut addrs = Vec::new();
+        let entries = s.split(|c| c == ',' || c == '\n').map(str::trim);
+        for (i, entry) in entries.enumerate() {
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            match Multiaddr::from_str(entry) {
+                Ok(ma) => addrs.push(ma),
+                Err(e) => {
+                    return Err(ListError {
+                        index: i,
+                        entry: entry.to_string(),
+                        error: e,
+                    })
+                }
+            }
+        }
+        Ok(addrs)
+    }
+
+    /// Parses many multiaddrs, e.g. from a crawl, collecting the failures
+    /// rather than stopping at the first. Inputs are parsed as by `from_str`
+    /// and aren't trimmed.
+    ///
+    /// The binary forms are built in one reused buffer, so most multiaddrs,
+    /// being short enough to be stored inline, are parsed without
+    /// allocating.
+    pub fn parse_many<'a, I>(inputs: I) -> ParsedMany
+        where I: IntoIterator<Item = &'a str>
+    {
+        let registry = Registry::new();
+        let opts = ParseOptions::default();
+        let mut buf = Vec::new();
+        let mut parsed = ParsedMany::default();
+        for (i, s) in inputs.into_iter().enumerate() {
+            parsed.push(i, s, parse_reusing(s, &registry, &opts, &mut buf));
+        }
+        parsed
+    }
+
+    /// Like `parse_many`, but spreads the work over rayon's thread pool.
+    /// Each thread reuses its own buffer. The results are in input order.
+    #[cfg(feature = "rayon")]
+    pub fn par_parse_many(inputs: &[&str]) -> ParsedMany {
+        let opts = ParseOptions::default();
+        let results: Vec<_> = inputs.par_iter().map_init(|| (Registry::new(), Vec::new()),
+                                                        |state, s| {
+            let (ref registry, ref mut buf) = *state;
+            parse_reusing(s, registry, &opts, buf)
+        }).collect();
+
+        let mut parsed = ParsedMany::default();
+        for (i, result) in results.into_iter().enumerate() {
+            parsed.push(i, inputs[i], result);
+        }
+        parsed
+    }
+}
+
+fn parse_reusing(s: &str, registry: &Registry, opts: &ParseOptions, buf: &mut Vec<u8>)
+                 -> ParseResult<Multiaddr> {
+    match parse_str_into(s, registry, opts, buf) {
+        Ok(()) => Ok(Multiaddr { bytes: storage_from_slice(buf) }),
+        Err(e) => {
+            log_rejected("multiaddr string", s, &e);
+            Err(e)
+        }
+    }
+}
+
+
+// The examples use dns names
+#[cfg(all(test, feature = "proto-dns"))]
+mod test {
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_parse_list() {
+        let list = "# bootstrap peers\n\
+                    /ip4/1.2.3.4/tcp/4001,/ip6/::1/tcp/4001\n\
+                    \n  /dns4/example.com/tcp/443/wss  ,\r\n";
+        let addrs = Multiaddr::parse_list(list).unwrap();
+        assert_eq!(addrs.iter().map(|ma| ma.to_string()).collect::<Vec<_>>(),
+                   vec!["/ip4/1.2.3.4/tcp/4001", "/ip6/::1/tcp/4001", "/dns4/example.com/tcp/443/wss"]);
+        assert!(Multiaddr::parse_list("").unwrap().is_empty());
+
+        let err = Multiaddr::parse_list("/ip4/1.2.3.4, ,/ip4/1.2.3/tcp/1").unwrap_err();
+        assert_eq!(err.index, 2);
+        assert_eq!(err.entry, "/ip4/1.2.3/tcp/1");
+        assert!(err.to_string().starts_with("entry 2 (/ip4/1.2.3/tcp/1): Error parsing ip4 address"));
+    }
+
+    #[test]
+    fn test_parse_many() {
+        let inputs = ["/ip4/1.2.3.4/tcp/4001", "/ip4/1.2.3", "/ip6/::1/udp/53/quic", "", "/foo"];
+        let parsed = Multiaddr::parse_many(inputs.iter().cloned());
+        assert_eq!(parsed.addrs.iter().map(|ma| ma.to_string()).collect::<Vec<_>>(),
+                   vec!["/ip4/1.2.3.4/tcp/4001", "/ip6/::1/udp/53/quic", ""]);
+        assert_eq!(parsed.errors.iter().map(|e| e.index).collect::<Vec<_>>(), vec![1, 4]);
+        assert_eq!(parsed.errors[1].entry, "/foo");
+        assert_eq!(parsed.addrs[0], Multiaddr::from_str(inputs[0]).unwrap());
+
+        #[cfg(feature = "rayon")]
+        assert_eq!(Multiaddr::par_parse_many(&inputs), parsed);
+    }
+}
@@ -0,0 +1,38 @@
+/// Creates a `Multiaddr` from a string literal, which is checked at compile
+/// time.
+///
+/// Literals that can't be checked at compile time are rejected too, even
+/// though `Multiaddr::from_str` accepts them: onion, onion3, memory and
+/// ip6zone addresses, ip6 addresses with a zone like "fe80::1%eth0", and
+/// internationalized domain names. Parse those at run time instead.
+///
+/// ```
+/// #[macro_use]
+/// extern crate rust_multiaddr;
+///
+/// # fn main() {
+/// let ma = multiaddr!("/ip4/127.0.0.1/tcp/4001");
+/// assert_eq!(ma.to_string(), "/ip4/127.0.0.1/tcp/4001");
+/// # }
+/// ```
+///
+/// An invalid literal is a compile error:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate rust_multiaddr;
+///
+/// # fn main() {
+/// let ma = multiaddr!("/ip4/127.0.0.1/tcp/65536");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! multiaddr {
+    ($s:expr) => {{
+        const _: () = match $crate::const_parse::check($s) {
+            Ok(()) => (),
+            Err(e) => panic!("{}", e),
+        };
+        <$crate::Multiaddr as ::std::str::FromStr>::from_str($s).unwrap()
+    }};
+}
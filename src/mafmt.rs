@@ -0,0 +1,257 @@
+//! Patterns describing the shapes of multiaddrs that a transport can dial,
+//! like js-mafmt.
+//!
+//! ```ignore
+//! use rust_multiaddr::mafmt;
+//!
+//! let ma = "/dns4/example.com/tcp/443/wss".parse().unwrap();
+//! assert!(mafmt::secure_websockets().matches(&ma));
+//! assert!(mafmt::reliable().matches(&ma));
+//! assert!(!mafmt::quic().matches(&ma));
+//! ```
+
+use protocol::Protocol;
+use protocol::Protocol::*;
+use Maddr;
+
+/// A pattern over the protocols of a multiaddr. Values are not looked at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// A single component of the given protocol
+    Base(Protocol),
+    /// Each of the patterns in turn
+    And(Vec<Pattern>),
+    /// Any one of the patterns
+    Or(Vec<Pattern>),
+}
+
+impl Pattern {
+    /// Returns a pattern matching `self` followed by `next`.
+    pub fn and(self, next: Pattern) -> Pattern {
+        match self {
+            Pattern::And(mut v) => {
+                v.push(next);
+                Pattern::And(v)
+            }
+            p => Pattern::And(vec![p, next]),
+        }
+    }
+
+    /// Returns a pattern matching either `self` or `other`.
+    pub fn or(self, other: Pattern) -> Pattern {
+        match self {
+            Pattern::Or(mut v) => {
+                v.push(other);
+                Pattern::Or(v)
+            }
+            p => Pattern::Or(vec![p, other]),
+        }
+    }
+
+    /// Checks whether the whole multiaddr matches the pattern.
+    pub fn matches(&self, ma: &Maddr) -> bool {
+        let protocols = ma.protocols();
+        self.match_prefix(&protocols).contains(&protocols.len())
+    }
+
+    /// Checks whether the leading components of the multiaddr match the
+    /// pattern, e.g. "/ip4/1.2.3.4/tcp/80/http" partially matches `tcp()`.
+    pub fn partial_matches(&self, ma: &Maddr) -> bool {
+        !self.match_prefix(&ma.protocols()).is_empty()
+    }
+
+    // Returns the numbers of leading protocols the pattern can consume
+    fn match_prefix(&self, protocols: &[Protocol]) -> Vec<usize> {
+        match *self {
+            Pattern::Base(p) => {
+                if protocols.first() == Some(&p) { vec![1] } else { vec![] }
+            }
+            Pattern::And(ref patterns) => {
+                let mut ends = vec![0];
+                for pattern in patterns {
+                    let mut next = Vec::new();
+                    for &end in &ends {
+                        for n in pattern.match_prefix(&protocols[end..]) {
+                            if !next.contains(&(end + n)) {
+                                next.push(end + n);
+                            }
+                        }
+                    }
+                    ends = next;
+                }
+                ends
+            }
+            Pattern::Or(ref patterns) => {
+                let mut ends = Vec::new();
+                for pattern in patterns {
+                    for n in pattern.match_prefix(protocols) {
+                        if !ends.contains(&n) {
+                            ends.push(n);
+                        }
+                    }
+                }
+                ends
+            }
+        }
+    }
+}
+
+pub fn base(p: Protocol) -> Pattern {
+    Pattern::Base(p)
+}
+
+/// A pattern matching each of `patterns` in turn.
+pub fn and(patterns: Vec<Pattern>) -> Pattern {
+    Pattern::And(patterns)
+}
+
+/// A pattern matching any one of `patterns`.
+pub fn or(patterns: Vec<Pattern>) -> Pattern {
+    Pattern::Or(patterns)
+}
+
+#[cfg(feature = "proto-dns")]
+pub fn dns4() -> Pattern {
+    base(DNS4)
+}
+
+#[cfg(feature = "proto-dns")]
+pub fn dns6() -> Pattern {
+    base(DNS6)
+}
+
+#[cfg(feature = "proto-dns")]
+pub fn dnsaddr() -> Pattern {
+    base(DNSADDR)
+}
+
+#[cfg(feature = "proto-dns")]
+pub fn dns() -> Pattern {
+    or(vec![base(DNS), dnsaddr(), dns4(), dns6()])
+}
+
+/// Without the dns protocols, matches nothing.
+#[cfg(not(feature = "proto-dns"))]
+pub fn dns() -> Pattern {
+    or(vec![])
+}
+
+pub fn ip() -> Pattern {
+    or(vec![base(IP4), base(IP6)])
+}
+
+pub fn tcp() -> Pattern {
+    or(vec![ip().and(base(TCP)), dns().and(base(TCP))])
+}
+
+pub fn udp() -> Pattern {
+    ip().and(base(UDP))
+}
+
+pub fn utp() -> Pattern {
+    udp().and(base(UTP))
+}
+
+pub fn quic() -> Pattern {
+    udp().and(base(QUIC))
+}
+
+pub fn websockets() -> Pattern {
+    or(vec![tcp().and(base(WS)), dns().and(base(WS))])
+}
+
+pub fn secure_websockets() -> Pattern {
+    or(vec![tcp().and(base(WSS)), dns().and(base(WSS))])
+}
+
+pub fn http() -> Pattern {
+    or(vec![tcp().and(base(HTTP)), ip().and(base(HTTP)), dns().and(base(HTTP))])
+}
+
+pub fn https() -> Pattern {
+    or(vec![tcp().and(base(HTTPS)), ip().and(base(HTTPS)), dns().and(base(HTTPS))])
+}
+
+/// Addresses of transports that provide reliable streams.
+pub fn reliable() -> Pattern {
+    or(vec![websockets(), secure_websockets(), http(), https(), tcp(), utp(), quic(), dns()])
+}
+
+/// A peer id on its own or behind a reliable transport, e.g.
+/// "/ip4/1.2.3.4/tcp/4001/ipfs/Qm...".
+pub fn p2p() -> Pattern {
+    or(vec![reliable().and(base(IPFS)), base(IPFS)])
+}
+
+/// A relayed address, with or without the relay's own address.
+pub fn circuit() -> Pattern {
+    or(vec![p2p().and(base(P2P_CIRCUIT)).and(p2p()),
+            p2p().and(base(P2P_CIRCUIT)),
+            base(P2P_CIRCUIT).and(p2p())])
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    const PEER: &'static str = "QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+
+    fn ma(s: &str) -> Multiaddr {
+        Multiaddr::from_str(&s.replace("PEER", PEER)).unwrap()
+    }
+
+    fn check(pattern: Pattern, good: &[&str], bad: &[&str]) {
+        for s in good {
+            assert!(pattern.matches(&ma(s)), "{} should match", s);
+        }
+        for s in bad {
+            assert!(!pattern.matches(&ma(s)), "{} shouldn't match", s);
+        }
+    }
+
+    #[cfg(feature = "proto-dns")]
+    #[test]
+    fn test_transports() {
+        check(tcp(),
+              &["/ip4/1.2.3.4/tcp/80", "/ip6/::1/tcp/80", "/dns4/example.com/tcp/80"],
+              &["/ip4/1.2.3.4/udp/80", "/ip4/1.2.3.4/tcp/80/http", "/tcp/80", "/"]);
+        check(quic(),
+              &["/ip4/1.2.3.4/udp/443/quic", "/ip6/::1/udp/443/quic"],
+              &["/ip4/1.2.3.4/udp/443", "/ip4/1.2.3.4/tcp/443/quic"]);
+        check(websockets(),
+              &["/ip4/1.2.3.4/tcp/80/ws", "/dns/example.com/tcp/80/ws", "/dnsaddr/example.com/ws"],
+              &["/ip4/1.2.3.4/tcp/80/wss", "/ip4/1.2.3.4/ws"]);
+        check(http(),
+              &["/ip4/1.2.3.4/tcp/80/http", "/ip4/1.2.3.4/http", "/dns6/example.com/http"],
+              &["/ip4/1.2.3.4/tcp/80/https"]);
+    }
+
+    #[cfg(feature = "proto-dns")]
+    #[test]
+    fn test_composite() {
+        check(reliable(),
+              &["/ip4/1.2.3.4/tcp/80", "/ip4/1.2.3.4/udp/1/utp", "/dns4/example.com/tcp/443/wss",
+                "/dns4/example.com"],
+              &["/ip4/1.2.3.4/udp/80", "/ip4/1.2.3.4/udt"]);
+        check(p2p(),
+              &["/ipfs/PEER", "/ip4/1.2.3.4/tcp/4001/ipfs/PEER", "/ip4/1.2.3.4/udp/1/quic/ipfs/PEER"],
+              &["/ip4/1.2.3.4/tcp/4001", "/ip4/1.2.3.4/udp/4001/ipfs/PEER"]);
+        check(circuit(),
+              &["/ip4/1.2.3.4/tcp/4001/ipfs/PEER/p2p-circuit/ipfs/PEER",
+                "/ipfs/PEER/p2p-circuit", "/p2p-circuit/ipfs/PEER"],
+              &["/p2p-circuit", "/ip4/1.2.3.4/tcp/4001/p2p-circuit/ipfs/PEER"]);
+    }
+
+    #[test]
+    fn test_combinators() {
+        let pattern = tcp().or(udp()).and(base(HTTP));
+        check(pattern.clone(),
+              &["/ip4/1.2.3.4/tcp/80/http", "/ip4/1.2.3.4/udp/80/http"],
+              &["/ip4/1.2.3.4/tcp/80", "/ip4/1.2.3.4/http"]);
+        assert!(pattern.partial_matches(&ma("/ip4/1.2.3.4/udp/80/http/ipfs/PEER")));
+        assert!(!pattern.partial_matches(&ma("/ip4/1.2.3.4/http")));
+    }
+}
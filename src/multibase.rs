@@ -0,0 +1,221 @@
+use {base32_encode, Maddr, Multiaddr, ParseError, ParseResult};
+
+const BASE58_ALPHABET: &'static [u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE32_ALPHABET: &'static [u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// The multibase encodings a multiaddr can be written in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Base {
+    /// Lowercase hex, prefix 'f'
+    Base16,
+    /// Lowercase RFC 4648 base32 without padding, prefix 'b'
+    Base32,
+    /// Bitcoin's base58, prefix 'z'
+    Base58Btc,
+}
+
+impl Base {
+    pub fn prefix(&self) -> char {
+        match *self {
+            Base::Base16 => 'f',
+            Base::Base32 => 'b',
+            Base::Base58Btc => 'z',
+        }
+    }
+
+    pub fn from_prefix(c: char) -> Option<Base> {
+        match c {
+            'f' => Some(Base::Base16),
+            'b' => Some(Base::Base32),
+            'z' => Some(Base::Base58Btc),
+            _ => None,
+        }
+    }
+}
+
+impl Maddr {
+    /// Encodes the binary form of the multiaddr as a multibase string, which
+    /// is safer than the textual form for DNS records and URLs.
+    pub fn to_multibase(&self, base: Base) -> String {
+        let mut s = base.prefix().to_string();
+        match base {
+            Base::Base16 => {
+                for b in self.as_bytes() {
+                    s.push_str(&format!("{:02x}", b));
+                }
+            }
+            Base::Base32 => s.push_str(&base32_encode(self.as_bytes())),
+            Base::Base58Btc => s.push_str(&base58_encode(self.as_bytes())),
+        }
+        s
+    }
+}
+
+impl Multiaddr {
+    /// Decodes and validates a multiaddr written by `to_multibase`.
+    pub fn from_multibase(s: &str) -> ParseResult<Multiaddr> {
+        let base = try!(s.chars().next().and_then(Base::from_prefix).ok_or_else(|| {
+            ParseError::other(format!("Unsupported multibase prefix in {}", s))
+        }));
+        let data = &s[1..];
+        let bytes = match base {
+            Base::Base16 => base16_decode(data),
+            Base::Base32 => base32_decode(data),
+            Base::Base58Btc => base58_decode(data),
+        };
+        let bytes = try!(bytes.ok_or_else(|| {
+            ParseError::other(format!("Invalid {:?} string: {}", base, s))
+        }));
+        Multiaddr::from_bytes(bytes)
+    }
+}
+
+// The decoders append to a buffer, so that parsing can decode straight into
+// the multiaddr. On failure the buffer is left as it was.
+
+pub(crate) fn base16_decode(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len() / 2);
+    if base16_decode_into(s, &mut bytes) { Some(bytes) } else { None }
+}
+
+pub(crate) fn base16_decode_into(s: &str, bytes: &mut Vec<u8>) -> bool {
+    if s.len() % 2 != 0 {
+        return false;
+    }
+    let start = bytes.len();
+    for i in 0..s.len() / 2 {
+        match s.get(2 * i..2 * i + 2).and_then(|d| u8::from_str_radix(d, 16).ok()) {
+            Some(b) => bytes.push(b),
+            None => {
+                bytes.truncate(start);
+                return false;
+            }
+        }
+    }
+    true
+}
+
+pub(crate) fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len() * 5 / 8);
+    if base32_decode_into(s, &mut bytes) { Some(bytes) } else { None }
+}
+
+pub(crate) fn base32_decode_into(s: &str, bytes: &mut Vec<u8>) -> bool {
+    let start = bytes.len();
+    let mut buf = 0u32;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let digit = match BASE32_ALPHABET.iter().position(|&a| a == c) {
+            Some(d) => d,
+            None => {
+                bytes.truncate(start);
+                return false;
+            }
+        };
+        buf = (buf << 5) | digit as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buf >> bits) as u8);
+        }
+    }
+    // Leftover bits are padding and must be zero
+    if bits >= 5 || buf & ((1 << bits) - 1) != 0 {
+        bytes.truncate(start);
+        return false;
+    }
+    true
+}
+
+pub(crate) fn base58_encode(b: &[u8]) -> String {
+    // Big-endian digits in base 58
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in b {
+        let mut carry = byte as u32;
+        for d in digits.iter_mut().rev() {
+            carry += (*d as u32) << 8;
+            *d = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.insert(0, (carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let zeros = b.iter().take_while(|&&b| b == 0).count();
+    let mut s = String::with_capacity(zeros + digits.len());
+    for _ in 0..zeros {
+        s.push('1');
+    }
+    for d in digits {
+        s.push(BASE58_ALPHABET[d as usize] as char);
+    }
+    s
+}
+
+pub(crate) fn base58_decode(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len());
+    if base58_decode_into(s, &mut bytes) { Some(bytes) } else { None }
+}
+
+pub(crate) fn base58_decode_into(s: &str, bytes: &mut Vec<u8>) -> bool {
+    // Big-endian digits in base 256, after the leading zero bytes
+    let zeros = s.bytes().take_while(|&c| c == b'1').count();
+    let start = bytes.len();
+    bytes.extend((0..zeros).map(|_| 0));
+    let digits = bytes.len();
+    for c in s.bytes().skip(zeros) {
+        let mut carry = match BASE58_ALPHABET.iter().position(|&a| a == c) {
+            Some(d) => d as u32,
+            None => {
+                bytes.truncate(start);
+                return false;
+            }
+        };
+        for b in bytes[digits..].iter_mut().rev() {
+            carry += (*b as u32) * 58;
+            *b = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(digits, carry as u8);
+            carry >>= 8;
+        }
+    }
+    true
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{base58_decode, base58_encode, Base};
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_base58() {
+        assert_eq!(base58_encode(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(base58_encode(&[0, 0, 1]), "112");
+        assert_eq!(base58_decode("112").unwrap(), vec![0, 0, 1]);
+        assert_eq!(base58_decode("StV1DL6CwTryKyV").unwrap(), b"hello world".to_vec());
+        assert!(base58_decode("0OIl").is_none());
+    }
+
+    #[test]
+    fn test_multibase() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        assert_eq!(ma.to_multibase(Base::Base16), "f0401020304060050");
+        assert_eq!(ma.to_multibase(Base::Base32), "baqaqeayeayafa");
+        for &base in &[Base::Base16, Base::Base32, Base::Base58Btc] {
+            let s = ma.to_multibase(base);
+            assert_eq!(Multiaddr::from_multibase(&s).unwrap(), ma);
+        }
+
+        assert!(Multiaddr::from_multibase("").is_err());
+        assert!(Multiaddr::from_multibase("m").is_err());
+        assert!(Multiaddr::from_multibase("f0401020304060").is_err());
+        assert!(Multiaddr::from_multibase("baqaqeayeayafb").is_err());
+        assert!(Multiaddr::from_multibase("f2a").is_err());
+    }
+}
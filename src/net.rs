@@ -0,0 +1,224 @@
+//! Opening std sockets for multiaddrs.
+//!
+//! Only the thin waist is supported: an ip4, ip6 or (for dialing) dns
+//! component followed by tcp or udp, with nothing after it. With the
+//! `async` feature, `dial_async` and `listen_async` do the same for tokio.
+
+use byteorder::{BigEndian, ByteOrder};
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+
+use protocol::Protocol::*;
+use {read_ip4, read_ip6, write_component, Maddr, Multiaddr, Protocol};
+
+#[cfg(feature = "async")]
+pub use tokio_net::{dial_async, listen_async, local_multiaddr, peer_multiaddr, AsyncListener, Dial};
+
+/// A socket listening on a multiaddr.
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    Udp(UdpSocket),
+}
+
+impl Listener {
+    /// Returns the multiaddr the socket is bound to, with the actual port
+    /// if it was bound to port 0.
+    pub fn local_multiaddr(&self) -> io::Result<Multiaddr> {
+        match *self {
+            Listener::Tcp(ref l) => l.local_addr().map(|a| tcp_multiaddr(&a)),
+            Listener::Udp(ref s) => s.local_addr().map(|a| udp_multiaddr(&a)),
+        }
+    }
+
+    /// Accepts a connection on a tcp listener, returning the stream and the
+    /// peer's multiaddr. Fails on a udp socket.
+    pub fn accept(&self) -> io::Result<(TcpStream, Multiaddr)> {
+        match *self {
+            Listener::Tcp(ref l) => {
+                let (stream, addr) = try!(l.accept());
+                Ok((stream, tcp_multiaddr(&addr)))
+            }
+            Listener::Udp(_) => Err(invalid_input("can't accept on a udp socket".to_string())),
+        }
+    }
+}
+
+/// Binds a tcp listener for /ip4|ip6/<ip>/tcp/<port>, or a udp socket for
+/// /ip4|ip6/<ip>/udp/<port>.
+pub fn listen(ma: &Maddr) -> io::Result<Listener> {
+    let (transport, addr) = try!(socket_addr(ma));
+    match transport {
+        TCP => TcpListener::bind(addr).map(Listener::Tcp),
+        _ => UdpSocket::bind(addr).map(Listener::Udp),
+    }
+}
+
+/// Connects to /ip4|ip6|dns|dns4|dns6/<host>/tcp/<port>. Names are resolved
+/// with the operating system's resolver, and each address is tried in turn.
+pub fn dial(ma: &Maddr) -> io::Result<TcpStream> {
+    match try!(dial_target(ma)) {
+        DialTarget::Addr(addr) => TcpStream::connect(addr),
+        DialTarget::Name(proto, name, port) => {
+            let addrs: Vec<_> = try!((&*name, port).to_socket_addrs())
+                .filter(|addr| family_matches(proto, addr))
+                .collect();
+            TcpStream::connect(&addrs[..])
+        }
+    }
+}
+
+// Where a dialable multiaddr points: an address, or a name to resolve
+pub(crate) enum DialTarget {
+    Addr(SocketAddr),
+    #[cfg_attr(not(feature = "proto-dns"), allow(dead_code))]
+    Name(Protocol, String, u16),
+}
+
+pub(crate) fn dial_target(ma: &Maddr) -> io::Result<DialTarget> {
+    let components: Vec<_> = ma.iter().collect();
+    match components[..] {
+        [(IP4, _), (TCP, _)] | [(IP6, _), (TCP, _)] |
+        [(IP6ZONE, _), (IP6, _), (TCP, _)] => {
+            socket_addr(ma).map(|(_, addr)| DialTarget::Addr(addr))
+        }
+        #[cfg(feature = "proto-dns")]
+        [(proto @ DNS, host), (TCP, port)] |
+        [(proto @ DNS4, host), (TCP, port)] |
+        [(proto @ DNS6, host), (TCP, port)] => {
+            let name = String::from_utf8_lossy(host).into_owned();
+            Ok(DialTarget::Name(proto, name, BigEndian::read_u16(port)))
+        }
+        _ => Err(unsupported(ma)),
+    }
+}
+
+// Whether a resolved address has the family a dns4 or dns6 name asks for
+#[cfg_attr(not(feature = "proto-dns"), allow(unused_variables))]
+pub(crate) fn family_matches(proto: Protocol, addr: &SocketAddr) -> bool {
+    match proto {
+        #[cfg(feature = "proto-dns")]
+        DNS4 => addr.is_ipv4(),
+        #[cfg(feature = "proto-dns")]
+        DNS6 => addr.is_ipv6(),
+        _ => true,
+    }
+}
+
+/// Returns /ip4|ip6/<ip>/tcp/<port> for a tcp peer or local address.
+pub fn tcp_multiaddr(addr: &SocketAddr) -> Multiaddr {
+    socket_multiaddr(addr, TCP)
+}
+
+/// Returns /ip4|ip6/<ip>/udp/<port> for a udp peer or local address.
+pub fn udp_multiaddr(addr: &SocketAddr) -> Multiaddr {
+    socket_multiaddr(addr, UDP)
+}
+
+fn socket_multiaddr(addr: &SocketAddr, transport: Protocol) -> Multiaddr {
+    let mut bytes = Vec::new();
+    match *addr {
+        SocketAddr::V4(ref addr) => write_component(IP4, &addr.ip().octets(), &mut bytes),
+        SocketAddr::V6(ref addr) => {
+            if addr.scope_id() != 0 {
+                write_component(IP6ZONE, addr.scope_id().to_string().as_bytes(), &mut bytes);
+            }
+            write_component(IP6, &addr.ip().octets(), &mut bytes)
+        }
+    }
+    let mut port = [0; 2];
+    BigEndian::write_u16(&mut port, addr.port());
+    write_component(transport, &port, &mut bytes);
+    Multiaddr { bytes: bytes.into() }
+}
+
+// Splits /ip4|ip6/<ip>/tcp|udp/<port> into the transport and the address.
+// An ip6zone before the ip6 address must be a numeric scope id, since std
+// can't look up interfaces by name.
+fn socket_addr(ma: &Maddr) -> io::Result<(Protocol, SocketAddr)> {
+    let components: Vec<_> = ma.iter().collect();
+    let (zone, components) = match components.split_first() {
+        Some((&(IP6ZONE, zone), rest)) => (Some(zone), rest),
+        _ => (None, &components[..]),
+    };
+    let (ip, transport, port) = match (zone, components) {
+        (None, &[(IP4, ip), (t @ TCP, port)]) | (None, &[(IP4, ip), (t @ UDP, port)]) => {
+            (IpAddr::V4(read_ip4(ip)), t, port)
+        }
+        (_, &[(IP6, ip), (t @ TCP, port)]) | (_, &[(IP6, ip), (t @ UDP, port)]) => {
+            (IpAddr::V6(read_ip6(ip)), t, port)
+        }
+        _ => return Err(unsupported(ma)),
+    };
+    let mut addr = SocketAddr::new(ip, BigEndian::read_u16(port));
+    if let (Some(zone), &mut SocketAddr::V6(ref mut addr)) = (zone, &mut addr) {
+        let scope_id = try!(::std::str::from_utf8(zone).ok()
+            .and_then(|zone| zone.parse().ok())
+            .ok_or_else(|| invalid_input(format!("ip6zone of {} is not a scope id", ma))));
+        addr.set_scope_id(scope_id);
+    }
+    Ok((transport, addr))
+}
+
+pub(crate) fn unsupported(ma: &Maddr) -> io::Error {
+    invalid_input(format!("unsupported multiaddr {:?}", ma))
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{dial, listen, socket_addr, tcp_multiaddr, udp_multiaddr, Listener};
+    use protocol::Protocol::TCP;
+    use std::io::ErrorKind;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_socket_multiaddrs() {
+        let addr = "127.0.0.1:80".parse().unwrap();
+        assert_eq!(tcp_multiaddr(&addr).to_string(), "/ip4/127.0.0.1/tcp/80");
+        let addr = "[::1]:53".parse().unwrap();
+        assert_eq!(udp_multiaddr(&addr).to_string(), "/ip6/::1/udp/53");
+        let addr = "[fe80::1%2]:80".parse().unwrap();
+        let ma = tcp_multiaddr(&addr);
+        assert_eq!(ma.to_string(), "/ip6zone/2/ip6/fe80::1/tcp/80");
+        assert_eq!(socket_addr(&ma).unwrap(), (TCP, addr));
+    }
+
+    #[test]
+    fn test_listen_and_dial() {
+        let listener = listen(&Multiaddr::from_str("/ip4/127.0.0.1/tcp/0").unwrap()).unwrap();
+        let local = listener.local_multiaddr().unwrap();
+        assert!(local.value_for(::Protocol::TCP).unwrap() != "0");
+
+        let stream = dial(&local).unwrap();
+        let (_, peer) = listener.accept().unwrap();
+        assert_eq!(peer, tcp_multiaddr(&stream.local_addr().unwrap()));
+
+        let udp = listen(&Multiaddr::from_str("/ip4/127.0.0.1/udp/0").unwrap()).unwrap();
+        match udp {
+            Listener::Udp(_) => {}
+            _ => panic!("expected a udp socket"),
+        }
+        assert_eq!(udp.accept().unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        for s in &["/ip4/127.0.0.1", "/ip4/127.0.0.1/tcp/80/ws", "/udp/80"] {
+            let ma = Multiaddr::from_str(s).unwrap();
+            assert_eq!(listen(&ma).unwrap_err().kind(), ErrorKind::InvalidInput);
+        }
+        #[cfg(feature = "proto-dns")]
+        {
+            let ma = Multiaddr::from_str("/dns4/localhost/tcp/80").unwrap();
+            assert_eq!(listen(&ma).unwrap_err().kind(), ErrorKind::InvalidInput);
+        }
+        let ma = Multiaddr::from_str("/ip4/127.0.0.1/udp/80").unwrap();
+        assert_eq!(dial(&ma).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
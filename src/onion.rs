@@ -0,0 +1,256 @@
+//! Typed onion service addresses, enabled by the `proto-onion` feature.
+//!
+//! In the textual form both versions are written as the base32 address
+//! without ".onion", then a colon and the port, as in
+//! "/onion3/vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd:80".
+
+use std::fmt;
+
+use protocol::Protocol::*;
+use {base32_encode, write_component, Multiaddr, ParseResult, ToMultiaddr};
+
+/// A version 2 onion service and port, for the onion protocol. Tor no
+/// longer supports these; prefer `Onion3Addr`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct OnionAddr {
+    hash: [u8; 10],
+    port: u16,
+}
+
+impl OnionAddr {
+    /// `hash` is the first 10 bytes of the SHA-1 digest of the service's
+    /// public key, the part of the address before ".onion".
+    pub fn new(hash: [u8; 10], port: u16) -> OnionAddr {
+        OnionAddr {
+            hash: hash,
+            port: port,
+        }
+    }
+
+    pub fn hash(&self) -> &[u8; 10] {
+        &self.hash
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+/// Returns /onion/<address>:<port>, or an error if the port is 0.
+impl ToMultiaddr for OnionAddr {
+    fn to_multiaddr(&self) -> ParseResult<Multiaddr> {
+        onion_multiaddr(ONION, &self.hash, self.port)
+    }
+}
+
+/// Displays the host name, "<address>.onion".
+impl fmt::Display for OnionAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}.onion", base32_encode(&self.hash))
+    }
+}
+
+/// A version 3 onion service and port, for the onion3 protocol.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Onion3Addr {
+    public_key: [u8; 32],
+    port: u16,
+}
+
+impl Onion3Addr {
+    /// `public_key` is the service's ed25519 public key, from which the
+    /// address and its checksum are derived.
+    pub fn new(public_key: [u8; 32], port: u16) -> Onion3Addr {
+        Onion3Addr {
+            public_key: public_key,
+            port: port,
+        }
+    }
+
+    /// Reads the 35 bytes of a decoded onion3 address: the public key, a
+    /// checksum and the version. Returns None if the checksum or version
+    /// is wrong.
+    pub fn from_address_bytes(b: &[u8; 35], port: u16) -> Option<Onion3Addr> {
+        let mut public_key = [0; 32];
+        public_key.copy_from_slice(&b[..32]);
+        let addr = Onion3Addr::new(public_key, port);
+        if addr.address_bytes()[..] == b[..] { Some(addr) } else { None }
+    }
+
+    pub fn public_key(&self) -> &[u8; 32] {
+        &self.public_key
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns the 35 bytes the address is the base32 encoding of: the
+    /// public key, a two byte checksum and the version, 3.
+    pub fn address_bytes(&self) -> [u8; 35] {
+        let mut input = Vec::with_capacity(48);
+        input.extend(b".onion checksum");
+        input.extend(&self.public_key);
+        input.push(3);
+        let checksum = sha3_256(&input);
+
+        let mut b = [0; 35];
+        b[..32].copy_from_slice(&self.public_key);
+        b[32..34].copy_from_slice(&checksum[..2]);
+        b[34] = 3;
+        b
+    }
+}
+
+/// Returns /onion3/<address>:<port>, or an error if the port is 0.
+impl ToMultiaddr for Onion3Addr {
+    fn to_multiaddr(&self) -> ParseResult<Multiaddr> {
+        onion_multiaddr(ONION3, &self.address_bytes(), self.port)
+    }
+}
+
+/// Displays the host name, "<address>.onion".
+impl fmt::Display for Onion3Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}.onion", base32_encode(&self.address_bytes()))
+    }
+}
+
+fn onion_multiaddr(proto: ::Protocol, hash: &[u8], port: u16) -> ParseResult<Multiaddr> {
+    let mut addr = hash.to_vec();
+    addr.push((port >> 8) as u8);
+    addr.push(port as u8);
+    let mut bytes = Vec::new();
+    write_component(proto, &addr, &mut bytes);
+    Multiaddr::from_bytes(bytes)
+}
+
+// SHA3-256 (FIPS 202), only used for onion3 checksums
+fn sha3_256(data: &[u8]) -> [u8; 32] {
+    const RATE: usize = 136;
+    let mut padded = data.to_vec();
+    padded.push(0x06);
+    while padded.len() % RATE != 0 {
+        padded.push(0);
+    }
+    let last = padded.len() - 1;
+    padded[last] |= 0x80;
+
+    let mut state = [0u64; 25];
+    for block in padded.chunks(RATE) {
+        for (i, lane) in block.chunks(8).enumerate() {
+            let mut b = [0; 8];
+            b.copy_from_slice(lane);
+            state[i] ^= u64::from_le_bytes(b);
+        }
+        keccak_f(&mut state);
+    }
+
+    let mut digest = [0; 32];
+    for (i, lane) in state[..4].iter().enumerate() {
+        digest[8 * i..8 * i + 8].copy_from_slice(&lane.to_le_bytes());
+    }
+    digest
+}
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+// The rotation of each lane and the lane it moves to in the rho and pi
+// steps, following the path that starts at lane 1
+const ROTATIONS: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+const LANES: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+fn keccak_f(a: &mut [u64; 25]) {
+    for rc in ROUND_CONSTANTS.iter() {
+        // theta
+        let mut c = [0u64; 5];
+        for x in 0..5 {
+            c[x] = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[5 * y + x] ^= d;
+            }
+        }
+        // rho and pi
+        let mut last = a[1];
+        for (&rotation, &lane) in ROTATIONS.iter().zip(LANES.iter()) {
+            let next = a[lane];
+            a[lane] = last.rotate_left(rotation);
+            last = next;
+        }
+        // chi
+        for y in 0..5 {
+            let mut row = [0u64; 5];
+            row.copy_from_slice(&a[5 * y..5 * y + 5]);
+            for x in 0..5 {
+                a[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+        // iota
+        a[0] ^= *rc;
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{sha3_256, Onion3Addr, OnionAddr};
+    use multibase::base32_decode;
+    use std::str::FromStr;
+    use {AddrComponent, Multiaddr, ToMultiaddr};
+
+    fn hex(b: &[u8]) -> String {
+        b.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_sha3_256() {
+        assert_eq!(hex(&sha3_256(b"")),
+                   "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a");
+        assert_eq!(hex(&sha3_256(b"abc")),
+                   "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532");
+        // Longer than a block
+        assert_eq!(hex(&sha3_256(&[b'a'; 200])),
+                   "cce34485baf2bf2aca99b94833892a4f52896d3d153f7b840cc4f9fe695f1387");
+    }
+
+    #[test]
+    fn test_onion3() {
+        let host = "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd";
+        let decoded = base32_decode(host).unwrap();
+        let mut b = [0; 35];
+        b.copy_from_slice(&decoded);
+        let addr = Onion3Addr::from_address_bytes(&b, 80).unwrap();
+        assert_eq!(addr.to_string(), format!("{}.onion", host));
+
+        let ma = addr.to_multiaddr().unwrap();
+        assert_eq!(ma.to_string(), format!("/onion3/{}:80", host));
+        assert_eq!(Multiaddr::from_str(&ma.to_string()).unwrap(), ma);
+        assert_eq!(ma.first(), Some(AddrComponent::ONION3(b, 80)));
+
+        // A flipped bit breaks the checksum
+        b[0] ^= 1;
+        assert!(Onion3Addr::from_address_bytes(&b, 80).is_none());
+        assert!(Onion3Addr::new(*addr.public_key(), 0).to_multiaddr().is_err());
+    }
+
+    #[test]
+    fn test_onion() {
+        let addr = OnionAddr::new([0x9a, 0x18, 0x08, 0x73, 0x06, 0x36, 0x90, 0x43, 0x09, 0x1f], 1234);
+        assert_eq!(addr.to_string(), "timaq4ygg2iegci7.onion");
+        assert_eq!(addr.to_multiaddr().unwrap().to_string(), "/onion/timaq4ygg2iegci7:1234");
+    }
+}
@@ -0,0 +1,128 @@
+use {Multiaddr, ParseResult, Registry};
+
+/// How forgiving parsing of the textual form is. `Multiaddr::from_str`
+/// uses the default options, which accept trailing slashes, port 0 and
+/// protocol names in any case, as in "/IP4/1.2.3.4/Tcp/80", but reject
+/// empty segments. Names are always displayed in lowercase.
+///
+/// ```ignore
+/// let opts = ParseOptions::strict().allow_zero_port(true);
+/// assert!(opts.parse("/ip4/0.0.0.0/tcp/0").is_ok());
+/// assert!(opts.parse("/ip4/0.0.0.0/tcp/0/").is_err());
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    trailing_slash: bool,
+    empty_segments: bool,
+    zero_port: bool,
+    uppercase_names: bool,
+}
+
+impl ParseOptions {
+    /// Options accepting only the exact canonical syntax.
+    pub fn strict() -> ParseOptions {
+        ParseOptions {
+            trailing_slash: false,
+            empty_segments: false,
+            zero_port: false,
+            uppercase_names: false,
+        }
+    }
+
+    /// Options accepting everything they can make sense of.
+    pub fn lenient() -> ParseOptions {
+        ParseOptions {
+            trailing_slash: true,
+            empty_segments: true,
+            zero_port: true,
+            uppercase_names: true,
+        }
+    }
+
+    /// Whether to accept "/ip4/1.2.3.4/" for "/ip4/1.2.3.4".
+    pub fn allow_trailing_slash(mut self, allow: bool) -> ParseOptions {
+        self.trailing_slash = allow;
+        self
+    }
+
+    /// Whether to skip empty segments, as in "/ip4/1.2.3.4//tcp/80".
+    pub fn allow_empty_segments(mut self, allow: bool) -> ParseOptions {
+        self.empty_segments = allow;
+        self
+    }
+
+    /// Whether to accept port 0 in tcp, udp, sctp and dccp components.
+    pub fn allow_zero_port(mut self, allow: bool) -> ParseOptions {
+        self.zero_port = allow;
+        self
+    }
+
+    /// Whether to accept protocol names in any case, as in "/IP4/1.2.3.4".
+    pub fn allow_uppercase_names(mut self, allow: bool) -> ParseOptions {
+        self.uppercase_names = allow;
+        self
+    }
+
+    pub fn trailing_slash(&self) -> bool {
+        self.trailing_slash
+    }
+
+    pub fn empty_segments(&self) -> bool {
+        self.empty_segments
+    }
+
+    pub fn zero_port(&self) -> bool {
+        self.zero_port
+    }
+
+    pub fn uppercase_names(&self) -> bool {
+        self.uppercase_names
+    }
+
+    /// Parses `s` against the built-in protocols. Use
+    /// `Registry::parse_with` for custom ones.
+    pub fn parse(&self, s: &str) -> ParseResult<Multiaddr> {
+        Registry::new().parse_with(s, self)
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            trailing_slash: true,
+            empty_segments: false,
+            zero_port: true,
+            uppercase_names: true,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::ParseOptions;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    #[test]
+    fn test_parse_options() {
+        let cases = ["/ip4/1.2.3.4/tcp/80/", "/ip4/1.2.3.4//tcp/80", "/ip4/1.2.3.4/tcp/0",
+                     "/IP4/1.2.3.4/Tcp/80"];
+        let strict = [false, false, false, false];
+        let default = [true, false, true, true];
+        let lenient = [true, true, true, true];
+        for (i, case) in cases.iter().enumerate() {
+            assert_eq!(ParseOptions::strict().parse(case).is_ok(), strict[i], "{}", case);
+            assert_eq!(ParseOptions::default().parse(case).is_ok(), default[i], "{}", case);
+            assert_eq!(Multiaddr::from_str(case).is_ok(), default[i], "{}", case);
+            assert_eq!(ParseOptions::lenient().parse(case).is_ok(), lenient[i], "{}", case);
+        }
+
+        assert_eq!(ParseOptions::lenient().parse("//IP4/1.2.3.4//TCP/80//").unwrap(),
+                   Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap());
+        assert_eq!(Multiaddr::from_str("/IP4/1.2.3.4/Tcp/80/P2P-Circuit").unwrap().to_string(),
+                   "/ip4/1.2.3.4/tcp/80/p2p-circuit");
+        assert!(ParseOptions::strict().parse("/").is_ok());
+        assert!(ParseOptions::strict().allow_zero_port(true).parse("/udp/0").is_ok());
+    }
+}
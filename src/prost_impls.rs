@@ -0,0 +1,96 @@
+//! Conversions for multiaddrs carried in protobuf `bytes` fields, as in
+//! libp2p's peer records, enabled by the `prost` feature.
+//!
+//! prost generates `Vec<u8>` for `bytes` fields, or `Bytes` when configured
+//! with `Config::bytes`; both convert into validated multiaddrs with
+//! `TryFrom`, and repeated fields with `Multiaddr::decode_repeated`.
+
+use prost::bytes::Bytes;
+use std::convert::TryFrom;
+
+use validate::Validator;
+use {ListError, Multiaddr, ParseError, Registry};
+
+impl TryFrom<Bytes> for Multiaddr {
+    type Error = ParseError;
+    fn try_from(b: Bytes) -> Result<Self, Self::Error> {
+        Multiaddr::from_bytes(b.to_vec())
+    }
+}
+
+impl From<Multiaddr> for Bytes {
+    fn from(ma: Multiaddr) -> Bytes {
+        Bytes::from(Vec::from(ma))
+    }
+}
+
+impl Multiaddr {
+    /// Decodes the multiaddrs of a repeated `bytes` field, failing on the
+    /// first one that isn't valid.
+    pub fn decode_repeated<B: AsRef<[u8]>>(fields: &[B]) -> Result<Vec<Multiaddr>, ListError> {
+        let accept_all = |_: &::Maddr| Ok(());
+        Multiaddr::decode_repeated_with(fields, &Registry::new(), &accept_all)
+    }
+
+    /// Like `decode_repeated`, but decodes with `registry` and also rejects
+    /// multiaddrs that `validator` rejects, e.g. to keep only the public
+    /// addresses of a peer record.
+    pub fn decode_repeated_with<B, V>(fields: &[B], registry: &Registry, validator: &V)
+                                      -> Result<Vec<Multiaddr>, ListError>
+        where B: AsRef<[u8]>,
+              V: Validator
+    {
+        fields.iter().enumerate().map(|(i, field)| {
+            let field = field.as_ref();
+            registry.from_bytes(field.to_vec())
+                .and_then(|ma| {
+                    match validator.check(&ma) {
+                        Ok(()) => Ok(ma),
+                        Err(violation) => Err(ParseError::other(violation.reason)),
+                    }
+                })
+                .map_err(|e| {
+                    ListError {
+                        index: i,
+                        entry: field.iter().map(|b| format!("{:02x}", b)).collect(),
+                        error: e,
+                    }
+                })
+        }).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use prost::bytes::Bytes;
+    use std::convert::TryFrom;
+    use std::str::FromStr;
+    use validate::PublicOnly;
+    use {Multiaddr, Registry};
+
+    #[test]
+    fn test_bytes_conversions() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        let b = Bytes::from(ma.clone());
+        assert_eq!(&b[..], ma.as_bytes());
+        assert_eq!(Multiaddr::try_from(b).unwrap(), ma);
+        assert!(Multiaddr::try_from(Bytes::from(vec![4, 1, 2])).is_err());
+    }
+
+    #[test]
+    fn test_decode_repeated() {
+        let public = Multiaddr::from_str("/ip4/8.8.8.8/tcp/4001").unwrap();
+        let private = Multiaddr::from_str("/ip4/10.0.0.1/tcp/4001").unwrap();
+        let fields = vec![Vec::from(public.clone()), Vec::from(private.clone())];
+        assert_eq!(Multiaddr::decode_repeated(&fields).unwrap(), vec![public, private]);
+
+        let e = Multiaddr::decode_repeated_with(&fields, &Registry::new(), &PublicOnly)
+            .unwrap_err();
+        assert_eq!(e.index, 1);
+        assert_eq!(e.entry, "040a000001060fa1");
+
+        let e = Multiaddr::decode_repeated(&[vec![4, 1, 2]]).unwrap_err();
+        assert_eq!((e.index, &e.entry[..]), (0, "040102"));
+    }
+}
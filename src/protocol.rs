@@ -4,112 +4,208 @@ use std::str::FromStr;
 
 use self::Protocol::*;
 
-#[derive(Copy, Clone)]
-pub enum Protocol {
-    IP4 = 4,
-    TCP = 6,
-    UDP = 17,
-    DCCP = 33,
-    IP6 = 41,
-    SCTP = 132,
-    UTP = 301,
-    UDT = 302,
-    IPFS = 421,
-    HTTP = 480,
-    HTTPS = 443,
-    ONION = 444,
+/// The size of a protocol's address. Fixed sizes are in bits, as in the
+/// spec's protocol table; an address that isn't a whole number of bytes is
+/// padded with zero bits to the next byte.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Size {
+    Fixed(u32),
+    /// A varint length in bytes followed by the address.
+    Variable,
 }
 
-impl From<Protocol> for u16 {
-    fn from(p: Protocol) -> u16 {
-        p as u16
+impl Size {
+    /// Returns the fixed size in bits, or None for variable sizes.
+    pub fn bits(&self) -> Option<u32> {
+        match *self {
+            Size::Fixed(bits) => Some(bits),
+            Size::Variable => None,
+        }
     }
-}
 
-impl FromStr for Protocol {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "ip4"   => Ok(IP4),
-            "tcp"   => Ok(TCP),
-            "udp"   => Ok(UDP),
-            "dccp"  => Ok(DCCP),
-            "ip6"   => Ok(IP6),
-            "sctp"  => Ok(SCTP),
-            "utp"   => Ok(UTP),
-            "udt"   => Ok(UDT),
-            "ipfs"  => Ok(IPFS),
-            "http"  => Ok(HTTP),
-            "https" => Ok(HTTPS),
-            "onion" => Ok(ONION),
-            _ => Err(()),
+    /// Returns the number of bytes a fixed-size address takes up, including
+    /// padding, or None for variable sizes.
+    pub fn bytes(&self) -> Option<u32> {
+        self.bits().map(|bits| bits / 8 + (bits % 8 != 0) as u32)
+    }
+
+    // The mask of the padding bits in the last byte of a fixed-size address
+    pub(crate) fn padding_mask(&self) -> u8 {
+        match *self {
+            Size::Fixed(bits) if bits % 8 != 0 => 0xff >> (bits % 8),
+            _ => 0,
         }
     }
 }
 
-// Size of address in bits
-pub enum Size {
-    Fixed(u32),
-    Variable,
+// Every protocol is declared exactly once, as a line of protocols.csv; the
+// enum and all of the name/code/size lookups below are generated from it.
+macro_rules! protocols {
+    ($($var:ident = $code:expr, $name:expr, $size:expr;)*) => {
+        /// A protocol of the multiaddr table. Features such as proto-dns add
+        /// protocols, so matches on it need a wildcard arm.
+        #[allow(non_camel_case_types)]
+        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+        #[repr(u32)]
+        #[non_exhaustive]
+        pub enum Protocol {
+            $($var = $code,)*
+        }
+
+        // Names and sizes of all protocols, for lookups in const fns
+        pub const PROTOCOLS: &'static [(&'static str, Size)] = &[$(($name, $size),)*];
+
+        impl FromStr for Protocol {
+            type Err = ();
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($name => Ok($var),)*
+                    _ => Err(()),
+                }
+            }
+        }
+
+        impl Protocol {
+            /// Every protocol, in the order of the protocol table.
+            pub const ALL: &'static [Protocol] = &[$($var,)*];
+
+            pub fn from_code(c: u32) -> Result<Protocol, ()> {
+                match c {
+                    $($code => Ok($var),)*
+                    _ => Err(()),
+                }
+            }
+
+            pub fn to_str(&self) -> &'static str {
+                match *self {
+                    $($var => $name,)*
+                }
+            }
+
+            pub fn size(&self) -> Size {
+                match *self {
+                    $($var => $size,)*
+                }
+            }
+        }
+    }
 }
 
+// Generated by build.rs from protocols.csv
+include!(concat!(env!("OUT_DIR"), "/protocols.rs"));
+
 impl Protocol {
-    // bad duplication. not sure how to fix
-    pub fn from_code(c: u16) -> Result<Protocol, ()> {
-        match c {
-            4   => Ok(IP4),
-            6   => Ok(TCP),
-            17  => Ok(UDP),
-            33  => Ok(DCCP),
-            41  => Ok(IP6),
-            132 => Ok(SCTP),
-            301 => Ok(UTP),
-            302 => Ok(UDT),
-            421 => Ok(IPFS),
-            480 => Ok(HTTP),
-            443 => Ok(HTTPS),
-            444 => Ok(ONION),
-            _ => Err(()),
+    /// Returns the protocol's code, the same as `u32::from`.
+    pub fn code(&self) -> u32 {
+        *self as u32
+    }
+
+    /// Returns the protocol's canonical name, the same as `to_str`. Aliases
+    /// such as p2p for ipfs are accepted when parsing but never returned.
+    pub fn name(&self) -> &'static str {
+        self.to_str()
+    }
+
+    /// Returns the size of the protocol's address in bits, or None if it is
+    /// variable.
+    pub fn size_bits(&self) -> Option<u32> {
+        self.size().bits()
+    }
+
+    /// Returns the number of bytes the protocol's address takes up, or None
+    /// if it is variable.
+    pub fn size_bytes(&self) -> Option<u32> {
+        self.size().bytes()
+    }
+
+    /// Checks whether the protocol is followed by an address in a multiaddr,
+    /// as in /tcp/80, unlike /ws.
+    pub fn requires_value(&self) -> bool {
+        self.size() != Size::Fixed(0)
+    }
+
+    /// Checks whether the protocol carries the connection: tcp, udp, dccp,
+    /// sctp, udt, utp, quic, webtransport, ws, wss, webrtc or memory.
+    pub fn is_transport(&self) -> bool {
+        match *self {
+            TCP | UDP | DCCP | SCTP | UDT | UTP | QUIC | WEBTRANSPORT | WS | WSS | WEBRTC |
+            MEMORY => true,
+            _ => false,
         }
     }
 
-    pub fn to_str(&self) -> &'static str {
+    /// Checks whether the protocol encrypts or authenticates the
+    /// connection: tls, wss, https and plaintextv2, which negotiates the
+    /// (absence of) security.
+    pub fn is_security(&self) -> bool {
         match *self {
-            IP4 => "ip4",
-            TCP => "tcp",
-            UDP => "udp",
-            DCCP => "dccp",
-            IP6 => "ip6",
-            SCTP => "sctp",
-            UTP => "utp",
-            UDT => "udt",
-            IPFS => "ipfs",
-            HTTP => "http",
-            HTTPS => "https",
-            ONION => "onion",
+            TLS | WSS | HTTPS | PLAINTEXTV2 => true,
+            _ => false,
         }
     }
 
-    pub fn size(&self) -> Size {
+    /// Checks whether implementations are moving away from the protocol:
+    /// quic, the draft-29 version replaced by quic-v1, and the
+    /// p2p-webrtc-star and p2p-websocket-star signalling protocols of older
+    /// js-libp2p versions. They are still parsed.
+    pub fn is_deprecated(&self) -> bool {
         match *self {
-            IP4 => Size::Fixed(4),
-            TCP => Size::Fixed(2),
-            UDP => Size::Fixed(2),
-            DCCP => Size::Fixed(2),
-            IP6 => Size::Fixed(16),
-            SCTP => Size::Fixed(2),
-            UTP => Size::Fixed(0),
-            UDT => Size::Fixed(0),
-            IPFS => Size::Variable,
-            HTTP => Size::Fixed(0),
-            HTTPS => Size::Fixed(0),
-            ONION => Size::Fixed(10),
+            QUIC | P2P_WEBRTC_STAR | P2P_WEBSOCKET_STAR => true,
+            _ => false,
         }
     }
 }
 
+impl From<Protocol> for u32 {
+    fn from(p: Protocol) -> u32 {
+        p as u32
+    }
+}
+
+/// Displays the name, e.g. "tcp", or with the alternate flag, "{:#}", the
+/// name followed by the code, "tcp(6)". Width and alignment are applied to
+/// the whole, for tables.
 impl fmt::Display for Protocol {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "{}", self.to_str())
+        if f.alternate() {
+            f.pad(&format!("{}({})", self.to_str(), self.code()))
+        } else {
+            f.pad(self.to_str())
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Protocol, Size, PROTOCOLS};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_metadata() {
+        assert_eq!(Protocol::ALL.len(), PROTOCOLS.len());
+        for &p in Protocol::ALL {
+            assert_eq!(Protocol::from_code(p.code()), Ok(p));
+            assert_eq!(Protocol::from_str(p.name()), Ok(p));
+            assert_eq!(p.requires_value(), p.size() != Size::Fixed(0));
+        }
+        assert!(Protocol::TCP.requires_value() && !Protocol::WS.requires_value());
+        assert_eq!((Protocol::IP4.size_bits(), Protocol::IP4.size_bytes()), (Some(32), Some(4)));
+        assert_eq!((Protocol::IP6.size_bits(), Protocol::IP6.size_bytes()), (Some(128), Some(16)));
+        assert_eq!(Protocol::IPFS.size_bytes(), None);
+        assert_eq!(Size::Fixed(12).bytes(), Some(2));
+        assert_eq!(Size::Fixed(12).padding_mask(), 0x0f);
+        assert!(Protocol::QUIC.is_transport() && !Protocol::IP4.is_transport());
+        assert!(Protocol::WSS.is_security() && !Protocol::WS.is_security());
+        assert!(Protocol::P2P_WEBRTC_STAR.is_deprecated() && !Protocol::WS.is_deprecated());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Protocol::TCP.to_string(), "tcp");
+        assert_eq!(format!("{:#}", Protocol::TCP), "tcp(6)");
+        assert_eq!(format!("{:#}", Protocol::P2P_CIRCUIT), "p2p-circuit(290)");
+        assert_eq!(format!("[{:<6}]", Protocol::IP4), "[ip4   ]");
+        assert_eq!(format!("[{:>#8}]", Protocol::IP4), "[  ip4(4)]");
     }
 }
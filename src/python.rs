@@ -0,0 +1,119 @@
+//! Python bindings, enabled by the `python` feature.
+//!
+//! Build with `maturin build --features python`. The extension module is
+//! named `rust_multiaddr`:
+//!
+//! ```python
+//! from rust_multiaddr import Multiaddr
+//!
+//! ma = Multiaddr("/ip4/127.0.0.1/tcp/4001")
+//! str(ma)            # "/ip4/127.0.0.1/tcp/4001"
+//! bytes(ma)          # b"\x04\x7f\x00\x00\x01\x06\x0f\xa1"
+//! ma.components()    # [("ip4", 4, "127.0.0.1"), ("tcp", 6, "4001")]
+//! ma.encapsulate(Multiaddr("/ws"))
+//! ```
+//!
+//! Invalid multiaddrs raise `ValueError`.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use {address_bytes_to_string, Multiaddr, ParseError, Size};
+
+fn to_py_error(e: ParseError) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+#[pyclass(name = "Multiaddr", frozen)]
+pub struct PyMultiaddr {
+    inner: Multiaddr,
+}
+
+#[pymethods]
+impl PyMultiaddr {
+    /// Parses the textual form of a multiaddr.
+    #[new]
+    fn new(s: &str) -> PyResult<PyMultiaddr> {
+        Multiaddr::from_str(s).map(|ma| PyMultiaddr { inner: ma }).map_err(to_py_error)
+    }
+
+    /// Validates and wraps the binary form of a multiaddr.
+    #[staticmethod]
+    fn from_bytes(b: &[u8]) -> PyResult<PyMultiaddr> {
+        Multiaddr::from_bytes(b.to_vec()).map(|ma| PyMultiaddr { inner: ma }).map_err(to_py_error)
+    }
+
+    /// Returns the components of the multiaddr, in order, as (name, code,
+    /// value) tuples. The value is None for protocols without an address.
+    fn components(&self) -> Vec<(&'static str, u32, Option<String>)> {
+        self.inner
+            .iter()
+            .map(|(p, addr)| {
+                let value = match p.size() {
+                    Size::Fixed(0) => None,
+                    _ => Some(address_bytes_to_string(addr, &p)),
+                };
+                (p.to_str(), u32::from(p), value)
+            })
+            .collect()
+    }
+
+    /// Returns the names of the protocols of the multiaddr, in order.
+    fn protocols(&self) -> Vec<&'static str> {
+        self.inner.protocol_stack()
+    }
+
+    /// Returns the multiaddr with `other` appended to it.
+    fn encapsulate(&self, other: &PyMultiaddr) -> PyMultiaddr {
+        PyMultiaddr { inner: Multiaddr::join(&[&self.inner, &other.inner]) }
+    }
+
+    fn __str__(&self) -> String {
+        self.inner.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Multiaddr('{}')", self.inner)
+    }
+
+    fn __bytes__<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, self.inner.as_bytes())
+    }
+
+    fn __eq__(&self, other: &PyMultiaddr) -> bool {
+        self.inner == other.inner
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.inner.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Parses the textual form of a multiaddr, the same as `Multiaddr(s)`.
+#[pyfunction]
+fn parse(s: &str) -> PyResult<PyMultiaddr> {
+    PyMultiaddr::new(s)
+}
+
+/// Checks whether `s` is a valid multiaddr, in textual or binary form.
+#[pyfunction]
+fn is_valid(s: &PyAny) -> bool {
+    if let Ok(b) = s.downcast::<PyBytes>() {
+        return Multiaddr::validate(b.as_bytes()).is_ok();
+    }
+    s.extract::<&str>().map_or(false, |s| Multiaddr::from_str(s).is_ok())
+}
+
+#[pymodule]
+fn rust_multiaddr(_py: Python, m: &PyModule) -> PyResult<()> {
+    try!(m.add_class::<PyMultiaddr>());
+    try!(m.add_function(try!(wrap_pyfunction!(parse, m))));
+    try!(m.add_function(try!(wrap_pyfunction!(is_valid, m))));
+    Ok(())
+}
@@ -0,0 +1,197 @@
+//! Random but realistic multiaddrs, for load testing DHTs and benchmarking
+//! peer stores, enabled by the `rand` feature.
+//!
+//! Unlike the `arbitrary` and `proptest` generators, which cover every
+//! protocol to find bugs, these look like the addresses of a real
+//! network: public ip4, ip6 or dns hosts with a tcp, udp or quic port and
+//! often a peer id.
+//!
+//! ```ignore
+//! let gen = RandomMultiaddrs::new().hosts(1, 1, 0).peer_ids(1.0);
+//! let addrs: Vec<Multiaddr> = rand::thread_rng().sample_iter(&gen).take(10_000).collect();
+//! ```
+
+use byteorder::{BigEndian, WriteBytesExt};
+use rand::distributions::Distribution;
+use rand::Rng;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use protocol::Protocol::*;
+use {write_component, write_protocol, Multiaddr};
+
+/// A generator of random multiaddrs, with weights for the kinds of host
+/// and transport. It is a `rand` `Distribution`, so it can also be used
+/// with `Rng::sample` and `Rng::sample_iter`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RandomMultiaddrs {
+    ip4: u32,
+    ip6: u32,
+    dns: u32,
+    tcp: u32,
+    udp: u32,
+    quic: u32,
+    peer_ids: f64,
+}
+
+impl RandomMultiaddrs {
+    /// A mix resembling a public libp2p network: mostly ip4, tcp and quic,
+    /// with a peer id on half of the addresses.
+    pub fn new() -> RandomMultiaddrs {
+        RandomMultiaddrs {
+            ip4: 6,
+            ip6: 3,
+            dns: 1,
+            tcp: 5,
+            udp: 1,
+            quic: 4,
+            peer_ids: 0.5,
+        }
+    }
+
+    /// Sets the weights of ip4, ip6 and dns4 hosts. dns4 hosts need the
+    /// proto-dns feature, and are generated as ip4 without it.
+    ///
+    /// Panics if all of the weights are 0.
+    pub fn hosts(mut self, ip4: u32, ip6: u32, dns: u32) -> RandomMultiaddrs {
+        assert!(ip4 + ip6 + dns > 0, "all host weights are 0");
+        self.ip4 = ip4;
+        self.ip6 = ip6;
+        self.dns = dns;
+        self
+    }
+
+    /// Sets the weights of /tcp, /udp and /udp/<port>/quic transports.
+    ///
+    /// Panics if all of the weights are 0.
+    pub fn transports(mut self, tcp: u32, udp: u32, quic: u32) -> RandomMultiaddrs {
+        assert!(tcp + udp + quic > 0, "all transport weights are 0");
+        self.tcp = tcp;
+        self.udp = udp;
+        self.quic = quic;
+        self
+    }
+
+    /// Sets the probability, from 0 to 1, of ending an address with an
+    /// /ipfs peer id.
+    pub fn peer_ids(mut self, probability: f64) -> RandomMultiaddrs {
+        self.peer_ids = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Generates a multiaddr.
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Multiaddr {
+        let mut bytes = Vec::new();
+        match pick(rng, &[self.ip4, self.ip6, self.dns]) {
+            1 => write_component(IP6, &public_ip6(rng).octets(), &mut bytes),
+            #[cfg(feature = "proto-dns")]
+            2 => write_component(DNS4, dns_name(rng).as_bytes(), &mut bytes),
+            _ => write_component(IP4, &public_ip4(rng).octets(), &mut bytes),
+        }
+
+        // Most nodes listen on the default port
+        let port = if rng.gen_bool(0.5) { 4001 } else { rng.gen_range(1024..65535) };
+        let transport = pick(rng, &[self.tcp, self.udp, self.quic]);
+        write_protocol(if transport == 0 { TCP } else { UDP }, &mut bytes);
+        bytes.write_u16::<BigEndian>(port).unwrap();
+        if transport == 2 {
+            write_protocol(QUIC, &mut bytes);
+        }
+
+        if rng.gen_bool(self.peer_ids) {
+            // A sha2-256 multihash
+            let mut peer_id = vec![0x12, 32];
+            peer_id.extend((0..32).map(|_| rng.gen::<u8>()));
+            write_component(IPFS, &peer_id, &mut bytes);
+        }
+        Multiaddr { bytes: bytes.into() }
+    }
+}
+
+impl Default for RandomMultiaddrs {
+    fn default() -> RandomMultiaddrs {
+        RandomMultiaddrs::new()
+    }
+}
+
+impl Distribution<Multiaddr> for RandomMultiaddrs {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Multiaddr {
+        self.generate(rng)
+    }
+}
+
+// Returns the index of a weight, picked in proportion to the weights
+fn pick<R: Rng + ?Sized>(rng: &mut R, weights: &[u32]) -> usize {
+    let mut n = rng.gen_range(0..weights.iter().sum::<u32>());
+    for (i, &w) in weights.iter().enumerate() {
+        if n < w {
+            return i;
+        }
+        n -= w;
+    }
+    unreachable!()
+}
+
+fn public_ip4<R: Rng + ?Sized>(rng: &mut R) -> Ipv4Addr {
+    loop {
+        let ip = Ipv4Addr::from(rng.gen::<u32>());
+        if is_public(IP4, &ip.octets()) {
+            return ip;
+        }
+    }
+}
+
+// In 2000::/3, the global unicast addresses
+fn public_ip6<R: Rng + ?Sized>(rng: &mut R) -> Ipv6Addr {
+    loop {
+        let mut octets: [u8; 16] = rng.gen();
+        octets[0] = 0x20 | (octets[0] & 0x1f);
+        if is_public(IP6, &octets) {
+            return Ipv6Addr::from(octets);
+        }
+    }
+}
+
+fn is_public(proto: ::Protocol, addr: &[u8]) -> bool {
+    let mut bytes = Vec::new();
+    write_component(proto, addr, &mut bytes);
+    Multiaddr { bytes: bytes.into() }.is_public()
+}
+
+#[cfg(feature = "proto-dns")]
+fn dns_name<R: Rng + ?Sized>(rng: &mut R) -> String {
+    const TLDS: &[&str] = &["com", "net", "org", "io"];
+    let label: String = (0..rng.gen_range(4..12))
+        .map(|_| (b'a' + rng.gen_range(0..26)) as char)
+        .collect();
+    format!("node-{}.{}.{}", rng.gen_range(0..1000), label, TLDS[rng.gen_range(0..TLDS.len())])
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::RandomMultiaddrs;
+    use protocol::Protocol::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use Multiaddr;
+
+    #[test]
+    fn test_random_multiaddrs() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let gen = RandomMultiaddrs::new();
+        let addrs: Vec<Multiaddr> = (0..500).map(|_| gen.generate(&mut rng)).collect();
+        for ma in &addrs {
+            assert!(ma.is_public(), "{} is not public", ma);
+            assert!(ma.port().is_some());
+            assert_eq!(Multiaddr::from_bytes(ma.as_bytes().to_vec()).as_ref(), Ok(ma));
+        }
+        assert!(addrs.iter().any(|ma| ma.protocols().contains(&QUIC)));
+        assert!(addrs.iter().any(|ma| ma.protocols().contains(&IPFS)));
+        assert!(addrs.iter().any(|ma| !ma.protocols().contains(&IPFS)));
+
+        let gen = RandomMultiaddrs::new().hosts(0, 1, 0).transports(1, 0, 0).peer_ids(0.0);
+        for ma in (&mut rng).sample_iter(&gen).take(100) {
+            assert_eq!(ma.protocols(), vec![IP6, TCP]);
+        }
+    }
+}
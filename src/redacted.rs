@@ -0,0 +1,90 @@
+use std::fmt;
+
+use protocol::Protocol::*;
+use {address_bytes_to_string, read_ip4, read_ip6, Maddr, Size};
+
+/// Displays a multiaddr with the parts that identify a user masked, as
+/// returned by `Maddr::redacted`.
+pub struct Redacted<'a> {
+    ma: &'a Maddr,
+}
+
+impl Maddr {
+    /// Returns an adapter that displays the multiaddr for logging without
+    /// leaking network information, e.g. "/ip4/1.2.x.x/tcp/4001/ipfs/Qmcgp…".
+    ///
+    /// Only the first two octets of ip4 addresses and the first two groups
+    /// of ip6 addresses are kept, and peer ids and onion addresses are cut
+    /// to their first five characters. Domain names, ports and the other
+    /// protocols are shown as they are. Protocols that aren't built in are
+    /// replaced by a single "/…".
+    pub fn redacted<'a>(&'a self) -> Redacted<'a> {
+        Redacted { ma: self }
+    }
+}
+
+impl<'a> fmt::Display for Redacted<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let mut components = self.ma.iter();
+        loop {
+            // Iteration stops early at protocols that aren't built in
+            let rest = components.remaining();
+            let (p, addr) = match components.next() {
+                Some(c) => c,
+                None if rest.is_empty() => return Ok(()),
+                None => return write!(f, "/…"),
+            };
+            try!(write!(f, "/{}", p));
+            match p {
+                IP4 => {
+                    let o = read_ip4(addr).octets();
+                    try!(write!(f, "/{}.{}.x.x", o[0], o[1]));
+                }
+                IP6 => {
+                    let s = read_ip6(addr).segments();
+                    try!(write!(f, "/{:x}:{:x}:x:x:x:x:x:x", s[0], s[1]));
+                }
+                IPFS => try!(write!(f, "/{}", truncate(&address_bytes_to_string(addr, &p)))),
+                #[cfg(feature = "proto-onion")]
+                ONION | ONION3 => try!(write!(f, "/{}", truncate(&address_bytes_to_string(addr, &p)))),
+                _ if p.size() == Size::Fixed(0) => {}
+                _ => try!(write!(f, "/{}", address_bytes_to_string(addr, &p))),
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Redacted<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "\"{}\"", self)
+    }
+}
+
+fn truncate(s: &str) -> String {
+    s.chars().take(5).chain(Some('…')).collect()
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use {Multiaddr, Registry, Size};
+
+    fn redacted(s: &str) -> String {
+        Multiaddr::from_str(s).unwrap().redacted().to_string()
+    }
+
+    #[test]
+    fn test_redacted() {
+        assert_eq!(redacted("/ip4/1.2.3.4/tcp/4001/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC"),
+                   "/ip4/1.2.x.x/tcp/4001/ipfs/Qmcgp…");
+        assert_eq!(redacted("/ip6/2604:1380::1/udp/53/quic"), "/ip6/2604:1380:x:x:x:x:x:x/udp/53/quic");
+        assert_eq!(redacted("/ip4/10.0.0.1/ws"), "/ip4/10.0.x.x/ws");
+
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", Size::Fixed(0)).unwrap();
+        let ma = registry.parse("/ip4/1.2.3.4/mytag").unwrap();
+        assert_eq!(ma.redacted().to_string(), "/ip4/1.2.x.x/…");
+        assert_eq!(format!("{:?}", ma.redacted()), "\"/ip4/1.2.x.x/…\"");
+    }
+}
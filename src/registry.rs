@@ -0,0 +1,520 @@
+use std::fmt;
+use std::str::FromStr;
+use varint::VarintWrite;
+
+use protocol::{Protocol, Size};
+use {AddrComponent, Limits, Maddr, Multiaddr, ParseError, ParseOptions, ParseResult};
+
+/// A set of protocols that multiaddrs can be parsed, validated and displayed
+/// against.
+///
+/// The built-in protocols are always registered. Additional protocols, such
+/// as application-private ones using codes from the private-use range, can be
+/// added with `register`. In the textual form, values of fixed-size custom
+/// protocols are written in hex and values of variable-size ones as UTF-8
+/// strings.
+pub struct Registry {
+    custom: Vec<CustomProtocol>,
+    // Other names protocols are parsed from, with the code they stand for,
+    // on top of BUILTIN_ALIASES
+    aliases: Vec<(String, u32)>,
+    limits: Limits,
+    allow_unknown: bool,
+    multihashes: Box<dyn MultihashValidator + Send + Sync>,
+}
+
+/// Decides which multihashes are accepted as the values of ipfs components.
+pub trait MultihashValidator {
+    fn validate(&self, multihash: &[u8]) -> Result<(), String>;
+}
+
+/// Accepts multihashes that are valid peer ids: sha2-256 multihashes and
+/// identity multihashes of at most 42 bytes. This is the default.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PeerIdRules;
+
+impl MultihashValidator for PeerIdRules {
+    fn validate(&self, multihash: &[u8]) -> Result<(), String> {
+        ::check_peer_id(multihash)
+    }
+}
+
+/// Accepts any bytes, for applications that only pass addresses along.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OpaqueBytes;
+
+impl MultihashValidator for OpaqueBytes {
+    fn validate(&self, _: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// The spec's current name for ipfs, which is displayed as ipfs. Kept out of
+// Registry::aliases so that creating a registry doesn't allocate.
+const BUILTIN_ALIASES: &'static [(&'static str, Protocol)] = &[("p2p", Protocol::IPFS)];
+
+pub struct CustomProtocol {
+    code: u32,
+    name: String,
+    size: Size,
+}
+
+// A protocol found in a registry
+#[derive(Copy, Clone)]
+pub(crate) enum Proto<'a> {
+    Builtin(Protocol),
+    Custom(&'a CustomProtocol),
+    // A code that isn't registered, assumed to have a length prefix
+    Unknown(u32),
+}
+
+impl<'a> Proto<'a> {
+    pub fn code(&self) -> u32 {
+        match *self {
+            Proto::Builtin(p) => u32::from(p),
+            Proto::Custom(c) => c.code,
+            Proto::Unknown(code) => code,
+        }
+    }
+
+    pub fn name(&self) -> &'a str {
+        match *self {
+            Proto::Builtin(p) => p.to_str(),
+            Proto::Custom(c) => &c.name,
+            Proto::Unknown(_) => "unknown",
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        match *self {
+            Proto::Builtin(p) => p.size(),
+            Proto::Custom(c) => c.size,
+            Proto::Unknown(_) => Size::Variable,
+        }
+    }
+}
+
+impl Registry {
+    /// Creates a registry containing only the built-in protocols, with the
+    /// default limits.
+    pub fn new() -> Registry {
+        Registry {
+            custom: Vec::new(),
+            aliases: Vec::new(),
+            limits: Limits::default(),
+            allow_unknown: false,
+            multihashes: Box::new(PeerIdRules),
+        }
+    }
+
+    /// Sets the limits enforced by `from_bytes`.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Sets whether unknown protocol codes are accepted, e.g. in addresses
+    /// from peers running newer versions. Disallowed by default.
+    ///
+    /// Unknown components are assumed to have a varint length prefix, as
+    /// variable-size protocols do, since there is no other way to tell
+    /// where they end. In the textual form they are written as the code
+    /// followed by the value in hex, e.g. "/4242/0a0b".
+    ///
+    /// The bytes of an unknown protocol with a fixed size have no prefix,
+    /// so they can't be decoded this way: the start of the value is taken
+    /// for a length, and the rest of the multiaddr is rejected or, worse,
+    /// decoded as different components. Protocols whose size is known
+    /// should be added with `register` instead.
+    pub fn set_allow_unknown(&mut self, allow: bool) {
+        self.allow_unknown = allow;
+    }
+
+    pub fn allows_unknown(&self) -> bool {
+        self.allow_unknown
+    }
+
+    /// Sets what `parse` and `from_bytes` accept in ipfs components.
+    pub fn set_multihash_validator<V>(&mut self, validator: V)
+        where V: MultihashValidator + Send + Sync + 'static
+    {
+        self.multihashes = Box::new(validator);
+    }
+
+    /// Registers a protocol. Fails if the code or the name is already taken.
+    ///
+    /// Fixed sizes are in bits, as in the spec's protocol table. Values of
+    /// sizes that aren't a multiple of 8 are padded with zero bits.
+    pub fn register(&mut self, code: u32, name: &str, size: Size) -> ParseResult<()> {
+        if self.lookup_known_code(code).is_some() {
+            return Err(ParseError::invalid_code(format!(
+                "Protocol code {} is already registered", code)));
+        }
+        // Numbers are how unknown protocols are written
+        if name.is_empty() || name.contains('/') || name.parse::<u32>().is_ok() {
+            return Err(ParseError::other(format!("Invalid protocol name: {}", name)));
+        }
+        if self.lookup_name(name).is_some() {
+            return Err(ParseError::other(format!(
+                "Protocol name {} is already registered", name)));
+        }
+
+        self.custom.push(CustomProtocol {
+            code: code,
+            name: name.to_string(),
+            size: size,
+        });
+        Ok(())
+    }
+
+    /// Registers `alias` as another name for the protocol called `name`,
+    /// built-in or custom, e.g. for a protocol that has been renamed.
+    /// Aliases are accepted when parsing, but the protocol is still
+    /// displayed under its own name. "p2p" is registered as an alias of
+    /// ipfs by default.
+    pub fn register_alias(&mut self, alias: &str, name: &str) -> ParseResult<()> {
+        let code = match self.lookup_name(name) {
+            Some(Proto::Builtin(p)) => u32::from(p),
+            Some(Proto::Custom(c)) => c.code,
+            _ => return Err(ParseError::invalid_code(format!("Invalid protocol: {}", name))),
+        };
+        if alias.is_empty() || alias.contains('/') || alias.parse::<u32>().is_ok() {
+            return Err(ParseError::other(format!("Invalid protocol name: {}", alias)));
+        }
+        if self.lookup_name(alias).is_some() {
+            return Err(ParseError::other(format!(
+                "Protocol name {} is already registered", alias)));
+        }
+
+        self.aliases.push((alias.to_string(), code));
+        Ok(())
+    }
+
+    /// Parses the textual form of a multiaddr.
+    pub fn parse(&self, s: &str) -> ParseResult<Multiaddr> {
+        self.parse_with(s, &ParseOptions::default())
+    }
+
+    /// Parses the textual form of a multiaddr, as strictly as `opts` say.
+    pub fn parse_with(&self, s: &str, opts: &ParseOptions) -> ParseResult<Multiaddr> {
+        match ::parse_str_to_bytes(s, self, opts) {
+            Ok(bytes) => Ok(Multiaddr { bytes: bytes.into() }),
+            Err(e) => {
+                ::log_rejected("multiaddr string", s, &e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Validates the binary form of a multiaddr.
+    pub fn from_bytes(&self, b: Vec<u8>) -> ParseResult<Multiaddr> {
+        try!(::verify_multiaddr_bytes(&b[..], self));
+        Ok(Multiaddr { bytes: b.into() })
+    }
+
+    /// Like `Maddr::replace`, but looks past the protocols registered here,
+    /// and checks `value` as this registry parses it.
+    pub fn replace(&self, ma: &Maddr, proto: Protocol, value: &str) -> ParseResult<Multiaddr> {
+        if let Size::Fixed(0) = proto.size() {
+            return Err(ParseError::invalid_address(format!("Protocol {} has no address", proto)));
+        }
+
+        let mut bytes = Vec::with_capacity(ma.as_bytes().len());
+        let mut replaced = false;
+        let mut rest = ma.as_bytes();
+        while !rest.is_empty() {
+            let start = rest;
+            match ::read_component(&mut rest, self) {
+                Ok((Proto::Builtin(p), _)) if p == proto && !replaced => {
+                    ::write_protocol(p, &mut bytes);
+                    try!(::write_checked_address(value, p, self, &mut bytes));
+                    replaced = true;
+                }
+                Ok(_) => bytes.extend(&start[..start.len() - rest.len()]),
+                // Not decodable with this registry, so copied through
+                Err(_) => {
+                    bytes.extend(start);
+                    break;
+                }
+            }
+        }
+
+        if !replaced {
+            return Err(ParseError::invalid_code(format!("Protocol {} not found in {}",
+                                                        proto,
+                                                        self.display(ma))));
+        }
+        Ok(Multiaddr { bytes: bytes.into() })
+    }
+
+    /// Returns a value that displays `ma` using the protocols in this
+    /// registry.
+    pub fn display<'a>(&'a self, ma: &'a Maddr) -> Display<'a> {
+        Display {
+            registry: self,
+            ma: ma,
+        }
+    }
+
+    // Checks the value of a built-in protocol beyond its length
+    pub(crate) fn check_address(&self, proto: Protocol, addr: &[u8]) -> Result<(), String> {
+        match proto {
+            Protocol::IPFS => self.multihashes.validate(addr),
+            _ => ::check_address_bytes(addr, proto),
+        }
+    }
+
+    pub(crate) fn lookup_name<'a>(&'a self, name: &str) -> Option<Proto<'a>> {
+        if let Ok(p) = Protocol::from_str(name) {
+            return Some(Proto::Builtin(p));
+        }
+        if let Some(c) = self.custom.iter().find(|c| c.name == name) {
+            return Some(Proto::Custom(c));
+        }
+        if let Some(&(_, p)) = BUILTIN_ALIASES.iter().find(|&&(alias, _)| alias == name) {
+            return Some(Proto::Builtin(p));
+        }
+        if let Some(&(_, code)) = self.aliases.iter().find(|&&(ref alias, _)| alias == name) {
+            return self.lookup_known_code(code);
+        }
+        match name.parse::<u32>() {
+            Ok(code) if self.allow_unknown && self.lookup_known_code(code).is_none() => {
+                Some(Proto::Unknown(code))
+            }
+            _ => None,
+        }
+    }
+
+    // Looks up a protocol name in the textual form, in any case if `opts`
+    // allow it
+    pub(crate) fn lookup_name_with<'a>(&'a self, name: &str, opts: &ParseOptions)
+                                       -> Option<Proto<'a>> {
+        match self.lookup_name(name) {
+            None if opts.uppercase_names() => {
+                self.lookup_name(&name.to_ascii_lowercase()).or_else(|| {
+                    let c = self.custom.iter().find(|c| c.name.eq_ignore_ascii_case(name));
+                    c.map(Proto::Custom)
+                })
+            }
+            p => p,
+        }
+    }
+
+    pub(crate) fn lookup_code<'a>(&'a self, code: u32) -> Option<Proto<'a>> {
+        match self.lookup_known_code(code) {
+            None if self.allow_unknown => Some(Proto::Unknown(code)),
+            p => p,
+        }
+    }
+
+    fn lookup_known_code<'a>(&'a self, code: u32) -> Option<Proto<'a>> {
+        if let Ok(p) = Protocol::from_code(code) {
+            return Some(Proto::Builtin(p));
+        }
+        self.custom.iter().find(|c| c.code == code).map(Proto::Custom)
+    }
+
+    /// Iterates over the decoded components of `ma`, including unknown
+    /// ones if they are allowed. Stops at custom protocols.
+    pub fn components<'a>(&'a self, ma: &'a Maddr) -> RegistryComponents<'a> {
+        RegistryComponents {
+            registry: self,
+            bytes: ma.as_bytes(),
+        }
+    }
+}
+
+/// An iterator over the components of a multiaddr, decoded with a
+/// `Registry`.
+pub struct RegistryComponents<'a> {
+    registry: &'a Registry,
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for RegistryComponents<'a> {
+    type Item = AddrComponent;
+    fn next(&mut self) -> Option<AddrComponent> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        match ::read_component(&mut self.bytes, self.registry) {
+            Ok((Proto::Builtin(p), addr)) => Some(AddrComponent::from_parts(p, addr)),
+            Ok((Proto::Unknown(code), addr)) => {
+                Some(AddrComponent::Unknown {
+                    code: code,
+                    bytes: addr.to_vec(),
+                })
+            }
+            _ => {
+                self.bytes = &[];
+                None
+            }
+        }
+    }
+}
+
+/// Displays a multiaddr using the protocols in a `Registry`.
+pub struct Display<'a> {
+    registry: &'a Registry,
+    ma: &'a Maddr,
+}
+
+impl<'a> fmt::Display for Display<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        ::fmt_multiaddr_bytes(self.ma.as_bytes(), self.registry, f)
+    }
+}
+
+pub(crate) fn write_custom_address(s: &str, proto: &CustomProtocol, v: &mut Vec<u8>)
+                                   -> Result<(), String> {
+    match proto.size {
+        Size::Variable => {
+            v.write_unsigned_varint_32(s.len() as u32).unwrap();
+            v.extend(s.as_bytes());
+            Ok(())
+        }
+        Size::Fixed(bits) => {
+            let n = bits / 8 + (bits % 8 != 0) as u32;
+            if s.len() != 2 * n as usize {
+                return Err(format!("Expected {} hex digits for {}, found {}",
+                                   2 * n, proto.name, s.len()));
+            }
+            for i in 0..n as usize {
+                let byte = try!(s.get(2 * i..2 * i + 2)
+                                 .and_then(|d| u8::from_str_radix(d, 16).ok())
+                                 .ok_or_else(|| format!("Invalid hex value for {}: {}", proto.name, s)));
+                v.push(byte);
+            }
+            if n > 0 && v[v.len() - 1] & proto.size.padding_mask() != 0 {
+                return Err(format!("Padding bits of the value for {} are not zero: {}",
+                                   proto.name, s));
+            }
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn custom_bytes_to_string(b: &[u8], proto: &CustomProtocol) -> String {
+    match proto.size {
+        Size::Variable => String::from_utf8_lossy(b).into_owned(),
+        Size::Fixed(_) => b.iter().map(|b| format!("{:02x}", b)).collect(),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{OpaqueBytes, Registry};
+    use AddrComponent;
+    use protocol::Size;
+    use {Multiaddr, ParseResult};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_custom_protocols() {
+        let mut registry = Registry::new();
+        registry.register(0x300001, "mytag", Size::Fixed(0)).unwrap();
+        registry.register(0x300002, "myid", Size::Fixed(16)).unwrap();
+        registry.register(0x300003, "myname", Size::Variable).unwrap();
+
+        let s = "/ip4/1.2.3.4/mytag/myid/0a0b/myname/foo";
+        let ma = registry.parse(s).unwrap();
+        assert_eq!(registry.display(&ma).to_string(), s);
+        assert!(registry.from_bytes(ma.as_bytes().to_vec()).is_ok());
+        // Without the registry, the custom protocols are written in hex
+        assert_eq!(ma.to_string(), "/ip4/1.2.3.4/3145729/8280c0010a0b8380c00103666f6f");
+        assert_eq!(registry.parse("/mytag").unwrap().to_string(), "/3145729/");
+
+        assert!(Multiaddr::from_str(s).is_err());
+        assert!(Multiaddr::from_bytes(ma.as_bytes().to_vec()).is_err());
+        assert!(registry.parse("/myid/0a").is_err());
+    }
+
+    #[test]
+    fn test_huge_fixed_size() {
+        let mut registry = Registry::new();
+        registry.register(0x300001, "big", Size::Fixed(u32::max_value())).unwrap();
+        assert!(registry.parse("/big/00").is_err());
+        assert!(registry.from_bytes(vec![0x81, 0x80, 0xc0, 0x01, 0]).is_err());
+        assert_eq!(Size::Fixed(u32::max_value()).bytes(), Some(1 << 29));
+    }
+
+    #[test]
+    fn test_unaligned_size() {
+        let mut registry = Registry::new();
+        registry.register(0x300001, "twelve", Size::Fixed(12)).unwrap();
+
+        let ma = registry.parse("/twelve/abc0").unwrap();
+        assert_eq!(&ma.as_bytes()[4..], &[0xab, 0xc0]);
+        assert_eq!(registry.display(&ma).to_string(), "/twelve/abc0");
+        assert!(registry.parse("/twelve/abc1").is_err());
+
+        let mut bytes = ma.as_bytes().to_vec();
+        bytes[5] = 0xc1;
+        assert!(registry.from_bytes(bytes).is_err());
+    }
+
+    #[test]
+    fn test_multihash_validator() {
+        // a sha1 multihash, which isn't a valid peer id
+        let s = "/ipfs/5dqoE3aJcBjKUmXmAMuKzTFXqFrcU3";
+        let mut registry = Registry::new();
+        assert!(registry.parse(s).is_err());
+
+        registry.set_multihash_validator(OpaqueBytes);
+        let ma = registry.parse(s).unwrap();
+        assert_eq!(ma.to_string(), s);
+        assert!(registry.from_bytes(ma.as_bytes().to_vec()).is_ok());
+        assert!(Multiaddr::from_bytes(ma.as_bytes().to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_unknown_codes() {
+        // /ip4/1.2.3.4 followed by code 4242 with a two-byte value
+        let bytes = vec![4, 1, 2, 3, 4, 0x92, 0x21, 2, 10, 11, 6, 0, 80];
+        let mut registry = Registry::new();
+        assert!(registry.from_bytes(bytes.clone()).is_err());
+        assert!(registry.parse("/4242/0a0b").is_err());
+
+        registry.set_allow_unknown(true);
+        let ma = registry.from_bytes(bytes.clone()).unwrap();
+        assert_eq!(registry.display(&ma).to_string(), "/ip4/1.2.3.4/4242/0a0b/tcp/80");
+        assert_eq!(registry.parse("/ip4/1.2.3.4/4242/0a0b/tcp/80").unwrap(), ma);
+        assert!(registry.parse("/6/0050").is_err());
+
+        let components: Vec<_> = registry.components(&ma).collect();
+        assert_eq!(components[1], AddrComponent::Unknown { code: 4242, bytes: vec![10, 11] });
+        assert_eq!(components.into_iter().collect::<ParseResult<Multiaddr>>().unwrap(), ma);
+        assert!(registry.register(4242, "4242", Size::Variable).is_err());
+    }
+
+    #[test]
+    fn test_register_conflicts() {
+        let mut registry = Registry::new();
+        assert!(registry.register(6, "foo", Size::Fixed(0)).is_err());
+        assert!(registry.register(0x300001, "tcp", Size::Fixed(0)).is_err());
+        registry.register(0x300001, "foo", Size::Fixed(0)).unwrap();
+        assert!(registry.register(0x300001, "bar", Size::Fixed(0)).is_err());
+        assert!(registry.register(0x300002, "foo", Size::Fixed(0)).is_err());
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut registry = Registry::new();
+        registry.register(0x300001, "foo", Size::Fixed(0)).unwrap();
+        registry.register_alias("foo-old", "foo").unwrap();
+        registry.register_alias("utp2", "utp").unwrap();
+        let ma = registry.parse("/FOO-OLD/utp2/Foo").unwrap();
+        assert_eq!(registry.display(&ma).to_string(), "/foo/utp/foo");
+
+        assert!(registry.register_alias("ipfs2", "p2p").is_ok());
+        assert!(registry.register_alias("p2p", "tcp").is_err());
+        assert!(registry.register_alias("bar", "nope").is_err());
+        assert!(registry.register_alias("12", "tcp").is_err());
+        assert!(registry.register(0x300002, "foo-old", Size::Fixed(0)).is_err());
+        assert!(Multiaddr::from_str("/utp2").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::ops::Range;
+
+use protocol::Protocol::*;
+use registry::Proto;
+use {read_component, Multiaddr, ParseResult, Protocol, Registry};
+
+/// What `validate_bytes` found in a valid multiaddr.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub components: Vec<ComponentInfo>,
+    pub warnings: Vec<Warning>,
+}
+
+/// A decoded component and where it is in the validated bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentInfo {
+    pub code: u32,
+    /// The protocol's name, or "unknown" for an unknown code.
+    pub name: String,
+    /// The whole component, including its code and length prefix.
+    pub range: Range<usize>,
+    /// The component's address alone.
+    pub value: Range<usize>,
+}
+
+/// Something legal but suspicious in a multiaddr. `index` is the position
+/// of the component in `ValidationReport::components`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Warning {
+    /// A code that isn't in the registry, only accepted because it allows
+    /// unknown protocols.
+    UnknownCode { index: usize, code: u32 },
+    /// A protocol that has been superseded, such as quic by quic-v1.
+    Deprecated { index: usize, protocol: Protocol },
+    /// Port 0, which can't be dialed.
+    ZeroPort { index: usize },
+}
+
+impl Multiaddr {
+    /// Validates the binary form of a multiaddr like `validate`, and
+    /// reports its components and anything suspicious in it. Meant for
+    /// linting addresses received from peers.
+    pub fn validate_bytes(b: &[u8]) -> ParseResult<ValidationReport> {
+        Registry::new().validate_bytes(b)
+    }
+}
+
+impl Registry {
+    /// Like `Multiaddr::validate_bytes`, with the protocols and limits of
+    /// this registry.
+    pub fn validate_bytes(&self, b: &[u8]) -> ParseResult<ValidationReport> {
+        try!(::verify_multiaddr_bytes(b, self));
+
+        let mut report = ValidationReport {
+            components: Vec::new(),
+            warnings: Vec::new(),
+        };
+        let mut rest = b;
+        while !rest.is_empty() {
+            let start = b.len() - rest.len();
+            let (proto, addr) = try!(read_component(&mut rest, self));
+            let end = b.len() - rest.len();
+
+            let index = report.components.len();
+            match proto {
+                Proto::Builtin(p) => {
+                    if p.is_deprecated() {
+                        report.warnings.push(Warning::Deprecated { index: index, protocol: p });
+                    }
+                    if is_port(p) && addr == [0, 0] {
+                        report.warnings.push(Warning::ZeroPort { index: index });
+                    }
+                }
+                Proto::Unknown(code) => {
+                    report.warnings.push(Warning::UnknownCode { index: index, code: code });
+                }
+                Proto::Custom(_) => {}
+            }
+            report.components.push(ComponentInfo {
+                code: proto.code(),
+                name: proto.name().to_string(),
+                range: start..end,
+                value: end - addr.len()..end,
+            });
+        }
+        Ok(report)
+    }
+}
+
+fn is_port(p: Protocol) -> bool {
+    match p {
+        TCP | UDP | DCCP | SCTP => true,
+        _ => false,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use super::{ComponentInfo, Warning};
+    use {Multiaddr, Protocol, Registry};
+
+    #[test]
+    fn test_validate_bytes() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/udp/0/quic").unwrap();
+        let report = Multiaddr::validate_bytes(ma.as_bytes()).unwrap();
+        assert_eq!(report.components,
+                   [ComponentInfo { code: 4, name: "ip4".into(), range: 0..5, value: 1..5 },
+                    ComponentInfo { code: 17, name: "udp".into(), range: 5..8, value: 6..8 },
+                    ComponentInfo { code: 460, name: "quic".into(), range: 8..10, value: 10..10 }]);
+        assert_eq!(report.warnings,
+                   [Warning::ZeroPort { index: 1 },
+                    Warning::Deprecated { index: 2, protocol: Protocol::QUIC }]);
+
+        assert!(Multiaddr::validate_bytes(&[0xe2, 0x20, 1, 7]).is_err());
+        let mut registry = Registry::new();
+        registry.set_allow_unknown(true);
+        let report = registry.validate_bytes(&[0xe2, 0x20, 1, 7]).unwrap();
+        assert_eq!(report.components[0].value, 3..4);
+        assert_eq!(report.warnings, [Warning::UnknownCode { index: 0, code: 4194 }]);
+    }
+}
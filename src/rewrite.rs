@@ -0,0 +1,162 @@
+//! Rules for rewriting listen addresses into the addresses advertised to
+//! other peers, e.g. behind a NAT that maps a private address and port to
+//! a public one.
+
+use std::net::Ipv4Addr;
+
+use byteorder::{BigEndian, ByteOrder};
+use protocol::Protocol::*;
+use {write_component, Maddr, Multiaddr, MultiaddrSet, ParseError, ParseResult, Registry};
+
+/// A rewriting rule, applied to one address at a time.
+pub trait Rule {
+    /// Returns the rewritten address, or None to drop the address.
+    fn apply(&self, ma: &Maddr) -> Option<Multiaddr>;
+}
+
+/// Closures make quick custom rules.
+impl<F: Fn(&Maddr) -> Option<Multiaddr>> Rule for F {
+    fn apply(&self, ma: &Maddr) -> Option<Multiaddr> {
+        self(ma)
+    }
+}
+
+/// Replaces a leading private ip4 address, such as 192.168.1.5, by a
+/// public one, e.g. the address a NAT maps it to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ReplacePrivateIp4(pub Ipv4Addr);
+
+impl Rule for ReplacePrivateIp4 {
+    fn apply(&self, ma: &Maddr) -> Option<Multiaddr> {
+        match ma.iter().next() {
+            Some((IP4, _)) if ma.is_private() => ma.replace(IP4, &self.0.to_string()).ok(),
+            _ => Some(ma.to_owned()),
+        }
+    }
+}
+
+/// Replaces the tcp or udp port `from` by `to`, e.g. for a port forwarded
+/// by a router.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SwapPort {
+    pub from: u16,
+    pub to: u16,
+}
+
+impl Rule for SwapPort {
+    fn apply(&self, ma: &Maddr) -> Option<Multiaddr> {
+        let mut bytes = Vec::with_capacity(ma.byte_len());
+        for (p, addr) in ma.iter() {
+            if (p == TCP || p == UDP) && BigEndian::read_u16(addr) == self.from {
+                let mut port = [0; 2];
+                BigEndian::write_u16(&mut port, self.to);
+                write_component(p, &port, &mut bytes);
+            } else {
+                write_component(p, addr, &mut bytes);
+            }
+        }
+        Some(Multiaddr { bytes: bytes.into() })
+    }
+}
+
+/// Appends /ipfs/<peer id> to addresses that don't end with a peer id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppendPeerId {
+    component: Multiaddr,
+}
+
+impl AppendPeerId {
+    /// Takes the binary multihash of the peer id.
+    pub fn new(peer_id: &[u8]) -> ParseResult<AppendPeerId> {
+        try!(Registry::new().check_address(IPFS, peer_id).map_err(ParseError::invalid_address));
+        let mut bytes = Vec::new();
+        write_component(IPFS, peer_id, &mut bytes);
+        Ok(AppendPeerId { component: Multiaddr { bytes: bytes.into() } })
+    }
+}
+
+impl Rule for AppendPeerId {
+    fn apply(&self, ma: &Maddr) -> Option<Multiaddr> {
+        match ma.iter().last() {
+            Some((IPFS, _)) => Some(ma.to_owned()),
+            _ => Some(Multiaddr::join(&[ma, &self.component])),
+        }
+    }
+}
+
+/// Applies a list of rules in order to each of a set of addresses.
+#[derive(Default)]
+pub struct Rewriter {
+    rules: Vec<Box<dyn Rule + Send + Sync>>,
+}
+
+impl Rewriter {
+    pub fn new() -> Rewriter {
+        Rewriter::default()
+    }
+
+    /// Adds a rule, applied after those already added.
+    pub fn add<R: Rule + Send + Sync + 'static>(&mut self, rule: R) -> &mut Rewriter {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Rewrites one address, returning None if a rule dropped it.
+    pub fn rewrite(&self, ma: &Maddr) -> Option<Multiaddr> {
+        let mut ma = ma.to_owned();
+        for rule in &self.rules {
+            ma = match rule.apply(&ma) {
+                Some(ma) => ma,
+                None => return None,
+            };
+        }
+        Some(ma)
+    }
+
+    /// Rewrites every address, dropping those a rule dropped and any
+    /// duplicates that result.
+    pub fn rewrite_all<'a, I>(&self, addrs: I) -> MultiaddrSet
+        where I: IntoIterator<Item = &'a Multiaddr>
+    {
+        addrs.into_iter().filter_map(|ma| self.rewrite(ma)).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{AppendPeerId, ReplacePrivateIp4, Rewriter, SwapPort};
+    use std::net::Ipv4Addr;
+    use std::str::FromStr;
+    use {Maddr, Multiaddr};
+
+    fn ma(s: &str) -> Multiaddr {
+        Multiaddr::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_rewriter() {
+        let peer = [&[0x12, 32][..], &[7; 32][..]].concat();
+        let mut rewriter = Rewriter::new();
+        rewriter.add(ReplacePrivateIp4(Ipv4Addr::new(5, 6, 7, 8)))
+                .add(SwapPort { from: 4001, to: 14001 })
+                .add(|ma: &Maddr| if ma.is_loopback() { None } else { Some(ma.to_owned()) })
+                .add(AppendPeerId::new(&peer).unwrap());
+
+        let addrs = vec![ma("/ip4/192.168.1.5/tcp/4001"),
+                         ma("/ip4/10.0.0.2/tcp/4001"),
+                         ma("/ip4/127.0.0.1/tcp/4001"),
+                         ma("/ip4/1.2.3.4/udp/4002/quic")];
+        let rewritten: Vec<_> = rewriter.rewrite_all(&addrs).into_iter().collect();
+        assert_eq!(rewritten.len(), 2);
+        assert_eq!(rewritten[0].value_for(::Protocol::IP4).unwrap(), "5.6.7.8");
+        assert_eq!(rewritten[0].value_for(::Protocol::TCP).unwrap(), "14001");
+        assert_eq!(rewritten[0].value_bytes_for(::Protocol::IPFS).unwrap(), &peer[..]);
+        assert_eq!(rewritten[1].value_for(::Protocol::IP4).unwrap(), "1.2.3.4");
+        assert_eq!(rewritten[1].value_for(::Protocol::UDP).unwrap(), "4002");
+
+        // A peer id isn't appended twice
+        assert_eq!(rewriter.rewrite(&rewritten[0]).unwrap(), rewritten[0]);
+        assert!(AppendPeerId::new(&[0x12, 2, 1, 2]).is_err());
+    }
+}
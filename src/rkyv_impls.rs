@@ -0,0 +1,55 @@
+//! rkyv archiving, enabled by the `rkyv` feature.
+//!
+//! A multiaddr is archived as its binary form. Since the bytes were valid
+//! when archived, an `ArchivedMultiaddr` is read in place as a `&Maddr`
+//! without validating it again.
+
+use rkyv::ser::{ScratchSpace, Serializer};
+use rkyv::vec::{ArchivedVec, VecResolver};
+use rkyv::{Archive, Deserialize, Fallible, Serialize};
+use std::fmt;
+use std::ops::Deref;
+
+use {storage_from_slice, Maddr, Multiaddr};
+
+/// The archived form of a `Multiaddr`, which derefs to `Maddr`.
+#[repr(transparent)]
+pub struct ArchivedMultiaddr {
+    bytes: ArchivedVec<u8>,
+}
+
+impl Deref for ArchivedMultiaddr {
+    type Target = Maddr;
+    fn deref(&self) -> &Maddr {
+        Maddr::from_bytes_unchecked(self.bytes.as_slice())
+    }
+}
+
+impl fmt::Debug for ArchivedMultiaddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl Archive for Multiaddr {
+    type Archived = ArchivedMultiaddr;
+    type Resolver = VecResolver;
+
+    unsafe fn resolve(&self, pos: usize, resolver: VecResolver, out: *mut ArchivedMultiaddr) {
+        // ArchivedMultiaddr is a transparent wrapper around ArchivedVec
+        ArchivedVec::resolve_from_slice(self.as_bytes(), pos, resolver,
+                                        out as *mut ArchivedVec<u8>);
+    }
+}
+
+impl<S: ScratchSpace + Serializer + ?Sized> Serialize<S> for Multiaddr {
+    fn serialize(&self, serializer: &mut S) -> Result<VecResolver, S::Error> {
+        ArchivedVec::serialize_from_slice(self.as_bytes(), serializer)
+    }
+}
+
+impl<D: Fallible + ?Sized> Deserialize<Multiaddr, D> for ArchivedMultiaddr {
+    fn deserialize(&self, _: &mut D) -> Result<Multiaddr, D::Error> {
+        Ok(Multiaddr { bytes: storage_from_slice(self.bytes.as_slice()) })
+    }
+}
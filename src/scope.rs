@@ -0,0 +1,129 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use protocol::Protocol::*;
+use {read_ip4, read_ip6, Maddr};
+
+// How widely an address can be reached, from most to least useful to others
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Scope {
+    Public,
+    // RFC1918, CGNAT, link-local and unique-local
+    Private,
+    Loopback,
+    // Unspecified, multicast, broadcast, documentation and reserved
+    Unroutable,
+}
+
+impl Maddr {
+    /// Checks whether the first ip4, ip6 or dns component refers to the
+    /// local host: 127.0.0.0/8, ::1 or localhost.
+    pub fn is_loopback(&self) -> bool {
+        self.scope() == Some(Scope::Loopback)
+    }
+
+    /// Checks whether the first ip4, ip6 or dns component refers to a
+    /// private network: RFC1918 or CGNAT ranges, link-local or unique-local
+    /// addresses, or .local names.
+    pub fn is_private(&self) -> bool {
+        self.scope() == Some(Scope::Private)
+    }
+
+    /// Checks whether the first ip4, ip6 or dns component is globally
+    /// routable, i.e. is worth advertising to remote peers.
+    pub fn is_public(&self) -> bool {
+        self.scope() == Some(Scope::Public)
+    }
+
+    pub(crate) fn scope(&self) -> Option<Scope> {
+        self.iter().filter_map(|(p, addr)| {
+            match p {
+                IP4 => Some(ip4_scope(&read_ip4(addr))),
+                IP6 => Some(ip6_scope(&read_ip6(addr))),
+                #[cfg(feature = "proto-dns")]
+                DNS | DNS4 | DNS6 | DNSADDR => Some(dns_scope(&String::from_utf8_lossy(addr))),
+                _ => None,
+            }
+        }).next()
+    }
+}
+
+fn ip4_scope(ip: &Ipv4Addr) -> Scope {
+    let o = ip.octets();
+    if ip.is_loopback() {
+        Scope::Loopback
+    } else if ip.is_private() || ip.is_link_local() || (o[0] == 100 && o[1] & 0xc0 == 64) {
+        Scope::Private
+    } else if o[0] == 0 || o[0] >= 224 || ip.is_documentation() {
+        Scope::Unroutable
+    } else {
+        Scope::Public
+    }
+}
+
+fn ip6_scope(ip: &Ipv6Addr) -> Scope {
+    let s = ip.segments();
+    if let [0, 0, 0, 0, 0, 0xffff, hi, lo] = s {
+        return ip4_scope(&Ipv4Addr::from((hi as u32) << 16 | lo as u32));
+    }
+
+    if ip.is_loopback() {
+        Scope::Loopback
+    } else if s[0] & 0xfe00 == 0xfc00 || s[0] & 0xffc0 == 0xfe80 {
+        Scope::Private
+    } else if ip.is_unspecified() || ip.is_multicast() || (s[0] == 0x2001 && s[1] == 0xdb8) {
+        Scope::Unroutable
+    } else {
+        Scope::Public
+    }
+}
+
+#[cfg(feature = "proto-dns")]
+fn dns_scope(name: &str) -> Scope {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    if name == "localhost" || name.ends_with(".localhost") {
+        Scope::Loopback
+    } else if name.ends_with(".local") {
+        Scope::Private
+    } else {
+        Scope::Public
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use Multiaddr;
+
+    fn ma(s: &str) -> Multiaddr {
+        Multiaddr::from_str(s).unwrap()
+    }
+
+    #[cfg(feature = "proto-dns")]
+    #[test]
+    fn test_classification() {
+        let loopback = ["/ip4/127.0.0.1/tcp/1", "/ip6/::1", "/dns4/localhost/tcp/1",
+                        "/ip6/::ffff:127.0.0.1"];
+        let private = ["/ip4/10.1.2.3", "/ip4/172.16.0.1", "/ip4/192.168.1.1/udp/1",
+                       "/ip4/169.254.1.1", "/ip4/100.64.0.1", "/ip4/100.127.255.255",
+                       "/ip6/fe80::1", "/ip6/fd00::1", "/dns/printer.local"];
+        let public = ["/ip4/8.8.8.8/tcp/53", "/ip4/100.128.0.1", "/ip4/172.32.0.1",
+                      "/ip6/2604:1380::1", "/dnsaddr/bootstrap.libp2p.io"];
+        let neither = ["/ip4/0.0.0.0", "/ip4/255.255.255.255", "/ip4/224.0.0.1",
+                       "/ip4/192.0.2.1", "/ip6/::", "/ip6/ff02::1", "/ip6/2001:db8::1",
+                       "/tcp/1", "/"];
+
+        for s in &loopback {
+            assert!(ma(s).is_loopback() && !ma(s).is_private() && !ma(s).is_public(), "{}", s);
+        }
+        for s in &private {
+            assert!(!ma(s).is_loopback() && ma(s).is_private() && !ma(s).is_public(), "{}", s);
+        }
+        for s in &public {
+            assert!(!ma(s).is_loopback() && !ma(s).is_private() && ma(s).is_public(), "{}", s);
+        }
+        for s in &neither {
+            assert!(!ma(s).is_loopback() && !ma(s).is_private() && !ma(s).is_public(), "{}", s);
+        }
+    }
+}
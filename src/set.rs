@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+use std::iter::FromIterator;
+use std::slice;
+use std::vec;
+use varint::VarintWrite;
+
+use {read_varint, ErrorKind, Maddr, Multiaddr, ParseError, ParseResult};
+
+/// A set of multiaddrs that remembers insertion order, e.g. the addresses a
+/// peer store advertises for a peer.
+///
+/// Lookups are O(1); removal is O(n) in the size of the set.
+#[derive(Clone, Debug, Default)]
+pub struct MultiaddrSet {
+    order: Vec<Multiaddr>,
+    members: HashSet<Multiaddr>,
+}
+
+impl MultiaddrSet {
+    pub fn new() -> MultiaddrSet {
+        MultiaddrSet::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn contains(&self, ma: &Maddr) -> bool {
+        self.members.contains(ma)
+    }
+
+    /// Adds `ma` at the end, returning false if it was already present.
+    pub fn insert(&mut self, ma: Multiaddr) -> bool {
+        if !self.members.insert(ma.clone()) {
+            return false;
+        }
+        self.order.push(ma);
+        true
+    }
+
+    /// Removes `ma`, returning false if it wasn't present.
+    pub fn remove(&mut self, ma: &Maddr) -> bool {
+        if !self.members.remove(ma) {
+            return false;
+        }
+        self.order.retain(|m| &**m != ma);
+        true
+    }
+
+    /// Iterates in insertion order.
+    pub fn iter<'a>(&'a self) -> slice::Iter<'a, Multiaddr> {
+        self.order.iter()
+    }
+
+    /// Returns the multiaddrs of `self` followed by those only in `other`.
+    pub fn union(&self, other: &MultiaddrSet) -> MultiaddrSet {
+        self.iter().chain(other.iter()).cloned().collect()
+    }
+
+    /// Returns the multiaddrs of `self` that are also in `other`.
+    pub fn intersection(&self, other: &MultiaddrSet) -> MultiaddrSet {
+        self.iter().filter(|ma| other.contains(ma)).cloned().collect()
+    }
+
+    /// Returns the multiaddrs of `self` that aren't in `other`.
+    pub fn difference(&self, other: &MultiaddrSet) -> MultiaddrSet {
+        self.iter().filter(|ma| !other.contains(ma)).cloned().collect()
+    }
+
+    /// Encodes the set compactly, for storing address books or sending
+    /// peer records. Each multiaddr is written as the number of leading
+    /// bytes it shares with the previous one, followed by the rest of its
+    /// bytes with a length prefix, so addresses that differ only in their
+    /// port or peer id take a few bytes each. The count of multiaddrs comes
+    /// first. All numbers are varints.
+    pub fn encode_compact(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.write_unsigned_varint_32(self.len() as u32).unwrap();
+        let mut prev: &[u8] = &[];
+        for ma in self.iter() {
+            let bytes = ma.as_bytes();
+            let shared = prev.iter().zip(bytes).take_while(|&(a, b)| a == b).count();
+            buf.write_unsigned_varint_32(shared as u32).unwrap();
+            buf.write_unsigned_varint_32((bytes.len() - shared) as u32).unwrap();
+            buf.extend(&bytes[shared..]);
+            prev = bytes;
+        }
+        buf
+    }
+
+    /// Decodes a set written by `encode_compact`, validating each multiaddr
+    /// as `Multiaddr::from_bytes` does.
+    pub fn decode_compact(mut b: &[u8]) -> ParseResult<MultiaddrSet> {
+        let count = try!(read_varint(&mut b).map_err(|e| {
+            ParseError::varint(ErrorKind::Other, format!("Error reading count: {}", e), e)
+        }));
+        let mut set = MultiaddrSet::new();
+        let mut prev: Vec<u8> = Vec::new();
+        for i in 0..count {
+            let (shared, len) = match (read_varint(&mut b), read_varint(&mut b)) {
+                (Ok(shared), Ok(len)) => (shared as usize, len as usize),
+                (Err(e), _) | (_, Err(e)) => {
+                    let message = format!("Error reading multiaddr {}: {}", i, e);
+                    return Err(ParseError::varint(ErrorKind::Other, message, e));
+                }
+            };
+            if shared > prev.len() || len > b.len() {
+                return Err(ParseError::truncated(format!("Multiaddr {} is cut short", i)));
+            }
+            let mut bytes = prev[..shared].to_vec();
+            bytes.extend(&b[..len]);
+            b = &b[len..];
+
+            let ma = try!(Multiaddr::from_bytes(bytes));
+            prev.clear();
+            prev.extend(ma.as_bytes());
+            set.insert(ma);
+        }
+        if !b.is_empty() {
+            return Err(ParseError::other(format!("{} bytes left after the last multiaddr",
+                                                 b.len())));
+        }
+        Ok(set)
+    }
+}
+
+/// Sets are equal if they have the same members, in any order.
+impl PartialEq for MultiaddrSet {
+    fn eq(&self, other: &MultiaddrSet) -> bool {
+        self.members == other.members
+    }
+}
+
+impl Eq for MultiaddrSet { }
+
+impl FromIterator<Multiaddr> for MultiaddrSet {
+    fn from_iter<I: IntoIterator<Item = Multiaddr>>(iter: I) -> MultiaddrSet {
+        let mut set = MultiaddrSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Multiaddr> for MultiaddrSet {
+    fn extend<I: IntoIterator<Item = Multiaddr>>(&mut self, iter: I) {
+        for ma in iter {
+            self.insert(ma);
+        }
+    }
+}
+
+impl IntoIterator for MultiaddrSet {
+    type Item = Multiaddr;
+    type IntoIter = vec::IntoIter<Multiaddr>;
+    fn into_iter(self) -> vec::IntoIter<Multiaddr> {
+        self.order.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a MultiaddrSet {
+    type Item = &'a Multiaddr;
+    type IntoIter = slice::Iter<'a, Multiaddr>;
+    fn into_iter(self) -> slice::Iter<'a, Multiaddr> {
+        self.iter()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::MultiaddrSet;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    fn set(addrs: &[&str]) -> MultiaddrSet {
+        addrs.iter().map(|s| Multiaddr::from_str(s).unwrap()).collect()
+    }
+
+    fn strings(set: &MultiaddrSet) -> Vec<String> {
+        set.iter().map(|ma| ma.to_string()).collect()
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut s = set(&["/ip4/1.2.3.4/tcp/1", "/ip4/1.2.3.4/tcp/2"]);
+        assert!(!s.insert(Multiaddr::from_str("/ip4/1.2.3.4/tcp/1").unwrap()));
+        assert!(s.insert(Multiaddr::from_str("/ip4/1.2.3.4/tcp/0").unwrap()));
+        assert_eq!(strings(&s), ["/ip4/1.2.3.4/tcp/1", "/ip4/1.2.3.4/tcp/2", "/ip4/1.2.3.4/tcp/0"]);
+
+        let two = Multiaddr::from_str("/ip4/1.2.3.4/tcp/2").unwrap();
+        assert!(s.contains(&two));
+        assert!(s.remove(&two));
+        assert!(!s.remove(&two));
+        assert!(!s.contains(&two));
+        assert_eq!(strings(&s), ["/ip4/1.2.3.4/tcp/1", "/ip4/1.2.3.4/tcp/0"]);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = set(&["/ip4/1.1.1.1/tcp/1", "/ip4/2.2.2.2/tcp/1", "/ip4/3.3.3.3/tcp/1"]);
+        let b = set(&["/ip4/4.4.4.4/tcp/1", "/ip4/2.2.2.2/tcp/1"]);
+        assert_eq!(strings(&a.union(&b)),
+                   ["/ip4/1.1.1.1/tcp/1", "/ip4/2.2.2.2/tcp/1", "/ip4/3.3.3.3/tcp/1", "/ip4/4.4.4.4/tcp/1"]);
+        assert_eq!(strings(&a.intersection(&b)), ["/ip4/2.2.2.2/tcp/1"]);
+        assert_eq!(strings(&a.difference(&b)), ["/ip4/1.1.1.1/tcp/1", "/ip4/3.3.3.3/tcp/1"]);
+        assert_eq!(a.union(&b), b.union(&a));
+    }
+
+    #[test]
+    fn test_compact_encoding() {
+        let s = set(&["/ip4/1.2.3.4/tcp/4001", "/ip4/1.2.3.4/tcp/4002/ws",
+                      "/ip4/1.2.3.4/udp/4001/quic", "/ip6/::1/tcp/4001"]);
+        let encoded = s.encode_compact();
+        let plain: usize = s.iter().map(|ma| ma.as_bytes().len() + 1).sum();
+        assert!(encoded.len() < plain);
+        let decoded = MultiaddrSet::decode_compact(&encoded).unwrap();
+        assert_eq!(strings(&decoded), strings(&s));
+        assert_eq!(MultiaddrSet::decode_compact(&[0]).unwrap(), MultiaddrSet::new());
+
+        for len in 0..encoded.len() {
+            assert!(MultiaddrSet::decode_compact(&encoded[..len]).is_err());
+        }
+        let mut trailing = encoded.clone();
+        trailing.push(0);
+        assert!(MultiaddrSet::decode_compact(&trailing).is_err());
+        // sharing more bytes than the previous multiaddr has
+        assert!(MultiaddrSet::decode_compact(&[1, 1, 0]).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! Conversion of DNS SRV records (RFC 2782) into multiaddrs.
+
+use host_port::Transport;
+use {Multiaddr, ParseError, ParseResult};
+
+/// A record from an SRV lookup.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    /// The host name of the target, with or without a trailing dot
+    pub target: String,
+}
+
+impl SrvRecord {
+    pub fn new(priority: u16, weight: u16, port: u16, target: &str) -> SrvRecord {
+        SrvRecord {
+            priority: priority,
+            weight: weight,
+            port: port,
+            target: target.to_string(),
+        }
+    }
+
+    /// Returns /dns4/<target>/tcp/<port>, or udp for `Transport::Udp`. The
+    /// target ".", which means the service isn't available, is an error.
+    pub fn to_multiaddr(&self, transport: Transport) -> ParseResult<Multiaddr> {
+        let target = self.target.trim_end_matches('.');
+        if target.is_empty() {
+            return Err(ParseError::invalid_address(format!("SRV record {:?} has no target",
+                                                           self.target)));
+        }
+        Multiaddr::from_host_port(&format!("{}:{}", target, self.port), transport)
+    }
+}
+
+impl Transport {
+    /// Returns the protocol label of SRV names, "_tcp" or "_udp".
+    pub fn srv_label(&self) -> &'static str {
+        match *self {
+            Transport::Tcp => "_tcp",
+            Transport::Udp => "_udp",
+        }
+    }
+}
+
+/// Returns the name to look up the SRV records of `service` at `domain`
+/// under, e.g. "_ipfs._tcp.example.com" for "ipfs".
+pub fn srv_name(service: &str, transport: Transport, domain: &str) -> String {
+    format!("_{}.{}.{}", service.trim_start_matches('_'), transport.srv_label(), domain)
+}
+
+/// Sorts records in the order to try them: lowest priority first and,
+/// within a priority, highest weight first. RFC 2782 picks among records of
+/// the same priority at random, in proportion to their weight; this is
+/// the deterministic version of that.
+pub fn sort_srv_records(records: &mut [SrvRecord]) {
+    records.sort_by(|a, b| a.priority.cmp(&b.priority).then(b.weight.cmp(&a.weight)));
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{sort_srv_records, srv_name, SrvRecord};
+    use host_port::Transport;
+
+    #[test]
+    fn test_to_multiaddr() {
+        let record = SrvRecord::new(10, 5, 4001, "node1.example.com.");
+        assert_eq!(record.to_multiaddr(Transport::Tcp).unwrap().to_string(),
+                   "/dns4/node1.example.com/tcp/4001");
+        assert_eq!(record.to_multiaddr(Transport::Udp).unwrap().to_string(),
+                   "/dns4/node1.example.com/udp/4001");
+        assert_eq!(SrvRecord::new(0, 0, 53, "1.2.3.4").to_multiaddr(Transport::Udp)
+                                                        .unwrap().to_string(),
+                   "/ip4/1.2.3.4/udp/53");
+        assert!(SrvRecord::new(0, 0, 0, ".").to_multiaddr(Transport::Tcp).is_err());
+        assert!(SrvRecord::new(0, 0, 80, "a..b").to_multiaddr(Transport::Tcp).is_err());
+    }
+
+    #[test]
+    fn test_srv_name_and_order() {
+        assert_eq!(srv_name("ipfs", Transport::Tcp, "example.com"), "_ipfs._tcp.example.com");
+        assert_eq!(srv_name("_sip", Transport::Udp, "example.com"), "_sip._udp.example.com");
+
+        let mut records = vec![SrvRecord::new(20, 0, 1, "c"),
+                               SrvRecord::new(10, 1, 2, "b"),
+                               SrvRecord::new(10, 9, 3, "a")];
+        sort_srv_records(&mut records);
+        let targets: Vec<_> = records.iter().map(|r| &r.target[..]).collect();
+        assert_eq!(targets, vec!["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,176 @@
+use std::borrow::Borrow;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::str::FromStr;
+
+use {BufferTooSmall, Maddr, Multiaddr, ParseError, ParseResult};
+
+/// A multiaddr stored inline in an array of `N` bytes, for use where
+/// allocating isn't possible or too slow, e.g. per packet.
+///
+/// ```
+/// # use rust_multiaddr::StaticMultiaddr;
+/// let mut ma: StaticMultiaddr<32> = "/ip4/1.2.3.4".parse().unwrap();
+/// ma.push(&"/tcp/80".parse::<StaticMultiaddr<8>>().unwrap()).unwrap();
+/// assert_eq!(ma.to_string(), "/ip4/1.2.3.4/tcp/80");
+/// ```
+///
+/// Decoding with `from_bytes` and appending with `push` don't allocate.
+/// Parsing the textual form goes through `Multiaddr::from_str`, which does.
+#[derive(Copy, Clone)]
+pub struct StaticMultiaddr<const N: usize> {
+    len: usize,
+    buf: [u8; N],
+}
+
+impl<const N: usize> StaticMultiaddr<N> {
+    /// Creates a multiaddr with no components.
+    pub const fn new() -> StaticMultiaddr<N> {
+        StaticMultiaddr { len: 0, buf: [0; N] }
+    }
+
+    /// Validates `b` and copies it, failing if it is longer than `N` bytes.
+    pub fn from_bytes(b: &[u8]) -> ParseResult<StaticMultiaddr<N>> {
+        let ma = try!(Maddr::from_bytes(b));
+        StaticMultiaddr::try_from(ma).map_err(|e| {
+            ParseError::too_long(format!("Multiaddr is {} bytes, more than the capacity of {}",
+                                         e.needed, e.available))
+        })
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Appends the components of `other`, failing without changing the
+    /// multiaddr if they don't fit.
+    pub fn push(&mut self, other: &Maddr) -> Result<(), BufferTooSmall> {
+        let bytes = other.as_bytes();
+        let len = self.len + bytes.len();
+        if len > N {
+            return Err(BufferTooSmall { needed: len, available: N });
+        }
+        self.buf[self.len..len].copy_from_slice(bytes);
+        self.len = len;
+        Ok(())
+    }
+
+    /// Removes all components.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for StaticMultiaddr<N> {
+    fn default() -> StaticMultiaddr<N> {
+        StaticMultiaddr::new()
+    }
+}
+
+impl<const N: usize> Deref for StaticMultiaddr<N> {
+    type Target = Maddr;
+    fn deref(&self) -> &Maddr {
+        Maddr::from_bytes_unchecked(&self.buf[..self.len])
+    }
+}
+
+impl<const N: usize> Borrow<Maddr> for StaticMultiaddr<N> {
+    fn borrow(&self) -> &Maddr {
+        self
+    }
+}
+
+impl<const N: usize> AsRef<Maddr> for StaticMultiaddr<N> {
+    fn as_ref(&self) -> &Maddr {
+        self
+    }
+}
+
+impl<'a, const N: usize> TryFrom<&'a Maddr> for StaticMultiaddr<N> {
+    type Error = BufferTooSmall;
+    fn try_from(ma: &'a Maddr) -> Result<Self, Self::Error> {
+        let mut s = StaticMultiaddr::new();
+        try!(s.push(ma));
+        Ok(s)
+    }
+}
+
+impl<const N: usize> From<StaticMultiaddr<N>> for Multiaddr {
+    fn from(ma: StaticMultiaddr<N>) -> Multiaddr {
+        Maddr::to_owned(&ma)
+    }
+}
+
+/// Fails if the multiaddr is invalid or longer than `N` bytes.
+impl<const N: usize> FromStr for StaticMultiaddr<N> {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ma = try!(Multiaddr::from_str(s));
+        StaticMultiaddr::from_bytes(ma.as_bytes())
+    }
+}
+
+impl<const N: usize> PartialEq for StaticMultiaddr<N> {
+    fn eq(&self, other: &StaticMultiaddr<N>) -> bool {
+        **self == **other
+    }
+}
+
+impl<const N: usize> Eq for StaticMultiaddr<N> {}
+
+impl<const N: usize> PartialEq<Multiaddr> for StaticMultiaddr<N> {
+    fn eq(&self, other: &Multiaddr) -> bool {
+        **self == **other
+    }
+}
+
+impl<const N: usize> Hash for StaticMultiaddr<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl<const N: usize> fmt::Display for StaticMultiaddr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<const N: usize> fmt::Debug for StaticMultiaddr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use super::StaticMultiaddr;
+    use {BufferTooSmall, Multiaddr};
+
+    #[test]
+    fn test_static_multiaddr() {
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/80").unwrap();
+        let s = StaticMultiaddr::<8>::from_bytes(ma.as_bytes()).unwrap();
+        assert_eq!(s, ma);
+        assert_eq!(Multiaddr::from(s), ma);
+        assert_eq!(s.port(), Some(80));
+
+        assert!(StaticMultiaddr::<7>::from_bytes(ma.as_bytes()).is_err());
+        assert!(StaticMultiaddr::<8>::from_bytes(&[4, 1, 2, 3]).is_err());
+        assert!("/ip4/1.2.3.4/tcp/80/ws".parse::<StaticMultiaddr<8>>().is_err());
+
+        let mut s = StaticMultiaddr::<10>::new();
+        assert_eq!(s.to_string(), "");
+        s.push(&ma).unwrap();
+        let ws = Multiaddr::from_str("/ws").unwrap();
+        s.push(&ws).unwrap();
+        assert_eq!(s.push(&ws), Err(BufferTooSmall { needed: 12, available: 10 }));
+        assert_eq!(s.to_string(), "/ip4/1.2.3.4/tcp/80/ws");
+        s.clear();
+        assert!(s.is_empty());
+    }
+}
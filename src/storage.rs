@@ -0,0 +1,131 @@
+use std::fmt;
+use std::ops::Deref;
+
+// Number of bytes stored without allocating. This covers ip6 + tcp/udp,
+// and with the length byte and the tag makes SmallBytes 32 bytes on 64-bit
+// targets, a word more than a Vec.
+const INLINE_CAP: usize = 30;
+
+// The buffer behind a Multiaddr. Short addresses, which are the vast
+// majority, are stored inline instead of on the heap.
+#[derive(Clone)]
+pub enum SmallBytes {
+    Inline { len: u8, buf: [u8; INLINE_CAP] },
+    Heap(Vec<u8>),
+}
+
+impl SmallBytes {
+    pub fn from_slice(b: &[u8]) -> SmallBytes {
+        if b.len() <= INLINE_CAP {
+            let mut buf = [0; INLINE_CAP];
+            buf[..b.len()].copy_from_slice(b);
+            SmallBytes::Inline { len: b.len() as u8, buf: buf }
+        } else {
+            SmallBytes::Heap(b.to_vec())
+        }
+    }
+
+    pub fn with_capacity(n: usize) -> SmallBytes {
+        if n <= INLINE_CAP {
+            SmallBytes::Inline { len: 0, buf: [0; INLINE_CAP] }
+        } else {
+            SmallBytes::Heap(Vec::with_capacity(n))
+        }
+    }
+
+    // Appends in place, moving to the heap once the inline buffer is full
+    pub fn extend_from_slice(&mut self, b: &[u8]) {
+        let spilled = match *self {
+            SmallBytes::Inline { ref mut len, ref mut buf } => {
+                let old = *len as usize;
+                if old + b.len() <= INLINE_CAP {
+                    buf[old..old + b.len()].copy_from_slice(b);
+                    *len += b.len() as u8;
+                    return;
+                }
+                let mut v = Vec::with_capacity(old + b.len());
+                v.extend_from_slice(&buf[..old]);
+                v.extend_from_slice(b);
+                v
+            }
+            SmallBytes::Heap(ref mut v) => return v.extend_from_slice(b),
+        };
+        *self = SmallBytes::Heap(spilled);
+    }
+
+    #[cfg(test)]
+    pub fn is_inline(&self) -> bool {
+        match *self {
+            SmallBytes::Inline { .. } => true,
+            SmallBytes::Heap(_) => false,
+        }
+    }
+}
+
+impl From<Vec<u8>> for SmallBytes {
+    fn from(v: Vec<u8>) -> SmallBytes {
+        if v.len() <= INLINE_CAP {
+            SmallBytes::from_slice(&v)
+        } else {
+            SmallBytes::Heap(v)
+        }
+    }
+}
+
+impl From<SmallBytes> for Vec<u8> {
+    fn from(b: SmallBytes) -> Vec<u8> {
+        match b {
+            SmallBytes::Inline { len, buf } => buf[..len as usize].to_vec(),
+            SmallBytes::Heap(v) => v,
+        }
+    }
+}
+
+impl Deref for SmallBytes {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match *self {
+            SmallBytes::Inline { len, ref buf } => &buf[..len as usize],
+            SmallBytes::Heap(ref v) => v,
+        }
+    }
+}
+
+impl fmt::Debug for SmallBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{SmallBytes, INLINE_CAP};
+    use std::mem;
+
+    #[test]
+    fn test_small_bytes() {
+        let short = vec![4, 1, 2, 3, 4, 6, 4, 210];
+        let s = SmallBytes::from(short.clone());
+        assert!(s.is_inline());
+        assert_eq!(&*s, &short[..]);
+
+        let long: Vec<u8> = (0..INLINE_CAP as u8 + 1).collect();
+        let s = SmallBytes::from_slice(&long);
+        assert!(!s.is_inline());
+        assert_eq!(&*s, &long[..]);
+        assert_eq!(&*s.clone(), &long[..]);
+
+        let mut s = SmallBytes::with_capacity(4);
+        s.extend_from_slice(&short);
+        assert!(s.is_inline());
+        s.extend_from_slice(&long);
+        assert!(!s.is_inline());
+        assert_eq!(s.len(), short.len() + long.len());
+        assert_eq!(&s[short.len()..], &long[..]);
+
+        if cfg!(target_pointer_width = "64") {
+            assert_eq!(mem::size_of::<SmallBytes>(), 32);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! proptest strategies, enabled by the `proptest` feature.
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn roundtrip(ma in rust_multiaddr::strategies::multiaddr()) {
+//!         let s = ma.to_string();
+//!         prop_assert_eq!(s.parse::<Multiaddr>().unwrap(), ma);
+//!     }
+//! }
+//! ```
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::{select, Index};
+use std::str::FromStr;
+use varint::VarintWrite;
+
+use protocol::PROTOCOLS;
+use {Multiaddr, Protocol, Size};
+
+/// Any built-in protocol.
+pub fn protocol() -> impl Strategy<Value = Protocol> {
+    let all: Vec<_> = PROTOCOLS.iter()
+                               .map(|&(name, _)| Protocol::from_str(name).unwrap())
+                               .collect();
+    select(all)
+}
+
+/// The bytes of a single valid component.
+fn component() -> impl Strategy<Value = Vec<u8>> {
+    protocol().prop_flat_map(|p| {
+        let mut code = Vec::new();
+        code.write_unsigned_varint_32(u32::from(p)).unwrap();
+        let addr = match p.size() {
+            Size::Fixed(_) => {
+                let mask = p.size().padding_mask();
+                vec(any::<u8>(), p.size_bytes().unwrap() as usize).prop_map(move |mut addr| {
+                    if let Some(last) = addr.last_mut() {
+                        *last &= !mask;
+                    }
+                    // onion ports can't be 0
+                    if p.name().starts_with("onion") && addr.ends_with(&[0, 0]) {
+                        *addr.last_mut().unwrap() = 1;
+                    }
+                    addr
+                }).boxed()
+            }
+            // Make the address a well-formed sha2-256 multihash
+            Size::Variable if p == Protocol::IPFS => {
+                vec(any::<u8>(), 32)
+                    .prop_map(|digest| [&[34, 0x12, 32][..], &digest[..]].concat())
+                    .boxed()
+            }
+            // The dns protocols, which take a domain name of up to four
+            // labels
+            Size::Variable => {
+                let chars = b"abcdefghijklmnopqrstuvwxyz0123456789".to_vec();
+                vec(vec(select(chars), 1..9), 1..5).prop_map(|labels| {
+                    let name = labels.join(&b'.');
+                    let mut bytes = Vec::new();
+                    bytes.write_unsigned_varint_32(name.len() as u32).unwrap();
+                    bytes.extend(name);
+                    bytes
+                }).boxed()
+            }
+        };
+        addr.prop_map(move |addr| [&code[..], &addr[..]].concat())
+    })
+}
+
+/// Valid multiaddrs with up to `max_len` components.
+pub fn multiaddr_with_len(max_len: usize) -> impl Strategy<Value = Multiaddr> {
+    vec(component(), 0..max_len + 1).prop_map(|components| {
+        Multiaddr::from_bytes(components.concat()).expect("generated an invalid multiaddr")
+    })
+}
+
+/// Valid multiaddrs with up to 8 components.
+pub fn multiaddr() -> impl Strategy<Value = Multiaddr> {
+    multiaddr_with_len(8)
+}
+
+/// Multiaddrs that have textual forms, rendered as strings.
+pub fn multiaddr_string() -> impl Strategy<Value = String> {
+    multiaddr().prop_filter_map("no textual form", |ma| {
+        let s = ma.to_string();
+        Multiaddr::from_str(&s).ok().map(|_| s)
+    })
+}
+
+/// The bytes of valid multiaddrs with a single mutation applied: a byte
+/// flipped, inserted or removed, or the bytes truncated. The result is
+/// usually, but not always, invalid.
+pub fn mutated_bytes() -> impl Strategy<Value = Vec<u8>> {
+    (multiaddr_with_len(4), 0..4u8, any::<Index>(), any::<u8>()).prop_map(|(ma, kind, i, b)| {
+        let mut bytes = ma.as_bytes().to_vec();
+        if bytes.is_empty() {
+            bytes.push(b);
+            return bytes;
+        }
+        let i = i.index(bytes.len());
+        match kind {
+            0 => bytes[i] ^= b | 1,
+            1 => bytes.insert(i, b),
+            2 => {
+                bytes.remove(i);
+            }
+            _ => bytes.truncate(i),
+        }
+        bytes
+    })
+}
+
+/// Textual forms of valid multiaddrs with a single character replaced,
+/// inserted or removed, or a segment dropped.
+pub fn mutated_string() -> impl Strategy<Value = String> {
+    let c = select(vec!['/', '0', '9', 'a', 'f', ':', '.', '-']);
+    (multiaddr_string(), 0..4u8, any::<Index>(), c).prop_map(|(s, kind, i, c)| {
+        let mut chars: Vec<char> = s.chars().collect();
+        if chars.is_empty() {
+            return c.to_string();
+        }
+        let i = i.index(chars.len());
+        match kind {
+            0 => chars[i] = c,
+            1 => chars.insert(i, c),
+            2 => {
+                chars.remove(i);
+            }
+            _ => {
+                let segs: Vec<&str> = s.split('/').collect();
+                let drop = 1 + i % (segs.len() - 1).max(1);
+                return segs.iter()
+                           .enumerate()
+                           .filter(|&(j, _)| j != drop)
+                           .map(|(_, seg)| *seg)
+                           .collect::<Vec<_>>()
+                           .join("/");
+            }
+        }
+        chars.into_iter().collect()
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{multiaddr, mutated_bytes, mutated_string};
+    use proptest::prelude::*;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    proptest! {
+        #[test]
+        fn test_bytes_roundtrip(ma in multiaddr()) {
+            prop_assert_eq!(Multiaddr::from_bytes(ma.as_bytes().to_vec()).unwrap(), ma);
+        }
+
+        #[test]
+        fn test_mutations_dont_panic(b in mutated_bytes(), s in mutated_string()) {
+            let _ = Multiaddr::from_bytes(b);
+            let _ = Multiaddr::from_str(&s);
+        }
+    }
+}
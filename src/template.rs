@@ -0,0 +1,149 @@
+use std::fmt;
+use std::str::FromStr;
+
+use {Maddr, Multiaddr, ParseError, ParseOptions, ParseResult, Protocol, Registry, Size};
+use registry::Proto;
+
+/// A multiaddr whose addresses may be left open with `*`, such as
+/// "/ip4/*/tcp/80", for matching concrete multiaddrs in configuration and
+/// tests, or for filling in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    // A single component, which must appear as it is
+    Literal(Multiaddr),
+    // A protocol with any address
+    Wildcard(Protocol),
+}
+
+impl Template {
+    /// Checks whether `ma` has the same protocols as the template, with the
+    /// same addresses where the template gives them.
+    pub fn matches(&self, ma: &Maddr) -> bool {
+        let components = ma.split();
+        components.len() == self.parts.len() &&
+            components.iter().zip(&self.parts).all(|(c, part)| {
+                match *part {
+                    Part::Literal(ref lit) => c == lit,
+                    Part::Wildcard(p) => c.iter().next().map(|(q, _)| q) == Some(p),
+                }
+            })
+    }
+
+    /// Returns the number of wildcards.
+    pub fn wildcards(&self) -> usize {
+        self.parts.iter().filter(|part| match **part {
+            Part::Wildcard(_) => true,
+            Part::Literal(_) => false,
+        }).count()
+    }
+
+    /// Substitutes `values`, in their textual form, for the wildcards in
+    /// order, e.g. filling "/ip4/*/tcp/*" with `["1.2.3.4", "80"]`.
+    pub fn fill(&self, values: &[&str]) -> ParseResult<Multiaddr> {
+        if values.len() != self.wildcards() {
+            return Err(ParseError::other(format!("Template {} has {} wildcards, but {} values were given",
+                                                 self, self.wildcards(), values.len())));
+        }
+        let mut values = values.iter();
+        let mut s = String::new();
+        for part in &self.parts {
+            match *part {
+                Part::Literal(ref lit) => s.push_str(&lit.to_string()),
+                Part::Wildcard(p) => s.push_str(&format!("/{}/{}", p, values.next().unwrap())),
+            }
+        }
+        Multiaddr::from_str(&s)
+    }
+}
+
+impl FromStr for Template {
+    type Err = ParseError;
+    fn from_str(s: &str) -> ParseResult<Template> {
+        let registry = Registry::new();
+        let mut segs = s.trim_end_matches('/').split('/');
+        if segs.next() != Some("") {
+            return Err(ParseError::other(format!("Template must begin with '/'")));
+        }
+
+        let mut parts = Vec::new();
+        while let Some(seg) = segs.next() {
+            let p = match registry.lookup_name_with(seg, &ParseOptions::default()) {
+                Some(Proto::Builtin(p)) => p,
+                _ => return Err(ParseError::invalid_code(format!("Invalid protocol: {}", seg))),
+            };
+            if p.size() == Size::Fixed(0) {
+                parts.push(Part::Literal(try!(Multiaddr::from_str(&format!("/{}", p)))));
+                continue;
+            }
+            match segs.next() {
+                Some("*") => parts.push(Part::Wildcard(p)),
+                Some(addr) => {
+                    let lit = try!(Multiaddr::from_str(&format!("/{}/{}", p, addr)));
+                    parts.push(Part::Literal(lit));
+                }
+                None => {
+                    return Err(ParseError::invalid_address(format!("Address not found for protocol {}", p)));
+                }
+            }
+        }
+        Ok(Template { parts: parts })
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for part in &self.parts {
+            try!(match *part {
+                Part::Literal(ref lit) => write!(f, "{}", lit),
+                Part::Wildcard(p) => write!(f, "/{}/*", p),
+            });
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Template;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    fn ma(s: &str) -> Multiaddr {
+        Multiaddr::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_matches() {
+        let t = Template::from_str("/ip4/*/tcp/80").unwrap();
+        assert_eq!(t.to_string(), "/ip4/*/tcp/80");
+        assert!(t.matches(&ma("/ip4/1.2.3.4/tcp/80")));
+        assert!(t.matches(&ma("/ip4/10.0.0.1/tcp/80")));
+        assert!(!t.matches(&ma("/ip4/1.2.3.4/tcp/81")));
+        assert!(!t.matches(&ma("/ip6/::1/tcp/80")));
+        assert!(!t.matches(&ma("/ip4/1.2.3.4/tcp/80/ws")));
+
+        let t = Template::from_str("/ip6/*/udp/*/quic").unwrap();
+        assert!(t.matches(&ma("/ip6/::1/udp/4001/quic")));
+        assert!(!t.matches(&ma("/ip6/::1/udp/4001")));
+
+        assert!(Template::from_str("/ip4/*/tcp").is_err());
+        assert!(Template::from_str("/ip4/1.2.3/tcp/*").is_err());
+        assert!(Template::from_str("/foo/*").is_err());
+    }
+
+    #[test]
+    fn test_fill() {
+        let t = Template::from_str("/ip4/*/tcp/*/ws").unwrap();
+        assert_eq!(t.wildcards(), 2);
+        assert_eq!(t.fill(&["1.2.3.4", "80"]).unwrap(), ma("/ip4/1.2.3.4/tcp/80/ws"));
+        assert!(t.fill(&["1.2.3.4"]).is_err());
+        assert!(t.fill(&["1.2.3.4", "99999"]).is_err());
+        assert_eq!(Template::from_str("/ip4/1.2.3.4").unwrap().fill(&[]).unwrap(), ma("/ip4/1.2.3.4"));
+    }
+}
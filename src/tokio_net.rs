@@ -0,0 +1,155 @@
+//! The tokio counterparts of the `net` helpers, enabled by the `async`
+//! feature and re-exported from `net`.
+
+use std::future::{self, Future};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::vec;
+use tokio::net::{lookup_host, TcpListener, TcpStream, UdpSocket};
+
+use net::{self, DialTarget, Listener};
+use {Maddr, Multiaddr, Protocol};
+
+/// A tokio socket listening on a multiaddr.
+#[derive(Debug)]
+pub enum AsyncListener {
+    Tcp(TcpListener),
+    Udp(UdpSocket),
+}
+
+impl AsyncListener {
+    /// Returns the multiaddr the socket is bound to, with the actual port
+    /// if it was bound to port 0.
+    pub fn local_multiaddr(&self) -> io::Result<Multiaddr> {
+        match *self {
+            AsyncListener::Tcp(ref l) => l.local_addr().map(|a| net::tcp_multiaddr(&a)),
+            AsyncListener::Udp(ref s) => s.local_addr().map(|a| net::udp_multiaddr(&a)),
+        }
+    }
+}
+
+/// Binds a socket like `net::listen` and registers it with tokio. This must
+/// be called from within a tokio runtime.
+pub fn listen_async(ma: &Maddr) -> io::Result<AsyncListener> {
+    match try!(net::listen(ma)) {
+        Listener::Tcp(l) => {
+            try!(l.set_nonblocking(true));
+            TcpListener::from_std(l).map(AsyncListener::Tcp)
+        }
+        Listener::Udp(s) => {
+            try!(s.set_nonblocking(true));
+            UdpSocket::from_std(s).map(AsyncListener::Udp)
+        }
+    }
+}
+
+/// The future returned by `dial_async`.
+pub type Dial = Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>;
+
+/// Connects to the same multiaddrs as `net::dial`, resolving names with
+/// tokio's resolver.
+pub fn dial_async(ma: &Maddr) -> Dial {
+    match net::dial_target(ma) {
+        Ok(DialTarget::Addr(addr)) => Box::pin(TcpStream::connect(addr)),
+        Ok(DialTarget::Name(proto, name, port)) => Box::pin(Connect {
+            lookup: Some(Box::pin(lookup_host((name, port)))),
+            proto: proto,
+            addrs: Vec::new().into_iter(),
+            connecting: None,
+            last_error: None,
+        }),
+        Err(e) => Box::pin(future::ready(Err(e))),
+    }
+}
+
+/// Returns the multiaddr of the local end of a tokio tcp stream.
+pub fn local_multiaddr(stream: &TcpStream) -> io::Result<Multiaddr> {
+    stream.local_addr().map(|a| net::tcp_multiaddr(&a))
+}
+
+/// Returns the multiaddr of the remote end of a tokio tcp stream.
+pub fn peer_multiaddr(stream: &TcpStream) -> io::Result<Multiaddr> {
+    stream.peer_addr().map(|a| net::tcp_multiaddr(&a))
+}
+
+// Resolves a name, then tries each of its addresses in turn
+struct Connect<F> {
+    lookup: Option<Pin<Box<F>>>,
+    proto: Protocol,
+    addrs: vec::IntoIter<SocketAddr>,
+    connecting: Option<Dial>,
+    last_error: Option<io::Error>,
+}
+
+impl<F, I> Future for Connect<F>
+    where F: Future<Output = io::Result<I>>,
+          I: Iterator<Item = SocketAddr>
+{
+    type Output = io::Result<TcpStream>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some(mut lookup) = this.lookup.take() {
+            match lookup.as_mut().poll(cx) {
+                Poll::Pending => {
+                    this.lookup = Some(lookup);
+                    return Poll::Pending;
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(addrs)) => {
+                    let proto = this.proto;
+                    let addrs: Vec<_> = addrs.filter(|a| net::family_matches(proto, a)).collect();
+                    this.addrs = addrs.into_iter();
+                }
+            }
+        }
+
+        loop {
+            if let Some(ref mut connecting) = this.connecting {
+                match connecting.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(stream)) => return Poll::Ready(Ok(stream)),
+                    Poll::Ready(Err(e)) => this.last_error = Some(e),
+                }
+            }
+            match this.addrs.next() {
+                Some(addr) => this.connecting = Some(Box::pin(TcpStream::connect(addr))),
+                None => {
+                    return Poll::Ready(Err(this.last_error.take().unwrap_or_else(|| {
+                        io::Error::new(io::ErrorKind::NotFound, "name resolved to no addresses")
+                    })));
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{dial_async, listen_async, peer_multiaddr, AsyncListener};
+    use std::io::ErrorKind;
+    use std::str::FromStr;
+    use tokio::runtime::Builder;
+    use Multiaddr;
+
+    #[test]
+    fn test_listen_and_dial() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        let _guard = rt.enter();
+
+        let listener = listen_async(&Multiaddr::from_str("/ip4/127.0.0.1/tcp/0").unwrap()).unwrap();
+        let local = listener.local_multiaddr().unwrap();
+        let stream = rt.block_on(dial_async(&local)).unwrap();
+        assert_eq!(peer_multiaddr(&stream).unwrap(), local);
+        match listener {
+            AsyncListener::Tcp(_) => {}
+            _ => panic!("expected a tcp listener"),
+        }
+
+        let ma = Multiaddr::from_str("/ip4/127.0.0.1/udp/80").unwrap();
+        assert_eq!(rt.block_on(dial_async(&ma)).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
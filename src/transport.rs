@@ -0,0 +1,124 @@
+use std::fmt;
+
+use protocol::Protocol::*;
+use Maddr;
+
+/// The kind of transport a multiaddr is dialed or listened on with, as
+/// returned by `Maddr::transport`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TransportKind {
+    /// Plain tcp
+    TcpIp,
+    /// quic, without webtransport
+    Quic,
+    /// ws or wss, usually over tcp
+    WebSocket,
+    /// webtransport over quic
+    WebTransport,
+    /// webrtc, including the older p2p-webrtc-star
+    WebRtc,
+    /// An onion or onion3 service, reached through Tor
+    Onion,
+    /// An in-process memory transport
+    Memory,
+    /// A p2p-circuit relay, whatever the transport to the relay
+    Relay,
+}
+
+impl TransportKind {
+    /// A short lowercase name, e.g. "tcp" or "websocket", to use as a
+    /// metrics label.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TransportKind::TcpIp => "tcp",
+            TransportKind::Quic => "quic",
+            TransportKind::WebSocket => "websocket",
+            TransportKind::WebTransport => "webtransport",
+            TransportKind::WebRtc => "webrtc",
+            TransportKind::Onion => "onion",
+            TransportKind::Memory => "memory",
+            TransportKind::Relay => "relay",
+        }
+    }
+}
+
+impl fmt::Display for TransportKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.name())
+    }
+}
+
+impl Maddr {
+    /// Classifies the multiaddr by the transport it would be dialed with,
+    /// the one furthest up the stack: /ip4/1.2.3.4/tcp/443/wss is a
+    /// `WebSocket` and /ip4/1.2.3.4/udp/443/quic/webtransport a
+    /// `WebTransport`. Relayed addresses are always `Relay`.
+    ///
+    /// Returns None if there is no transport, or only one without a kind of
+    /// its own, like plain udp, sctp, dccp, udt or utp.
+    pub fn transport(&self) -> Option<TransportKind> {
+        let mut kind = None;
+        for (p, _) in self.iter() {
+            kind = match p {
+                P2P_CIRCUIT => return Some(TransportKind::Relay),
+                WEBRTC | P2P_WEBRTC_STAR => Some(TransportKind::WebRtc),
+                WEBTRANSPORT => Some(TransportKind::WebTransport),
+                WS | WSS | P2P_WEBSOCKET_STAR => Some(TransportKind::WebSocket),
+                QUIC => Some(TransportKind::Quic),
+                MEMORY => Some(TransportKind::Memory),
+                TCP if kind.is_none() => Some(TransportKind::TcpIp),
+                #[cfg(feature = "proto-onion")]
+                ONION | ONION3 => Some(TransportKind::Onion),
+                _ => kind,
+            };
+        }
+        kind
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::TransportKind;
+    use std::str::FromStr;
+    use Multiaddr;
+
+    fn transport(s: &str) -> Option<TransportKind> {
+        Multiaddr::from_str(s).unwrap().transport()
+    }
+
+    #[test]
+    fn test_transport() {
+        let peer = "QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+        assert_eq!(transport("/ip4/1.2.3.4/tcp/4001"), Some(TransportKind::TcpIp));
+        assert_eq!(transport(&format!("/ip6/::1/tcp/4001/ipfs/{}", peer)),
+                   Some(TransportKind::TcpIp));
+        assert_eq!(transport("/ip4/1.2.3.4/udp/4001/quic"), Some(TransportKind::Quic));
+        assert_eq!(transport("/ip4/1.2.3.4/tcp/443/wss"), Some(TransportKind::WebSocket));
+        assert_eq!(transport("/ip4/1.2.3.4/udp/443/quic/webtransport"),
+                   Some(TransportKind::WebTransport));
+        assert_eq!(transport("/ip4/1.2.3.4/udp/443/webrtc"), Some(TransportKind::WebRtc));
+        assert_eq!(transport("/memory/1234"), Some(TransportKind::Memory));
+        assert_eq!(transport(&format!("/ip4/1.2.3.4/udp/4001/quic/ipfs/{}/p2p-circuit/ipfs/{}",
+                                      peer, peer)),
+                   Some(TransportKind::Relay));
+
+        assert_eq!(transport("/ip4/1.2.3.4"), None);
+        assert_eq!(transport("/ip4/1.2.3.4/udp/53"), None);
+        assert_eq!(transport(&format!("/ipfs/{}", peer)), None);
+        assert_eq!(TransportKind::WebSocket.to_string(), "websocket");
+    }
+
+    #[test]
+    #[cfg(feature = "proto-dns")]
+    fn test_dns_transport() {
+        assert_eq!(transport("/dns4/example.com/tcp/80/ws/p2p-websocket-star"),
+                   Some(TransportKind::WebSocket));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-onion")]
+    fn test_onion_transport() {
+        assert_eq!(transport("/onion/timaq4ygg2iegci7:80"), Some(TransportKind::Onion));
+    }
+}
@@ -0,0 +1,121 @@
+//! Conversions between multiaddrs and URLs, enabled by the `url` feature.
+
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use url::{Host, Url};
+
+use protocol::Protocol::*;
+use {read_ip4, read_ip6, write_component, write_protocol, Maddr, Multiaddr, ParseError,
+     ParseResult};
+
+impl Maddr {
+    /// Returns the URL of an http, https, ws or wss address, such as
+    /// "/dns4/example.com/tcp/8080/wss" or "/ip4/1.2.3.4/http". The address
+    /// must consist of an ip or dns component, optionally a tcp port, and
+    /// the protocol giving the scheme.
+    pub fn to_url(&self) -> Option<Url> {
+        let components: Vec<_> = self.iter().collect();
+        let (host, port, scheme) = match components.len() {
+            3 if components[1].0 == TCP => {
+                (components[0], Some(BigEndian::read_u16(components[1].1)), components[2].0)
+            }
+            2 => (components[0], None, components[1].0),
+            _ => return None,
+        };
+
+        let host = match host {
+            (IP4, addr) => read_ip4(addr).to_string(),
+            (IP6, addr) => format!("[{}]", read_ip6(addr)),
+            #[cfg(feature = "proto-dns")]
+            (DNS, name) | (DNS4, name) | (DNS6, name) => String::from_utf8_lossy(name).into_owned(),
+            _ => return None,
+        };
+        let scheme = match scheme {
+            HTTP | HTTPS | WS | WSS => scheme.to_str(),
+            _ => return None,
+        };
+        let port = port.map_or(String::new(), |port| format!(":{}", port));
+        Url::parse(&format!("{}://{}{}", scheme, host, port)).ok()
+    }
+}
+
+impl Multiaddr {
+    /// Converts an http, https, ws or wss URL into a multiaddr, the host
+    /// becoming an ip4, ip6 or dns4 component followed by the tcp port,
+    /// e.g. "https://example.com" becomes "/dns4/example.com/tcp/443/https".
+    /// URLs with a path other than "/", a query, a fragment or credentials
+    /// have no multiaddr.
+    pub fn from_url(url: &Url) -> ParseResult<Multiaddr> {
+        let scheme = match url.scheme() {
+            "http" => HTTP,
+            "https" => HTTPS,
+            "ws" => WS,
+            "wss" => WSS,
+            s => return Err(ParseError::invalid_code(format!("Unsupported URL scheme: {}", s))),
+        };
+        if url.path() != "/" || url.query().is_some() || url.fragment().is_some() ||
+           !url.username().is_empty() || url.password().is_some() {
+            return Err(ParseError::other(format!("{} has parts a multiaddr can't hold", url)));
+        }
+
+        let mut bytes = Vec::new();
+        match url.host() {
+            Some(Host::Ipv4(ip)) => write_component(IP4, &ip.octets(), &mut bytes),
+            Some(Host::Ipv6(ip)) => write_component(IP6, &ip.octets(), &mut bytes),
+            #[cfg(feature = "proto-dns")]
+            Some(Host::Domain(name)) => write_component(DNS4, name.as_bytes(), &mut bytes),
+            #[cfg(not(feature = "proto-dns"))]
+            Some(Host::Domain(_)) => {
+                return Err(ParseError::invalid_address(format!("{} has a domain name, which needs \
+                                                               the proto-dns feature", url)));
+            }
+            None => return Err(ParseError::invalid_address(format!("{} has no host", url))),
+        }
+        write_protocol(TCP, &mut bytes);
+        // Every supported scheme has a default port
+        bytes.write_u16::<BigEndian>(url.port_or_known_default().unwrap()).unwrap();
+        write_protocol(scheme, &mut bytes);
+        Multiaddr::from_bytes(bytes)
+    }
+}
+
+
+// The examples use dns names
+#[cfg(all(test, feature = "proto-dns"))]
+mod test {
+    use std::str::FromStr;
+    use url::Url;
+    use Multiaddr;
+
+    fn to_url(s: &str) -> Option<String> {
+        Multiaddr::from_str(s).unwrap().to_url().map(|url| url.to_string())
+    }
+
+    fn from_url(s: &str) -> Option<String> {
+        Multiaddr::from_url(&Url::parse(s).unwrap()).ok().map(|ma| ma.to_string())
+    }
+
+    #[test]
+    fn test_to_url() {
+        assert_eq!(to_url("/ip4/1.2.3.4/tcp/8080/http").unwrap(), "http://1.2.3.4:8080/");
+        assert_eq!(to_url("/ip6/::1/tcp/443/wss").unwrap(), "wss://[::1]/");
+        assert_eq!(to_url("/dns4/example.com/https").unwrap(), "https://example.com/");
+        assert_eq!(to_url("/dns/example.com/tcp/80/ws").unwrap(), "ws://example.com/");
+        assert!(to_url("/ip4/1.2.3.4/tcp/80").is_none());
+        assert!(to_url("/ip4/1.2.3.4/udp/80/http").is_none());
+        assert!(to_url("/ip4/1.2.3.4/tcp/80/http/ws").is_none());
+        assert!(to_url("/dnsaddr/example.com/http").is_none());
+    }
+
+    #[test]
+    fn test_from_url() {
+        assert_eq!(from_url("https://example.com").unwrap(), "/dns4/example.com/tcp/443/https");
+        assert_eq!(from_url("http://1.2.3.4:8080/").unwrap(), "/ip4/1.2.3.4/tcp/8080/http");
+        assert_eq!(from_url("ws://[::1]:9000").unwrap(), "/ip6/::1/tcp/9000/ws");
+        assert!(from_url("ftp://example.com").is_none());
+        assert!(from_url("https://example.com/index.html").is_none());
+        assert!(from_url("https://example.com/?q=1").is_none());
+
+        let ma = Multiaddr::from_str("/ip4/1.2.3.4/tcp/8443/wss").unwrap();
+        assert_eq!(Multiaddr::from_url(&ma.to_url().unwrap()).unwrap(), ma);
+    }
+}
@@ -0,0 +1,206 @@
+//! Profiles of the addresses an application accepts, e.g. a gateway that
+//! only forwards to public peers, with an explanation for those it rejects.
+//!
+//! ```ignore
+//! let v = Dialable.and(PublicOnly).and(BrowserSafe);
+//! if let Err(violation) = v.check(&ma) {
+//!     println!("not relaying to {}: {}", ma, violation);
+//! }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use byteorder::{BigEndian, ByteOrder};
+use protocol::Protocol::*;
+use {read_ip4, read_ip6, Maddr};
+
+/// Why a multiaddr was rejected by a `Validator`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub reason: String,
+}
+
+impl Violation {
+    pub fn new(reason: String) -> Violation {
+        Violation { reason: reason }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(&self.reason)
+    }
+}
+
+impl Error for Violation {}
+
+/// A check of the shape of a multiaddr.
+pub trait Validator {
+    fn check(&self, ma: &Maddr) -> Result<(), Violation>;
+
+    /// Accepts what both validators accept.
+    fn and<V: Validator>(self, other: V) -> And<Self, V>
+        where Self: Sized
+    {
+        And(self, other)
+    }
+
+    /// Accepts what either validator accepts. When both reject, the
+    /// violation of `other` is returned.
+    fn or<V: Validator>(self, other: V) -> Or<Self, V>
+        where Self: Sized
+    {
+        Or(self, other)
+    }
+}
+
+/// Closures make quick custom validators.
+impl<F: Fn(&Maddr) -> Result<(), Violation>> Validator for F {
+    fn check(&self, ma: &Maddr) -> Result<(), Violation> {
+        self(ma)
+    }
+}
+
+/// See `Validator::and`.
+#[derive(Copy, Clone, Debug)]
+pub struct And<A, B>(A, B);
+
+impl<A: Validator, B: Validator> Validator for And<A, B> {
+    fn check(&self, ma: &Maddr) -> Result<(), Violation> {
+        self.0.check(ma).and_then(|_| self.1.check(ma))
+    }
+}
+
+/// See `Validator::or`.
+#[derive(Copy, Clone, Debug)]
+pub struct Or<A, B>(A, B);
+
+impl<A: Validator, B: Validator> Validator for Or<A, B> {
+    fn check(&self, ma: &Maddr) -> Result<(), Violation> {
+        self.0.check(ma).or_else(|_| self.1.check(ma))
+    }
+}
+
+/// Accepts addresses that can be dialed as they are: they start with an
+/// ip address or domain name followed by a tcp or udp port. Unspecified
+/// and multicast addresses, which can only be listened on, and port 0 are
+/// rejected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Dialable;
+
+impl Validator for Dialable {
+    fn check(&self, ma: &Maddr) -> Result<(), Violation> {
+        let mut components = ma.iter();
+        match components.next() {
+            Some((IP4, addr)) => {
+                let ip = read_ip4(addr);
+                if ip == Ipv4Addr::new(0, 0, 0, 0) || ip.is_multicast() {
+                    return Err(Violation::new(format!("{} can't be dialed", ip)));
+                }
+            }
+            Some((IP6, addr)) => {
+                let ip = read_ip6(addr);
+                if ip == Ipv6Addr::from([0; 16]) || ip.is_multicast() {
+                    return Err(Violation::new(format!("{} can't be dialed", ip)));
+                }
+            }
+            #[cfg(feature = "proto-dns")]
+            Some((DNS, _)) | Some((DNS4, _)) | Some((DNS6, _)) | Some((DNSADDR, _)) => {}
+            _ => return Err(Violation::new(format!("{} doesn't start with an ip address", ma))),
+        }
+        match components.next() {
+            Some((TCP, addr)) | Some((UDP, addr)) if BigEndian::read_u16(addr) == 0 => {
+                Err(Violation::new(format!("{} has port 0", ma)))
+            }
+            Some((TCP, _)) | Some((UDP, _)) => Ok(()),
+            _ => Err(Violation::new(format!("{} has no tcp or udp port", ma))),
+        }
+    }
+}
+
+/// Accepts addresses that browsers can dial: secure websockets, as /wss or
+/// /tls/ws, and webrtc. Plain websockets are only accepted to the local
+/// host, since browsers refuse them from secure pages elsewhere.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BrowserSafe;
+
+impl Validator for BrowserSafe {
+    fn check(&self, ma: &Maddr) -> Result<(), Violation> {
+        let protocols = ma.protocols();
+        let secure_ws = protocols.windows(2).any(|w| w == [TLS, WS]);
+        if secure_ws || protocols.iter().any(|&p| p == WSS || p == WEBRTC) {
+            return Ok(());
+        }
+        if protocols.contains(&WS) {
+            if ma.is_loopback() {
+                return Ok(());
+            }
+            return Err(Violation::new(format!("{} uses insecure websockets", ma)));
+        }
+        Err(Violation::new(format!("{} has no transport browsers support", ma)))
+    }
+}
+
+/// Accepts addresses whose ip address or domain name is globally routable.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PublicOnly;
+
+impl Validator for PublicOnly {
+    fn check(&self, ma: &Maddr) -> Result<(), Violation> {
+        if ma.is_public() {
+            Ok(())
+        } else {
+            Err(Violation::new(format!("{} is not a public address", ma)))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{BrowserSafe, Dialable, PublicOnly, Validator, Violation};
+    use std::str::FromStr;
+    use {Maddr, Multiaddr};
+
+    fn check<V: Validator>(v: &V, s: &str) -> Result<(), String> {
+        v.check(&Multiaddr::from_str(s).unwrap()).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_profiles() {
+        assert!(check(&Dialable, "/ip4/8.8.8.8/tcp/4001/ws").is_ok());
+        assert_eq!(check(&Dialable, "/ip4/0.0.0.0/tcp/4001"),
+                   Err("0.0.0.0 can't be dialed".into()));
+        assert_eq!(check(&Dialable, "/ip6/::1/udp/0/quic"),
+                   Err("/ip6/::1/udp/0/quic has port 0".into()));
+        assert!(check(&Dialable, "/ip4/8.8.8.8").is_err());
+        assert!(check(&Dialable, "/tcp/80").is_err());
+
+        assert!(check(&BrowserSafe, "/ip4/8.8.8.8/tcp/443/wss").is_ok());
+        assert!(check(&BrowserSafe, "/ip4/8.8.8.8/tcp/443/tls/ws").is_ok());
+        assert!(check(&BrowserSafe, "/ip4/127.0.0.1/tcp/80/ws").is_ok());
+        assert_eq!(check(&BrowserSafe, "/ip4/8.8.8.8/tcp/80/ws"),
+                   Err("/ip4/8.8.8.8/tcp/80/ws uses insecure websockets".into()));
+        assert!(check(&BrowserSafe, "/ip4/8.8.8.8/tcp/4001").is_err());
+
+        assert!(check(&PublicOnly, "/ip4/8.8.8.8/tcp/4001").is_ok());
+        assert!(check(&PublicOnly, "/ip4/192.168.1.1/tcp/4001").is_err());
+    }
+
+    #[test]
+    fn test_combinators() {
+        let v = Dialable.and(PublicOnly);
+        assert!(check(&v, "/ip4/8.8.8.8/tcp/4001").is_ok());
+        assert!(check(&v, "/ip4/10.0.0.1/tcp/4001").is_err());
+        assert!(check(&v, "/ip4/8.8.8.8/tcp/0").is_err());
+
+        let v = BrowserSafe.or(|ma: &Maddr| {
+            if ma.port() == Some(8080) { Ok(()) } else { Err(Violation::new("no".to_string())) }
+        });
+        assert!(check(&v, "/ip4/8.8.8.8/tcp/443/wss").is_ok());
+        assert!(check(&v, "/ip4/8.8.8.8/tcp/8080").is_ok());
+        assert_eq!(check(&v, "/ip4/8.8.8.8/tcp/80"), Err("no".into()));
+    }
+}
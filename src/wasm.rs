@@ -0,0 +1,105 @@
+//! JavaScript bindings, enabled by the `wasm` feature.
+//!
+//! Build with `wasm-pack build -- --features wasm`. The exported class is
+//! named `Multiaddr`:
+//!
+//! ```js
+//! const ma = new Multiaddr("/ip4/127.0.0.1/tcp/4001");
+//! ma.toString();   // "/ip4/127.0.0.1/tcp/4001"
+//! ma.toBytes();    // Uint8Array [4, 127, 0, 0, 1, 6, 15, 161]
+//! ma.components(); // [{name: "ip4", code: 4, value: "127.0.0.1"}, ...]
+//! ```
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+use {address_bytes_to_string, Multiaddr, ParseError, Size};
+
+fn to_js_error(e: ParseError) -> JsValue {
+    JsValue::from_str(&format!("{:?}", e))
+}
+
+#[wasm_bindgen(js_name = Multiaddr)]
+pub struct JsMultiaddr {
+    inner: Multiaddr,
+}
+
+#[wasm_bindgen(js_class = Multiaddr)]
+impl JsMultiaddr {
+    /// Parses the textual form of a multiaddr.
+    #[wasm_bindgen(constructor)]
+    pub fn new(s: &str) -> Result<JsMultiaddr, JsValue> {
+        Multiaddr::from_str(s)
+            .map(|ma| JsMultiaddr { inner: ma })
+            .map_err(to_js_error)
+    }
+
+    /// Validates and wraps the binary form of a multiaddr.
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(b: &[u8]) -> Result<JsMultiaddr, JsValue> {
+        Multiaddr::try_from(b)
+            .map(|ma| JsMultiaddr { inner: ma })
+            .map_err(to_js_error)
+    }
+
+    /// Checks whether `b` is the binary form of a valid multiaddr.
+    #[wasm_bindgen(js_name = isValidBytes)]
+    pub fn is_valid_bytes(b: &[u8]) -> bool {
+        Multiaddr::validate(b).is_ok()
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_string(&self) -> String {
+        self.inner.to_string()
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.as_bytes().to_vec()
+    }
+
+    /// Returns the components of the multiaddr, in order.
+    pub fn components(&self) -> Vec<JsValue> {
+        self.inner
+            .iter()
+            .map(|(p, addr)| {
+                let value = match p.size() {
+                    Size::Fixed(0) => None,
+                    _ => Some(address_bytes_to_string(addr, &p)),
+                };
+                JsValue::from(Component {
+                    name: p.to_str().to_string(),
+                    code: u32::from(p),
+                    value: value,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A single protocol of a multiaddr and its address, if it has one.
+#[wasm_bindgen]
+pub struct Component {
+    name: String,
+    code: u32,
+    value: Option<String>,
+}
+
+#[wasm_bindgen]
+impl Component {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> u32 {
+        self.code
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> Option<String> {
+        self.value.clone()
+    }
+}
@@ -0,0 +1,77 @@
+// Lives in its own test binary so that the counting allocator does not slow
+// down the unit tests
+extern crate rust_multiaddr;
+
+use rust_multiaddr::{Maddr, Multiaddr, Registry};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::str::FromStr;
+
+// Counts the allocations of each thread, to check what parsing allocates
+struct CountingAlloc;
+
+thread_local!(static ALLOCATIONS: Cell<usize> = const { Cell::new(0) });
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+#[test]
+fn test_iter_without_allocating() {
+    let ma = Multiaddr::from_str("/ip6zone/eth0/ip6/fe80::1/tcp/80/ws").unwrap();
+    let before = ALLOCATIONS.with(|n| n.get());
+    assert_eq!(ma.iter().count(), 4);
+    assert_eq!(ma.iter().rev().count(), 4);
+    assert_eq!(ma.iter().size_hint(), (0, Some(ma.as_bytes().len())));
+    assert_eq!(ALLOCATIONS.with(|n| n.get()), before);
+}
+
+#[test]
+fn test_registry_new_without_allocating() {
+    let before = ALLOCATIONS.with(|n| n.get());
+    let registry = Registry::new();
+    assert_eq!(ALLOCATIONS.with(|n| n.get()), before);
+    assert!(registry.parse("/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC").is_ok());
+}
+
+#[test]
+fn test_parse_without_allocating() {
+    // The only allocation is the buffer the address is written into, which
+    // is sized for the text and so has to grow once for a scoped ip6
+    let mut inputs = vec![("/ip4/1.2.3.4/tcp/80", 1),
+                          ("/ip6zone/eth0/ip6/fe80::1/udp/53/quic", 1),
+                          ("/ip6/fe80::1%eth0/tcp/80", 2),
+                          ("/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC", 1),
+                          ("/ipfs/bafzbeigvf25ytwc3akrijfecaotc74udrhcxzh2cx3we5qqnw5vgrei4bm", 1)];
+    if cfg!(feature = "proto-dns") {
+        inputs.push(("/dns4/example.com/tcp/443/wss", 1));
+    }
+    for &(s, allocations) in &inputs {
+        let before = ALLOCATIONS.with(|n| n.get());
+        let ma = Multiaddr::from_str(s).unwrap();
+        // Converting to Bytes can allocate again
+        if !cfg!(feature = "bytes") {
+            assert_eq!(ALLOCATIONS.with(|n| n.get()), before + allocations, "{}", s);
+        }
+
+        let bytes = ma.as_bytes().to_vec();
+        let before = ALLOCATIONS.with(|n| n.get());
+        Maddr::from_bytes(&bytes).unwrap();
+        assert_eq!(ALLOCATIONS.with(|n| n.get()), before, "{}", s);
+        let parsed = Multiaddr::from_bytes(bytes).unwrap();
+        if !cfg!(feature = "bytes") {
+            assert_eq!(ALLOCATIONS.with(|n| n.get()), before, "{}", s);
+        }
+        assert_eq!(parsed, ma);
+    }
+}